@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use clap::{command, Parser, Subcommand, ValueHint};
+use clap::{command, Parser, Subcommand, ValueEnum, ValueHint};
 use clap_complete::Shell;
 
 #[derive(Parser)]
@@ -17,14 +17,38 @@ pub struct BaseCli {
     pub recursive: bool,
     #[arg(global = true, long = "debug", help = "Enable debug output")]
     pub debug: bool,
+    #[arg(
+        global = true,
+        long = "instance",
+        help = "Use this instance instead of the selected one (`default` for the default instance)"
+    )]
+    pub instance: Option<String>,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     #[command(about = "Print Version")]
     Version,
-    #[command(about = "Install/Update OWML (default installs to %APPDATA%/ow-mod-man/OWML)")]
+    #[command(
+        about = "Install/Update OWML for the selected game and install required mods (default installs to %APPDATA%/ow-mod-man/OWML)"
+    )]
     Setup { owml_path: Option<PathBuf> },
+    #[command(about = "Show or switch which game mods are managed for")]
+    Game {
+        #[arg(help = "The game to switch to", value_enum)]
+        game: Option<GameArg>,
+        #[arg(
+            long = "game-path",
+            help = "Where Outer Wilds Alpha, or the game of the selected instance, is installed",
+            value_hint = ValueHint::DirPath
+        )]
+        game_path: Option<PathBuf>,
+    },
+    #[command(about = "Manage instances, separate installs of OWML and the game")]
+    Instance {
+        #[command(subcommand)]
+        command: InstanceCommands,
+    },
     #[command(
         about = "View the current database alert (if there is one)",
         alias = "alerts"
@@ -38,6 +62,18 @@ pub enum Commands {
             long = "dry-run"
         )]
         dry: bool,
+        #[arg(
+            help = "Show the release notes of each update before updating",
+            short = 'c',
+            long = "changelog"
+        )]
+        changelog: bool,
+        #[arg(
+            help = "Don't ask before applying updates that add dependencies, conflicts, or pre-patchers",
+            short = 'y',
+            long = "yes"
+        )]
+        yes: bool,
     },
     #[command(
         about = "List local (installed) or remote (in the database) mods",
@@ -46,11 +82,39 @@ pub enum Commands {
     List {
         #[command(subcommand)]
         mod_type: Option<ModListTypes>,
+        #[arg(
+            global = true,
+            short = 'q',
+            long = "query",
+            allow_hyphen_values = true,
+            help = "Filter and sort the list with a search query (ex: \"tag:story sort:downloads -installed\")",
+            value_hint = ValueHint::Other
+        )]
+        query: Option<String>,
+        #[arg(
+            global = true,
+            long = "sizes",
+            help = "Show how much disk space each installed mod uses, and the total"
+        )]
+        sizes: bool,
+        #[arg(
+            short = 'a',
+            long = "addons",
+            help = "Group installed addons under their parent mods, this needs to fetch the database"
+        )]
+        addons: bool,
     },
-    #[command(about = "Search the remote database for mods")]
+    #[command(
+        about = "Search the remote database for mods, supports filters like tag:, author:, has:prerelease, sort: and -installed"
+    )]
     Search {
-        #[arg(help = "The search query to use in the search", value_hint = ValueHint::Other)]
-        query: String,
+        #[arg(
+            help = "The search query to use in the search",
+            required = true,
+            allow_hyphen_values = true,
+            value_hint = ValueHint::Other
+        )]
+        query: Vec<String>,
     },
     #[command(about = "View info about a specific mod")]
     Info {
@@ -94,12 +158,12 @@ pub enum Commands {
         prerelease: bool,
     },
     #[command(
-        about = "Install a mod from a .zip file (-r not supported)",
-        alias = "iz"
+        about = "Install a mod from an archive (.zip, .tar.gz, .tar.zst, .7z) or an unpacked mod folder (-r not supported)",
+        aliases = ["install-zip", "iz"]
     )]
-    InstallZip {
-        #[arg(help = "The path to the zip file containing the mod to install", value_hint = ValueHint::FilePath)]
-        zip_path: PathBuf,
+    InstallFile {
+        #[arg(help = "The path to the archive or folder containing the mod to install", value_hint = ValueHint::AnyPath)]
+        file_path: PathBuf,
     },
     #[command(about = "Install a mod from a URL (-r not supported)", alias = "iu")]
     InstallUrl {
@@ -114,8 +178,57 @@ pub enum Commands {
         #[arg(help = "The unique name of the mod to uninstall", value_hint = ValueHint::Other)]
         unique_name: String,
     },
+    #[command(
+        about = "Uninstall mods that were only installed as dependencies and aren't needed anymore"
+    )]
+    Autoremove {
+        #[arg(
+            help = "Don't uninstall anything, just list mods that would be uninstalled",
+            short = 'd',
+            long = "dry-run"
+        )]
+        dry: bool,
+    },
+    #[command(
+        about = "Switch a mod between its releases and prereleases, installing the other version if needed"
+    )]
+    Channel {
+        #[arg(help = "The unique name of the mod", value_hint = ValueHint::Other)]
+        unique_name: String,
+        #[arg(help = "The channel the mod should follow", value_enum)]
+        channel: ChannelArg,
+    },
+    #[command(about = "List operations performed on mods, oldest first")]
+    History {
+        #[arg(
+            help = "Delete the history and the files kept for undoing",
+            long = "clear"
+        )]
+        clear: bool,
+    },
+    #[command(
+        about = "Undo the last operation that can be undone, or the one with the given ID (see `owmods history`)"
+    )]
+    Undo {
+        #[arg(help = "The ID of the operation to undo", value_hint = ValueHint::Other)]
+        id: Option<u32>,
+        #[arg(
+            short = 'f',
+            long = "force",
+            help = "Undo it even if its mods were changed since, those changes are lost"
+        )]
+        force: bool,
+    },
     #[command(about = "Export enabled mods to stdout as JSON")]
     Export,
+    #[command(about = "Import settings from the old mod manager, its files are left as they are")]
+    Migrate {
+        #[arg(
+            help = "The old manager's folder (defaults to %APPDATA%/OuterWildsModManager)",
+            value_hint = ValueHint::DirPath
+        )]
+        path: Option<PathBuf>,
+    },
     #[command(
         about = "Import mods from a .json file (installs if not there, enables if already installed)"
     )]
@@ -169,6 +282,24 @@ pub enum Commands {
         )]
         port: u16,
     },
+    #[command(about = "Run a daemon that serves JSON-RPC requests to manage mods")]
+    Daemon {
+        #[arg(
+            short = 'p',
+            long = "port",
+            help = "Listen on this localhost TCP port instead of a Unix socket (0 to auto-assign)",
+            value_hint = ValueHint::Other,
+            conflicts_with = "socket"
+        )]
+        port: Option<u16>,
+        #[arg(
+            short = 's',
+            long = "socket",
+            help = "Path to the Unix socket to listen on (defaults to daemon.sock in the app directory)",
+            value_hint = ValueHint::FilePath
+        )]
+        socket: Option<PathBuf>,
+    },
     #[command(about = "Quickly open something")]
     Open {
         #[arg(help = "db, owml, owml_docs, website, or a mod's unique name", value_hint = ValueHint::Other)]
@@ -189,6 +320,16 @@ pub enum Commands {
     },
     #[command(about = "Clear which mod warnings were already shown")]
     ClearWarnings,
+    #[command(about = "Tools for mod authors")]
+    Dev {
+        #[command(subcommand)]
+        command: DevCommands,
+    },
+    #[command(about = "Build and serve mod databases from a folder of mod zips")]
+    Db {
+        #[command(subcommand)]
+        command: DbCommands,
+    },
     #[command(about = "Generate auto completions for the given shell")]
     GenerateCompletions {
         #[arg(
@@ -199,6 +340,18 @@ pub enum Commands {
     },
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+pub enum GameArg {
+    OuterWilds,
+    Alpha,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ChannelArg {
+    Stable,
+    Prerelease,
+}
+
 #[derive(Subcommand)]
 pub enum ModListTypes {
     #[command(about = "Show the mods that are currently installed")]
@@ -206,3 +359,116 @@ pub enum ModListTypes {
     #[command(about = "Show all mods in the database (may want to use grep/find with this!)")]
     Remote,
 }
+
+#[derive(Subcommand)]
+pub enum DevCommands {
+    #[command(
+        about = "Validate a mod folder and package it into a zip (exits with 2 if problems are found)"
+    )]
+    Pack {
+        #[arg(help = "The folder containing the mod's manifest.json", value_hint = ValueHint::DirPath)]
+        folder: PathBuf,
+        #[arg(
+            short = 'o',
+            long = "output",
+            help = "Where to write the zip (defaults to <uniqueName>.zip in the current directory)",
+            value_hint = ValueHint::FilePath
+        )]
+        output: Option<PathBuf>,
+        #[arg(long = "check", help = "Only validate the mod, don't write a zip")]
+        check: bool,
+        #[arg(long = "strict", help = "Treat warnings as errors")]
+        strict: bool,
+    },
+    #[command(
+        about = "Link a mod folder (like your build output) into OWML's Mods folder so changes show up without copying"
+    )]
+    Link {
+        #[arg(help = "The folder containing the mod's manifest.json", value_hint = ValueHint::DirPath)]
+        folder: PathBuf,
+    },
+    #[command(
+        about = "Remove a linked mod, restoring the previously installed copy if there was one"
+    )]
+    Unlink {
+        #[arg(help = "The unique name of the linked mod", value_hint = ValueHint::Other)]
+        unique_name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum InstanceCommands {
+    #[command(about = "List instances", alias = "ls")]
+    List,
+    #[command(about = "Add an instance")]
+    Add {
+        #[arg(help = "The name of the instance")]
+        name: String,
+        #[arg(help = "Where OWML is (or will be) installed for this instance", value_hint = ValueHint::DirPath)]
+        owml_path: PathBuf,
+        #[arg(
+            long = "game-path",
+            help = "Where the game is installed (defaults to the game path in OWML's config)",
+            value_hint = ValueHint::DirPath
+        )]
+        game_path: Option<PathBuf>,
+        #[arg(
+            long = "runner",
+            help = "A program to run OWML and pre-patchers with, like wine"
+        )]
+        runner: Option<String>,
+        #[arg(long = "game", help = "The game this instance is for", value_enum)]
+        game: Option<GameArg>,
+    },
+    #[command(
+        about = "Remove an instance, its OWML install and mods are kept",
+        alias = "rm"
+    )]
+    Remove {
+        #[arg(help = "The name of the instance")]
+        name: String,
+    },
+    #[command(
+        about = "Select the instance to use from now on (`default` for the default instance)"
+    )]
+    Use {
+        #[arg(help = "The name of the instance")]
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DbCommands {
+    #[command(about = "Build a database.json from a folder of mod zips")]
+    Build {
+        #[arg(help = "The folder containing the mod zips", value_hint = ValueHint::DirPath)]
+        dir: PathBuf,
+        #[arg(
+            long = "base-url",
+            help = "The URL the zips will be hosted at, each mod's download URL is this plus the zip's name",
+            value_hint = ValueHint::Url
+        )]
+        base_url: String,
+        #[arg(
+            short = 'o',
+            long = "output",
+            help = "Where to write the database (defaults to database.json in the folder)",
+            value_hint = ValueHint::FilePath
+        )]
+        output: Option<PathBuf>,
+    },
+    #[command(
+        about = "Serve a database built from a folder of mod zips on localhost, for testing or private registries"
+    )]
+    Serve {
+        #[arg(help = "The folder containing the mod zips", value_hint = ValueHint::DirPath)]
+        dir: PathBuf,
+        #[arg(
+            short = 'p',
+            long = "port",
+            help = "The port to listen on (defaults to a random free port)",
+            default_value_t = 0
+        )]
+        port: u16,
+    },
+}
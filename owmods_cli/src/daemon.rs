@@ -0,0 +1,484 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use owmods_core::{
+    config::Config,
+    db::{LocalDatabase, RemoteDatabase},
+    download::install_mod_from_db,
+    game::launch_game,
+    history::{History, Operation},
+    patcher::Patches,
+    search::SearchQuery,
+    socket::{LogServer, SocketMessage},
+    toggle::toggle_mod,
+    updates::{check_mod_needs_update, update_all},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpListener,
+    sync::{broadcast, mpsc, Mutex},
+};
+
+const JSONRPC_VERSION: &str = "2.0";
+
+/// The front-end operations are recorded in the history as
+const FRONT_END: &str = "daemon";
+
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+const OPERATION_FAILED: i32 = -32000;
+
+/// Where the daemon should listen for clients
+pub enum DaemonAddress {
+    /// Listen on localhost with the given port, 0 to auto-assign
+    Tcp(u16),
+    /// Listen on a Unix socket at the given path
+    #[cfg(unix)]
+    Unix(std::path::PathBuf),
+}
+
+struct DaemonState {
+    config: Config,
+    local_db: LocalDatabase,
+    remote_db: RemoteDatabase,
+    log_port: u16,
+}
+
+impl DaemonState {
+    fn refresh_local(&mut self) -> Result<()> {
+        self.local_db = LocalDatabase::fetch(self.config.get_owml_path())?;
+        self.local_db.validate_updates(&self.remote_db);
+        Ok(())
+    }
+
+    async fn refresh(&mut self) -> Result<()> {
+        let instance_override = self.config.instance_override.take();
+        self.config = Config::get(Some(self.config.path.clone()))?;
+        // Keep using the instance passed with --instance
+        self.config.instance_override = instance_override;
+        self.remote_db = RemoteDatabase::fetch_from_config(&self.config).await?;
+        self.refresh_local()
+    }
+}
+
+type SharedState = Arc<Mutex<DaemonState>>;
+type LogSender = broadcast::Sender<SocketMessage>;
+
+#[derive(Deserialize)]
+struct RpcRequest {
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct RpcError {
+    code: i32,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i32, message: String) -> Self {
+        Self { code, message }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(why: anyhow::Error) -> Self {
+        Self::new(OPERATION_FAILED, format!("{why:?}"))
+    }
+}
+
+impl From<serde_json::Error> for RpcError {
+    fn from(why: serde_json::Error) -> Self {
+        Self::new(OPERATION_FAILED, why.to_string())
+    }
+}
+
+#[derive(Serialize)]
+struct RpcResponse {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    fn new(id: Value, res: Result<Value, RpcError>) -> Self {
+        let (result, error) = match res {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            jsonrpc: JSONRPC_VERSION,
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchParams {
+    query: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InstallParams {
+    unique_name: String,
+    #[serde(default)]
+    recursive: bool,
+    #[serde(default)]
+    prerelease: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateParams {
+    #[serde(default)]
+    dry: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ToggleParams {
+    unique_name: String,
+    enabled: bool,
+    #[serde(default)]
+    recursive: bool,
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    // Allow omitting params entirely for methods where every param has a default
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+async fn handle_request(
+    method: &str,
+    params: Value,
+    state: &SharedState,
+) -> Result<Value, RpcError> {
+    // Holding the lock for the entire request is what serializes operations between clients
+    let mut state = state.lock().await;
+    match method {
+        "refresh" => {
+            state.refresh().await?;
+            Ok(Value::Bool(true))
+        }
+        "list" => Ok(serde_json::to_value(
+            state.local_db.all().collect::<Vec<_>>(),
+        )?),
+        "search" => {
+            let params: SearchParams = parse_params(params)?;
+            let query = SearchQuery::parse(&params.query);
+            Ok(serde_json::to_value(
+                state.remote_db.query(&query, Some(&state.local_db)),
+            )?)
+        }
+        "install" => {
+            let params: InstallParams = parse_params(params)?;
+            let mut record = History::from_config(&state.config).start(
+                Operation::Install,
+                FRONT_END,
+                &state.local_db,
+            );
+            record.preserve(&params.unique_name)?;
+            install_mod_from_db(
+                &params.unique_name,
+                &state.config,
+                &state.remote_db,
+                &state.local_db,
+                params.recursive,
+                params.prerelease,
+            )
+            .await?;
+            state.refresh_local()?;
+            record.finish(&state.local_db)?;
+            Ok(Value::Bool(true))
+        }
+        "update" => {
+            let params: UpdateParams = parse_params(params)?;
+            let mut record = History::from_config(&state.config).start(
+                Operation::Update,
+                FRONT_END,
+                &state.local_db,
+            );
+            if !params.dry {
+                for local_mod in state.local_db.valid() {
+                    if check_mod_needs_update(local_mod, &state.remote_db).0 {
+                        record.preserve(&local_mod.manifest.unique_name)?;
+                    }
+                }
+            }
+            let updated =
+                update_all(&state.config, &state.local_db, &state.remote_db, params.dry).await?;
+            state.refresh_local()?;
+            record.finish(&state.local_db)?;
+            Ok(Value::Bool(updated))
+        }
+        "toggle" => {
+            let params: ToggleParams = parse_params(params)?;
+            let operation = if params.enabled {
+                Operation::Enable
+            } else {
+                Operation::Disable
+            };
+            let record =
+                History::from_config(&state.config).start(operation, FRONT_END, &state.local_db);
+            let show_warnings_for = toggle_mod(
+                &params.unique_name,
+                &state.local_db,
+                params.enabled,
+                params.recursive,
+            )?;
+            state.refresh_local()?;
+            record.finish(&state.local_db)?;
+            let unpatched = Patches::from_config(&state.config)
+                .unpatch_inactive(&state.config, &state.local_db)
+                .await?;
+            let show_warnings_for: Vec<String> = show_warnings_for
+                .into_iter()
+                .filter(|name| !unpatched.iter().any(|p| &p.name == name))
+                .collect();
+            Ok(serde_json::to_value(show_warnings_for)?)
+        }
+        "run" => {
+            let config = state.config.clone();
+            let port = state.log_port;
+            Patches::from_config(&config)
+                .sync(&config, &state.local_db)
+                .await?;
+            tokio::spawn(async move {
+                if let Err(why) = launch_game(&config, false, Some(&port)).await {
+                    error!("Couldn't Launch Game: {:?}", why);
+                }
+            });
+            Ok(json!({ "port": port }))
+        }
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Method {method} not found"),
+        )),
+    }
+}
+
+fn log_notification(msg: &SocketMessage) -> Value {
+    json!({
+        "jsonrpc": JSONRPC_VERSION,
+        "method": "log",
+        "params": msg,
+    })
+}
+
+async fn handle_connection<S>(stream: S, state: SharedState, log_tx: LogSender)
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let (out_tx, mut out_rx) = mpsc::channel::<String>(64);
+
+    // Responses and log notifications can interleave, so all writes go through one task
+    tokio::spawn(async move {
+        while let Some(line) = out_rx.recv().await {
+            let res = async {
+                writer.write_all(line.as_bytes()).await?;
+                writer.write_all(b"\n").await?;
+                writer.flush().await
+            }
+            .await;
+            if res.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<RpcRequest>(&line) {
+            Ok(request) if request.method == "logs.subscribe" => {
+                let mut log_rx = log_tx.subscribe();
+                let out_tx = out_tx.clone();
+                tokio::spawn(async move {
+                    while let Ok(msg) = log_rx.recv().await {
+                        let notification = log_notification(&msg).to_string();
+                        if out_tx.send(notification).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+                request
+                    .id
+                    .map(|id| RpcResponse::new(id, Ok(Value::Bool(true))))
+            }
+            Ok(request) => {
+                let res = handle_request(&request.method, request.params, &state).await;
+                if let Err(why) = &res {
+                    error!("{} failed: {}", request.method, why.message);
+                }
+                // Requests without an id are notifications and don't get a response
+                request.id.map(|id| RpcResponse::new(id, res))
+            }
+            Err(why) => Some(RpcResponse::new(
+                Value::Null,
+                Err(RpcError::new(PARSE_ERROR, why.to_string())),
+            )),
+        };
+        if let Some(response) = response {
+            match serde_json::to_string(&response) {
+                Ok(response) => {
+                    if out_tx.send(response).await.is_err() {
+                        break;
+                    }
+                }
+                Err(why) => error!("Couldn't Serialize Response: {:?}", why),
+            }
+        }
+    }
+}
+
+async fn serve_tcp(listener: TcpListener, state: SharedState, log_tx: LogSender) -> Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(handle_connection(stream, state.clone(), log_tx.clone()));
+    }
+}
+
+/// Start the daemon, this holds the databases and a log server and serves JSON-RPC requests until killed.
+pub async fn run_daemon(config: Config, address: DaemonAddress) -> Result<()> {
+    let remote_db = RemoteDatabase::fetch_from_config(&config).await?;
+    let mut local_db = LocalDatabase::fetch(config.get_owml_path())?;
+    local_db.validate_updates(&remote_db);
+
+    let log_server = LogServer::new(0).await?;
+    let log_port = log_server.port;
+    let (log_tx, _) = broadcast::channel(256);
+    let (game_tx, mut game_rx) = mpsc::channel(32);
+
+    let forward_tx = log_tx.clone();
+    tokio::spawn(async move {
+        while let Some(msg) = game_rx.recv().await {
+            // No subscribers is fine, the message is just dropped
+            forward_tx.send(msg).ok();
+        }
+    });
+    tokio::spawn(async move {
+        if let Err(why) = log_server.listen(game_tx, false).await {
+            error!("Log Server Stopped: {:?}", why);
+        }
+    });
+
+    let state: SharedState = Arc::new(Mutex::new(DaemonState {
+        config,
+        local_db,
+        remote_db,
+        log_port,
+    }));
+
+    match address {
+        DaemonAddress::Tcp(port) => {
+            let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+            info!(
+                "Daemon listening on 127.0.0.1:{}",
+                listener.local_addr()?.port()
+            );
+            serve_tcp(listener, state, log_tx).await
+        }
+        #[cfg(unix)]
+        DaemonAddress::Unix(path) => {
+            if path.exists() {
+                if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+                    return Err(anyhow!(
+                        "Another daemon is already listening on {}",
+                        path.display()
+                    ));
+                }
+                // Leftover from a daemon that didn't shut down cleanly
+                std::fs::remove_file(&path)?;
+            }
+            let listener = tokio::net::UnixListener::bind(&path)?;
+            info!("Daemon listening on {}", path.display());
+            loop {
+                let (stream, _) = listener.accept().await?;
+                tokio::spawn(handle_connection(stream, state.clone(), log_tx.clone()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::PathBuf;
+
+    use tokio::{io::BufReader, net::TcpStream};
+
+    use super::*;
+
+    async fn start_daemon() -> TcpStream {
+        let state: SharedState = Arc::new(Mutex::new(DaemonState {
+            config: Config::default(Some(PathBuf::from("/test/settings.json"))).unwrap(),
+            local_db: LocalDatabase::default(),
+            remote_db: RemoteDatabase::default(),
+            log_port: 0,
+        }));
+        let (log_tx, _) = broadcast::channel(1);
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(serve_tcp(listener, state, log_tx));
+        TcpStream::connect(("127.0.0.1", port)).await.unwrap()
+    }
+
+    async fn call(
+        lines: &mut tokio::io::Lines<BufReader<tokio::io::ReadHalf<TcpStream>>>,
+        writer: &mut tokio::io::WriteHalf<TcpStream>,
+        request: Value,
+    ) -> Value {
+        writer
+            .write_all(format!("{request}\n").as_bytes())
+            .await
+            .unwrap();
+        let line = lines.next_line().await.unwrap().unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_daemon_round_trip() {
+        let stream = start_daemon().await;
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut lines = BufReader::new(reader).lines();
+
+        let response = call(
+            &mut lines,
+            &mut writer,
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "list" }),
+        )
+        .await;
+        assert_eq!(response["jsonrpc"], JSONRPC_VERSION);
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"], json!([]));
+        assert!(response.get("error").is_none());
+
+        let response = call(
+            &mut lines,
+            &mut writer,
+            json!({ "jsonrpc": "2.0", "id": "two", "method": "explode" }),
+        )
+        .await;
+        assert_eq!(response["id"], "two");
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+        assert!(response.get("result").is_none());
+    }
+}
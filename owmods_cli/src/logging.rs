@@ -177,6 +177,12 @@ pub fn log_mod_validation_errors(local_mod: &UnsafeLocalMod, local_db: &LocalDat
                     name, new_version
                 )
             }
+            ModValidationError::UnparseableVersion(version) => {
+                warn!(
+                    "{}'s version \"{}\" isn't a valid version, so it can't be checked for updates",
+                    name, version
+                )
+            }
         }
     }
 }
@@ -1,4 +1,8 @@
-use std::process;
+use std::{
+    path::{Path, PathBuf},
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::{anyhow, Result};
 use clap::{CommandFactory, Parser};
@@ -6,44 +10,79 @@ use colored::Colorize;
 use log::{error, info, warn, LevelFilter};
 use owmods_core::{
     alerts::fetch_alert,
-    config::Config,
-    db::{LocalDatabase, RemoteDatabase},
+    changelog::fetch_changelog,
+    config::{Config, Game, Instance},
+    constants::{DEFAULT_INSTANCE_NAME, GITHUB_API_URL, OWML_MANIFEST_NAME},
+    db::{build_database, write_database, DatabaseServer, LocalDatabase, RemoteDatabase},
+    dev::{get_pack_name, link_mod, pack_mod, unlink_mod, validate_mod_folder, DiagnosticLevel},
     download::{
-        download_and_install_owml, install_mod_from_db, install_mod_from_url, install_mod_from_zip,
+        download_and_install_owml, install_mod_from_db, install_mod_from_file,
+        install_mod_from_url, install_required_mods,
     },
-    file::get_default_owml_path,
+    file::{format_size, get_app_path, get_default_alpha_owml_path, get_default_owml_path},
+    history::{format_time_ago, History, Operation},
     io::{export_mods, import_mods},
-    mods::local::{LocalMod, UnsafeLocalMod},
+    migrate::{get_old_manager_path, migrate_old_manager},
+    mods::{
+        local::{
+            write_channel, write_install_reason, InstallReason, LocalMod, ReleaseChannel,
+            UnsafeLocalMod,
+        },
+        remote::RemoteMod,
+    },
     open::{open_readme, open_shortcut},
-    remove::{remove_failed_mod, remove_mod},
+    patcher::Patches,
+    remove::{get_orphaned_mods, get_unneeded_deps, remove_failed_mod, remove_mod},
+    search::SearchQuery,
     toggle::toggle_mod,
-    updates::update_all,
+    updates::{check_mod_needs_update, update_all, update_owml, UpdatePlan},
     validate::fix_deps,
 };
 
 mod cli;
+mod daemon;
 mod game;
 mod logging;
 
-use cli::{BaseCli, Commands, ModListTypes};
+use cli::{
+    BaseCli, ChannelArg, Commands, DbCommands, DevCommands, GameArg, InstanceCommands, ModListTypes,
+};
+use daemon::{run_daemon, DaemonAddress};
 use game::{start_game, start_just_logs};
 use logging::{log_mod_validation_errors, show_pre_patcher_warning, Logger};
 
+/// How the CLI identifies itself in the operation history
+const FRONT_END: &str = "cli";
+
 async fn run_from_cli(cli: BaseCli) -> Result<()> {
     let r = cli.recursive;
 
-    let config = Config::get(None)?;
+    let mut config = Config::get(None)?;
+    if let Some(instance) = &cli.instance {
+        config.use_instance(instance)?;
+    }
+    let config = config;
 
     let ran_setup = matches!(
         &cli.command,
-        Commands::Setup { owml_path: _ } | Commands::Version
+        Commands::Setup { owml_path: _ }
+            | Commands::Game { .. }
+            | Commands::Instance { .. }
+            | Commands::Migrate { .. }
+            | Commands::Version
+            | Commands::Dev {
+                command: DevCommands::Pack { .. }
+            }
+            | Commands::Db { .. }
     );
 
     if !config.check_owml() && !ran_setup {
         info!(
-            "Welcome to the Outer Wild Mods CLI! In order to continue you'll need to setup OWML.",
+            "Welcome to the Outer Wild Mods CLI! In order to continue you'll need to setup OWML for {} (instance {}).",
+            game_name(config.get_game()),
+            config.get_instance_name()
         );
-        info!("To do this, run `owmods setup /path/to/owml`. Or, run with no path to auto-install it to {}.", config.owml_path);
+        info!("To do this, run `owmods setup /path/to/owml`. Or, run with no path to auto-install it to {}.", config.get_owml_path());
         info!("This message will display until a valid OWML path is set or OWML is installed");
         return Ok(());
     }
@@ -53,28 +92,157 @@ async fn run_from_cli(cli: BaseCli) -> Result<()> {
             info!(env!("CARGO_PKG_VERSION"));
         }
         Commands::Setup { owml_path } => {
+            let mut config = config.clone();
             if let Some(owml_path) = owml_path {
-                let mut new_config = config.clone();
-                new_config.owml_path = owml_path.to_str().unwrap().to_string();
-                if new_config.check_owml() {
+                config.set_owml_path(owml_path.to_str().unwrap());
+                if config.check_owml() {
                     info!("Path to OWML is valid! Updating config...");
-                    new_config.save()?;
-                    info!("Done! Happy Modding!");
+                    config.save()?;
                 } else {
                     error!(
                         "Error: OWML.Manifest.json, OWML.Launcher.exe, or OWML.DefaultConfig.json Not Found In {}",
                         owml_path.to_str().unwrap()
                     );
+                    return Ok(());
                 }
             } else {
-                let mut config = config.clone();
-                config.owml_path = get_default_owml_path()?.to_str().unwrap().to_string();
-                let db = RemoteDatabase::fetch(&config.database_url).await?;
+                let default_path = match (config.get_instance(), config.game) {
+                    (Some(instance), _) => PathBuf::from(&instance.owml_path),
+                    (None, Game::OuterWilds) => get_default_owml_path()?,
+                    (None, Game::Alpha) => get_default_alpha_owml_path()?,
+                };
+                config.set_owml_path(default_path.to_str().unwrap());
+                let db = RemoteDatabase::fetch_from_config(&config).await?;
                 let owml = db
                     .get_owml()
                     .ok_or_else(|| anyhow!("OWML not found, is the database URL correct?"))?;
                 download_and_install_owml(&config, owml).await?;
-                info!("Done! Happy Modding!");
+                config.save()?;
+            }
+            let db = RemoteDatabase::fetch_from_config(&config).await?;
+            let local_db = LocalDatabase::fetch(config.get_owml_path())?;
+            let record =
+                History::from_config(&config).start(Operation::Install, FRONT_END, &local_db);
+            install_required_mods(&config, &db).await?;
+            record.finish(&LocalDatabase::fetch(config.get_owml_path())?)?;
+            info!("Done! Happy Modding!");
+        }
+        Commands::Game { game, game_path } => {
+            let mut config = config.clone();
+            let game = game.map(to_game);
+            let game_path = game_path.as_ref().map(|p| p.to_str().unwrap().to_string());
+            match config.get_instance_mut() {
+                Some(instance) => {
+                    if let Some(game_path) = game_path {
+                        info!("Game path of {} set to {}", instance.name, game_path);
+                        instance.game_path = game_path;
+                    }
+                    if let Some(game) = game {
+                        instance.game = game;
+                    }
+                }
+                None => {
+                    if let Some(game_path) = game_path {
+                        info!("Outer Wilds Alpha path set to {}", game_path);
+                        config.alpha.game_path = game_path;
+                    }
+                    if let Some(game) = game {
+                        config.game = game;
+                    }
+                }
+            }
+            if game.is_some() {
+                info!("Now managing mods for {}", game_name(config.get_game()));
+                if !config.check_owml() {
+                    info!(
+                        "OWML isn't setup for {} yet, run `owmods setup` to install it",
+                        game_name(config.get_game())
+                    );
+                }
+            } else {
+                info!("Managing mods for {}", game_name(config.get_game()));
+                info!("OWML Path: {}", config.get_owml_path());
+                if let Some(game_path) = config.get_game_path() {
+                    info!("Game Path: {}", game_path);
+                }
+            }
+            config.save()?;
+        }
+        Commands::Instance { command } => {
+            let mut config = config.clone();
+            match command {
+                InstanceCommands::List => {
+                    let selected = config.get_instance_name();
+                    let default_marker = if selected == DEFAULT_INSTANCE_NAME {
+                        " (Selected)"
+                    } else {
+                        ""
+                    };
+                    let default_owml_path = match config.game {
+                        Game::OuterWilds => &config.owml_path,
+                        Game::Alpha => &config.alpha.owml_path,
+                    };
+                    info!(
+                        "- {} [{}] at {}{}",
+                        DEFAULT_INSTANCE_NAME.bold(),
+                        game_name(config.game),
+                        default_owml_path,
+                        default_marker
+                    );
+                    for instance in config.instances.iter() {
+                        info!(
+                            "- {} [{}] at {}{}",
+                            instance.name.bold(),
+                            game_name(instance.game),
+                            instance.owml_path,
+                            if instance.name == selected {
+                                " (Selected)"
+                            } else {
+                                ""
+                            }
+                        );
+                    }
+                }
+                InstanceCommands::Add {
+                    name,
+                    owml_path,
+                    game_path,
+                    runner,
+                    game,
+                } => {
+                    config.add_instance(Instance {
+                        name: name.clone(),
+                        owml_path: owml_path.to_str().unwrap().to_string(),
+                        game_path: game_path
+                            .as_ref()
+                            .map(|p| p.to_str().unwrap().to_string())
+                            .unwrap_or_default(),
+                        game_runner: runner.clone(),
+                        game: game.map(to_game).unwrap_or_default(),
+                    })?;
+                    config.save()?;
+                    info!("Added instance {}", name);
+                    if !owml_path.join(OWML_MANIFEST_NAME).is_file() {
+                        info!(
+                            "OWML isn't installed there yet, run `owmods --instance {} setup` to install it",
+                            name
+                        );
+                    }
+                }
+                InstanceCommands::Remove { name } => {
+                    let instance = config.remove_instance(name)?;
+                    config.save()?;
+                    info!(
+                        "Removed instance {}, OWML and its mods are still in {}",
+                        name, instance.owml_path
+                    );
+                }
+                InstanceCommands::Use { name } => {
+                    config.use_instance(name)?;
+                    config.instance = Some(name.clone()).filter(|n| n != DEFAULT_INSTANCE_NAME);
+                    config.save()?;
+                    info!("Now using instance {}", name);
+                }
             }
         }
         Commands::Alert => {
@@ -92,50 +260,109 @@ async fn run_from_cli(cli: BaseCli) -> Result<()> {
                 info!("No alert");
             };
         }
-        Commands::List { mod_type } => match mod_type {
+        Commands::List {
+            mod_type,
+            query,
+            sizes,
+            addons,
+        } => match mod_type {
             Some(ModListTypes::Local) | None => {
-                let db = LocalDatabase::fetch(&config.owml_path)?;
+                let db = LocalDatabase::fetch(config.get_owml_path())?;
+                // Tags, download counts, and parents come from the remote database,
+                // only fetch it when filtering or grouping addons so listing works offline
+                let remote_db = if query.is_some() || *addons {
+                    Some(RemoteDatabase::fetch_from_config(&config).await?)
+                } else {
+                    None
+                };
+                let mods: Vec<&LocalMod> = if let Some(query) = query {
+                    let query = SearchQuery::parse(query);
+                    db.query(&query, remote_db.as_ref())
+                        .into_iter()
+                        .filter_map(|m| match m {
+                            UnsafeLocalMod::Valid(m) => Some(m),
+                            UnsafeLocalMod::Invalid(_) => None,
+                        })
+                        .collect()
+                } else {
+                    let mut mods: Vec<&LocalMod> = db.valid().collect();
+                    mods.sort_by(|a, b| b.enabled.cmp(&a.enabled));
+                    mods
+                };
                 let mut output = String::new();
                 output += &format!(
                     "Found {} Installed Mods at {}:\n(+): Enabled\n(-): Disabled\n\n",
-                    db.mods.len(),
-                    config.owml_path
+                    mods.len(),
+                    config.get_owml_path()
                 );
-                let mut mods: Vec<&LocalMod> = db.valid().collect();
-                mods.sort_by(|a, b| b.enabled.cmp(&a.enabled));
-                for local_mod in mods.iter() {
+                let mods = group_addons(
+                    mods,
+                    |m| &m.manifest.unique_name,
+                    |m| {
+                        remote_db
+                            .as_ref()
+                            .and_then(|db| db.get_mod(&m.manifest.unique_name))
+                            .and_then(|m| m.parent.as_deref())
+                    },
+                );
+                let mut total_size: u64 = 0;
+                for (local_mod, is_addon) in mods {
+                    let size = if *sizes {
+                        let size = local_mod.get_install_size()?;
+                        total_size += size;
+                        format!(" [{}]", format_size(size))
+                    } else {
+                        String::new()
+                    };
                     output += &format!(
-                        "({}) {} v{} by {} ({})\n",
+                        "{}({}) {} v{} by {} ({}){}\n",
+                        if is_addon { ADDON_PREFIX } else { "" },
                         if local_mod.enabled { "+" } else { "-" },
                         local_mod.manifest.name,
                         local_mod.manifest.version,
                         local_mod.manifest.author,
-                        &local_mod.manifest.unique_name.to_string().bold()
+                        &local_mod.manifest.unique_name.to_string().bold(),
+                        size
                     );
                 }
+                if *sizes {
+                    output += &format!("\nTotal: {}\n", format_size(total_size));
+                }
                 info!("{}", &output);
             }
             Some(ModListTypes::Remote) => {
-                let db = RemoteDatabase::fetch(&config.database_url).await?;
+                let db = RemoteDatabase::fetch_from_config(&config).await?;
+                let local_db = LocalDatabase::fetch(config.get_owml_path())?;
+                let query = SearchQuery::parse(query.as_deref().unwrap_or_default());
+                let mods = db.query(&query, Some(&local_db));
                 let mut output = String::new();
-                output += &format!("Found {} Remote Mods:\n", db.mods.values().len());
-                for remote_mod in db.mods.values() {
+                output += &format!("Found {} Remote Mods:\n", mods.len());
+                let mods = group_addons(mods, |m| &m.unique_name, |m| m.parent.as_deref());
+                for (remote_mod, is_addon) in mods {
                     output += &format!(
-                        "- {} by {} ({})\n",
+                        "{}- {} by {} ({}){}\n",
+                        if is_addon { ADDON_PREFIX } else { "" },
                         remote_mod.name,
                         remote_mod
                             .author_display
                             .as_ref()
                             .unwrap_or(&remote_mod.author),
-                        &remote_mod.unique_name.to_string().bold()
+                        &remote_mod.unique_name.to_string().bold(),
+                        if remote_mod.is_required() {
+                            " (Required)"
+                        } else {
+                            ""
+                        }
                     )
                 }
                 info!("{}", &output);
             }
         },
         Commands::Search { query } => {
-            let db = RemoteDatabase::fetch(&config.database_url).await?;
-            let mods = db.search(query);
+            let db = RemoteDatabase::fetch_from_config(&config).await?;
+            let local_db = LocalDatabase::fetch(config.get_owml_path())?;
+            let query = SearchQuery::parse(&query.join(" "));
+            let mods = db.query(&query, Some(&local_db));
             for remote_mod in mods {
                 info!(
                     "{} v{} by {} ({})",
@@ -147,8 +374,8 @@ async fn run_from_cli(cli: BaseCli) -> Result<()> {
             }
         }
         Commands::Info { unique_name } => {
-            let remote_db = RemoteDatabase::fetch(&config.database_url).await?;
-            let local_db = LocalDatabase::fetch(&config.owml_path)?;
+            let remote_db = RemoteDatabase::fetch_from_config(&config).await?;
+            let local_db = LocalDatabase::fetch(config.get_owml_path())?;
             let local_mod = local_db.get_mod(unique_name);
             let remote_mod = remote_db.get_mod(unique_name);
             let installed = local_mod.is_some();
@@ -173,8 +400,31 @@ async fn run_from_cli(cli: BaseCli) -> Result<()> {
                 if installed {
                     let local_mod = local_mod.unwrap();
                     info!("Installed At: {}", local_mod.mod_path);
+                    info!("Linked: {}", yes_no(local_mod.linked));
                     info!("Enabled: {}", yes_no(local_mod.enabled));
                     info!("Installed Version: {}", local_mod.manifest.version);
+                    info!(
+                        "Installed As: {}",
+                        match local_mod.install_reason {
+                            InstallReason::Explicit => "Explicitly",
+                            InstallReason::Dependency => "A Dependency",
+                        }
+                    );
+                    info!("Channel: {}", channel_name(local_mod.channel));
+                    if local_mod.uses_pre_patcher() {
+                        let patch = Patches::from_config(&config).get(unique_name)?;
+                        info!(
+                            "Patched Game: {}",
+                            match patch {
+                                Some(patch) => format!("Yes (with {})", patch.version),
+                                None => "No".to_string(),
+                            }
+                        );
+                    }
+                    info!(
+                        "Install Size: {}",
+                        format_size(local_mod.get_install_size()?)
+                    );
                     if let Some(owml_version) = &local_mod.manifest.owml_version {
                         info!("Expected OWML Version: {}", owml_version);
                     }
@@ -188,15 +438,39 @@ async fn run_from_cli(cli: BaseCli) -> Result<()> {
                 info!("In Database: {}", yes_no(has_remote));
                 if has_remote {
                     let remote_mod = remote_mod.unwrap();
+                    info!(
+                        "Source: {}",
+                        remote_mod.source.as_deref().unwrap_or("Default Database")
+                    );
                     info!("Description: {}", remote_mod.description);
                     info!("GitHub Repo URL: {}", remote_mod.repo);
                     info!("Downloads: {}", remote_mod.download_count);
+                    if let Ok(Some(size)) = remote_mod.get_download_size().await {
+                        info!("Download Size: {}", format_size(size));
+                    }
                     if let Some(parent) = &remote_mod.parent {
                         info!("Parent Mod: {}", parent);
                     }
+                    let mut addons: Vec<String> = remote_db
+                        .addons(unique_name)
+                        .map(|addon| {
+                            if local_db.get_mod(&addon.unique_name).is_some() {
+                                format!("{} (Installed)", addon.unique_name)
+                            } else {
+                                addon.unique_name.clone()
+                            }
+                        })
+                        .collect();
+                    if !addons.is_empty() {
+                        addons.sort();
+                        info!("Addons: {}", addons.join(", "));
+                    }
                     if let Some(tags) = &remote_mod.tags {
                         info!("Tags: {}", tags.join(", "));
                     }
+                    if remote_mod.is_required() {
+                        info!("Required: Yes");
+                    }
                     info!("Remote Version: {}", remote_mod.version);
                     if let Some(prerelease) = &remote_mod.prerelease {
                         info!(
@@ -212,57 +486,97 @@ async fn run_from_cli(cli: BaseCli) -> Result<()> {
             overwrite,
             prerelease,
         } => {
-            let remote_db = RemoteDatabase::fetch(&config.database_url).await?;
-            let local_db = LocalDatabase::fetch(&config.owml_path)?;
+            let remote_db = RemoteDatabase::fetch_from_config(&config).await?;
+            let local_db = LocalDatabase::fetch(config.get_owml_path())?;
             let local_mod = local_db.get_mod(unique_name);
             let mut flag = true;
 
             if *overwrite && local_mod.is_some() {
                 warn!("Overriding {}", unique_name);
-            } else if let Some(local_mod) = local_db.get_mod(unique_name) {
-                error!(
-                    "{} is already installed at {}, use -o to overwrite",
-                    unique_name, local_mod.mod_path
-                );
+            } else if let Some(local_mod) = local_mod {
+                if local_mod.install_reason == InstallReason::Dependency {
+                    write_install_reason(Path::new(&local_mod.mod_path), InstallReason::Explicit)?;
+                    info!(
+                        "{} is already installed, it's now marked as explicitly installed",
+                        unique_name
+                    );
+                } else {
+                    error!(
+                        "{} is already installed at {}, use -o to overwrite",
+                        unique_name, local_mod.mod_path
+                    );
+                }
                 flag = false;
             }
 
             if flag {
+                let parent = remote_db
+                    .get_mod(unique_name)
+                    .and_then(|m| m.parent.as_ref())
+                    .filter(|p| local_db.get_mod(p).is_none());
+                if let (Some(parent), false) = (parent, r) {
+                    warn!(
+                        "{} is an addon for {}, which isn't installed. Use -r to install it as well",
+                        unique_name, parent
+                    );
+                }
+                let mut record =
+                    History::from_config(&config).start(Operation::Install, FRONT_END, &local_db);
+                record.preserve(unique_name)?;
                 install_mod_from_db(unique_name, &config, &remote_db, &local_db, r, *prerelease)
-                    .await?
+                    .await?;
+                record.finish(&LocalDatabase::fetch(config.get_owml_path())?)?;
             }
         }
-        Commands::InstallZip { zip_path } => {
-            info!("Installing From {}", zip_path.to_str().unwrap());
-            let local_db = LocalDatabase::fetch(&config.owml_path)?;
-            let new_mod = install_mod_from_zip(zip_path, &config, &local_db)?;
+        Commands::InstallFile { file_path } => {
+            info!("Installing From {}", file_path.to_str().unwrap());
+            let local_db = LocalDatabase::fetch(config.get_owml_path())?;
+            let record =
+                History::from_config(&config).start(Operation::Install, FRONT_END, &local_db);
+            let new_mod = install_mod_from_file(file_path, &config, &local_db)?;
+            record.finish(&LocalDatabase::fetch(config.get_owml_path())?)?;
             info!("Installed {}!", new_mod.manifest.name);
         }
         Commands::InstallUrl { url } => {
-            let local_db = LocalDatabase::fetch(&config.owml_path)?;
+            let local_db = LocalDatabase::fetch(config.get_owml_path())?;
             info!("Installing From {}", url);
+            let record =
+                History::from_config(&config).start(Operation::Install, FRONT_END, &local_db);
             let new_mod = install_mod_from_url(url, &config, &local_db).await?;
+            record.finish(&LocalDatabase::fetch(config.get_owml_path())?)?;
             info!("Installed {}!", new_mod.manifest.name);
         }
         Commands::Uninstall { unique_name } => {
-            let db = LocalDatabase::fetch(&config.owml_path)?;
+            let db = LocalDatabase::fetch(config.get_owml_path())?;
             if unique_name == "all" {
                 let mut answer = String::new();
                 warn!("WARNING: This will uninstall ALL MODS. Continue? (yes/no)");
                 std::io::stdin().read_line(&mut answer)?;
                 if answer.trim() == "yes" {
                     info!("Uninstalling all mods...");
+                    let mut record =
+                        History::from_config(&config).start(Operation::Uninstall, FRONT_END, &db);
                     for local_mod in db.all() {
                         info!("Uninstalling {}...", local_mod.get_name());
                         match local_mod {
                             UnsafeLocalMod::Invalid(local_mod) => {
                                 remove_failed_mod(local_mod)?;
                             }
+                            UnsafeLocalMod::Valid(local_mod) if local_mod.linked => {
+                                warn!("Skipping linked mod {}", local_mod.manifest.name);
+                            }
                             UnsafeLocalMod::Valid(local_mod) => {
-                                remove_mod(local_mod, &db, false)?;
+                                record.preserve(&local_mod.manifest.unique_name)?;
+                                let unpatched = unpatch_mods(&config, &[local_mod], &db).await;
+                                for mod_name in remove_mod(local_mod, &db, false)? {
+                                    if !unpatched.contains(&mod_name) {
+                                        show_pre_patcher_warning(&mod_name);
+                                    }
+                                }
                             }
                         }
                     }
+                    record.finish(&LocalDatabase::fetch(config.get_owml_path())?)?;
                     info!("Complete");
                 } else {
                     warn!("Aborting");
@@ -275,9 +589,37 @@ async fn run_from_cli(cli: BaseCli) -> Result<()> {
                         unique_name,
                         if r { " and dependencies" } else { "" }
                     );
+                    let mut record =
+                        History::from_config(&config).start(Operation::Uninstall, FRONT_END, &db);
+                    record.preserve(unique_name)?;
+                    let mut removing = vec![local_mod];
+                    if r {
+                        for dep in get_unneeded_deps(local_mod, &db) {
+                            record.preserve(&dep.manifest.unique_name)?;
+                            removing.push(dep);
+                        }
+                    }
+                    let unpatched = unpatch_mods(&config, &removing, &db).await;
                     let show_warnings_for = remove_mod(local_mod, &db, r)?;
+                    record.finish(&LocalDatabase::fetch(config.get_owml_path())?)?;
                     for mod_name in show_warnings_for {
-                        show_pre_patcher_warning(&mod_name);
+                        if !unpatched.contains(&mod_name) {
+                            show_pre_patcher_warning(&mod_name);
+                        }
+                    }
+                    // Not being able to check for addons shouldn't stop an uninstall
+                    if let Ok(remote_db) = RemoteDatabase::fetch_from_config(&config).await {
+                        let addons: Vec<&str> = db
+                            .addons(unique_name, &remote_db)
+                            .map(|m| m.manifest.name.as_str())
+                            .collect();
+                        if !addons.is_empty() {
+                            warn!(
+                                "The following addons of {} are still installed and won't work without it: {}",
+                                unique_name,
+                                addons.join(", ")
+                            );
+                        }
                     }
                     info!("Done");
                 } else {
@@ -285,33 +627,254 @@ async fn run_from_cli(cli: BaseCli) -> Result<()> {
                 }
             }
         }
+        Commands::Autoremove { dry } => {
+            let db = LocalDatabase::fetch(config.get_owml_path())?;
+            // Without the remote database we can't tell which mods are parents of addons, so only dependencies are checked
+            let remote_db = RemoteDatabase::fetch_from_config(&config).await.ok();
+            let orphans = get_orphaned_mods(&db, remote_db.as_ref());
+            if orphans.is_empty() {
+                info!("No mods to uninstall");
+            } else if *dry {
+                info!("The following mods aren't needed anymore and would be uninstalled:");
+                for local_mod in orphans {
+                    info!(
+                        "- {} ({})",
+                        local_mod.manifest.name, local_mod.manifest.unique_name
+                    );
+                }
+            } else {
+                let mut record =
+                    History::from_config(&config).start(Operation::Uninstall, FRONT_END, &db);
+                let unpatched = unpatch_mods(&config, &orphans, &db).await;
+                for local_mod in orphans {
+                    info!("Uninstalling {}...", local_mod.manifest.name);
+                    record.preserve(&local_mod.manifest.unique_name)?;
+                    for mod_name in remove_mod(local_mod, &db, false)? {
+                        if !unpatched.contains(&mod_name) {
+                            show_pre_patcher_warning(&mod_name);
+                        }
+                    }
+                }
+                record.finish(&LocalDatabase::fetch(config.get_owml_path())?)?;
+                info!("Done");
+            }
+        }
+        Commands::Channel {
+            unique_name,
+            channel,
+        } => {
+            let remote_db = RemoteDatabase::fetch_from_config(&config).await?;
+            let local_db = LocalDatabase::fetch(config.get_owml_path())?;
+            let local_mod = local_db
+                .get_mod(unique_name)
+                .ok_or_else(|| anyhow!("Mod {} is not installed", unique_name))?;
+            let remote_mod = remote_db
+                .get_mod(unique_name)
+                .ok_or_else(|| anyhow!("Mod {} not found in database", unique_name))?;
+            let channel = match channel {
+                ChannelArg::Stable => ReleaseChannel::Stable,
+                ChannelArg::Prerelease => ReleaseChannel::Prerelease,
+            };
+            if channel == ReleaseChannel::Prerelease && remote_mod.prerelease.is_none() {
+                warn!(
+                    "{} doesn't have a prerelease right now, it'll stay on the release until it does",
+                    remote_mod.name
+                );
+            }
+            let (version, url) = remote_mod.get_channel_release(channel);
+            let mod_path = if local_mod.linked || *version == local_mod.manifest.version {
+                local_mod.mod_path.clone()
+            } else {
+                info!(
+                    "Switching {} from {} to {}",
+                    local_mod.manifest.name, local_mod.manifest.version, version
+                );
+                let mut record =
+                    History::from_config(&config).start(Operation::Update, FRONT_END, &local_db);
+                record.preserve(unique_name)?;
+                let new_mod = install_mod_from_url(url, &config, &local_db).await?;
+                record.finish(&LocalDatabase::fetch(config.get_owml_path())?)?;
+                new_mod.mod_path
+            };
+            write_channel(Path::new(&mod_path), channel)?;
+            info!(
+                "{} now follows the {} channel",
+                local_mod.manifest.name,
+                channel_name(channel)
+            );
+        }
+        Commands::History { clear } => {
+            let history = History::from_config(&config);
+            if *clear {
+                history.clear()?;
+                info!("History cleared");
+            } else {
+                let entries = history.entries()?;
+                if entries.is_empty() {
+                    info!("No history yet");
+                }
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                for entry in entries.iter() {
+                    let status = if let Some(undoes) = entry.undoes {
+                        format!(" of #{undoes}")
+                    } else if entries.iter().any(|e| e.undoes == Some(entry.id)) {
+                        " (undone)".to_string()
+                    } else {
+                        String::new()
+                    };
+                    info!(
+                        "{} {}{} {}",
+                        format!("#{}", entry.id).bold(),
+                        entry.operation,
+                        status,
+                        format!(
+                            "({} via {})",
+                            format_time_ago(entry.timestamp, now),
+                            entry.front_end
+                        )
+                        .dimmed()
+                    );
+                    for change in entry.changes.iter() {
+                        info!("  - {}", change);
+                    }
+                }
+            }
+        }
+        Commands::Undo { id, force } => {
+            let history = History::from_config(&config);
+            let local_db = LocalDatabase::fetch(config.get_owml_path())?;
+            let entry = history
+                .undo(*id, &config, &local_db, FRONT_END, *force)
+                .await?;
+            if entry.changes.is_empty() {
+                info!(
+                    "Nothing needed to change to undo #{}",
+                    entry.undoes.unwrap_or(0)
+                );
+            } else {
+                info!("Undid #{}:", entry.undoes.unwrap_or(0));
+                for change in entry.changes.iter() {
+                    info!("  - {}", change);
+                }
+            }
+        }
         Commands::Export => {
-            let local_db = LocalDatabase::fetch(&config.owml_path)?;
+            let local_db = LocalDatabase::fetch(config.get_owml_path())?;
             println!("{}", export_mods(&local_db)?);
         }
         Commands::Import {
             file_path,
             disable_missing,
         } => {
-            let remote_db = RemoteDatabase::fetch(&config.database_url).await?;
-            let local_db = LocalDatabase::fetch(&config.owml_path)?;
+            let remote_db = RemoteDatabase::fetch_from_config(&config).await?;
+            let local_db = LocalDatabase::fetch(config.get_owml_path())?;
+            let record =
+                History::from_config(&config).start(Operation::Import, FRONT_END, &local_db);
             import_mods(&config, &local_db, &remote_db, file_path, *disable_missing).await?;
+            record.finish(&LocalDatabase::fetch(config.get_owml_path())?)?;
         }
-        Commands::Update { dry } => {
-            let remote_db = RemoteDatabase::fetch(&config.database_url).await?;
-            let local_db = LocalDatabase::fetch(&config.owml_path)?;
-            let updated = update_all(&config, &local_db, &remote_db, *dry).await?;
-            if updated {
-                if !*dry {
-                    info!("Update Complete!");
+        Commands::Migrate { path } => {
+            let old_path = match path {
+                Some(path) => path.clone(),
+                None => get_old_manager_path()?,
+            };
+            info!("Migrating from {}...", old_path.display());
+            let mut config = config.clone();
+            let report = migrate_old_manager(&mut config, &old_path, FRONT_END)?;
+            config.save()?;
+            if report.migrated.is_empty() {
+                info!("Nothing new to migrate");
+            } else {
+                info!("Migrated:");
+                for item in report.migrated.iter() {
+                    info!("  - {}", item);
+                }
+            }
+            if !report.skipped.is_empty() {
+                warn!("Couldn't migrate:");
+                for item in report.skipped.iter() {
+                    warn!("  - {}", item);
+                }
+            }
+        }
+        Commands::Update {
+            dry,
+            changelog,
+            yes,
+        } => {
+            let remote_db = RemoteDatabase::fetch_from_config(&config).await?;
+            let local_db = LocalDatabase::fetch(config.get_owml_path())?;
+            let mut updates: Vec<String> = vec![];
+            for local_mod in local_db.valid() {
+                if let (true, Some(remote_mod)) = check_mod_needs_update(local_mod, &remote_db) {
+                    if *changelog {
+                        show_changelog(local_mod, remote_mod).await;
+                    }
+                    updates.push(local_mod.manifest.unique_name.clone());
+                }
+            }
+            if *dry {
+                if !update_all(&config, &local_db, &remote_db, true).await? {
+                    info!("No Updates Available!");
                 }
             } else {
-                info!("No Updates Available!");
+                let owml_updated = update_owml(&config, &remote_db).await?;
+                if updates.is_empty() {
+                    if owml_updated {
+                        info!("Update Complete!");
+                    } else {
+                        info!("No Updates Available!");
+                    }
+                } else {
+                    info!("Downloading {} update(s)...", updates.len());
+                    let plan = UpdatePlan::new(&updates, &local_db, &remote_db).await?;
+                    for impact in plan.impacts.iter() {
+                        info!(
+                            "{}: {} -> {}",
+                            impact.name, impact.old_version, impact.new_version
+                        );
+                        for line in impact.describe() {
+                            info!("  - {}", line);
+                        }
+                    }
+                    let proceed = if plan.needs_confirmation() && !*yes {
+                        let mut answer = String::new();
+                        warn!("Some updates need attention, see above. Continue? (yes/no)");
+                        std::io::stdin().read_line(&mut answer)?;
+                        answer.trim() == "yes"
+                    } else {
+                        true
+                    };
+                    if proceed {
+                        let mut record = History::from_config(&config).start(
+                            Operation::Update,
+                            FRONT_END,
+                            &local_db,
+                        );
+                        for unique_name in updates.iter() {
+                            record.preserve(unique_name)?;
+                        }
+                        plan.apply(&config, &local_db, &remote_db).await?;
+                        record.finish(&LocalDatabase::fetch(config.get_owml_path())?)?;
+                        info!("Update Complete!");
+                    } else {
+                        warn!("Aborting");
+                    }
+                }
             }
         }
         Commands::Enable { unique_name } | Commands::Disable { unique_name } => {
-            let db = LocalDatabase::fetch(&config.owml_path)?;
+            let db = LocalDatabase::fetch(config.get_owml_path())?;
             let enable = matches!(cli.command, Commands::Enable { unique_name: _ });
+            let operation = if enable {
+                Operation::Enable
+            } else {
+                Operation::Disable
+            };
+            let record = History::from_config(&config).start(operation, FRONT_END, &db);
             let mut show_warnings_for: Vec<String> = vec![];
             if unique_name == "*" || unique_name == "all" {
                 for local_mod in db.valid() {
@@ -325,8 +888,15 @@ async fn run_from_cli(cli: BaseCli) -> Result<()> {
             } else {
                 show_warnings_for = toggle_mod(unique_name, &db, enable, r)?;
             }
+            let new_db = LocalDatabase::fetch(config.get_owml_path())?;
+            record.finish(&new_db)?;
+            let unpatched = Patches::from_config(&config)
+                .unpatch_inactive(&config, &new_db)
+                .await?;
             for mod_name in show_warnings_for {
-                show_pre_patcher_warning(&mod_name);
+                if !unpatched.iter().any(|p| p.name == mod_name) {
+                    show_pre_patcher_warning(&mod_name);
+                }
             }
         }
         Commands::LogServer { port } => {
@@ -339,8 +909,8 @@ async fn run_from_cli(cli: BaseCli) -> Result<()> {
             new_window,
         } => {
             info!("Attempting to launch game...");
-            let mut local_db = LocalDatabase::fetch(&config.owml_path)?;
-            let remote_db = RemoteDatabase::fetch(&config.database_url).await;
+            let mut local_db = LocalDatabase::fetch(config.get_owml_path())?;
+            let remote_db = RemoteDatabase::fetch_from_config(&config).await;
             if let Ok(remote_db) = remote_db {
                 local_db.validate_updates(&remote_db);
             }
@@ -360,28 +930,52 @@ async fn run_from_cli(cli: BaseCli) -> Result<()> {
                 warn!("Skipping option --new-window as this is a Windows only flag");
             }
             let port = if no_server { None } else { Some(port) };
+            for mod_name in Patches::from_config(&config)
+                .sync(&config, &local_db)
+                .await?
+            {
+                info!("Patched the game with {}", mod_name);
+            }
             start_game(&local_db, &config, port, *new_window).await?;
         }
+        Commands::Daemon { port, socket } => {
+            let address = match (port, socket) {
+                (Some(port), _) => DaemonAddress::Tcp(*port),
+                #[cfg(unix)]
+                (None, socket) => DaemonAddress::Unix(
+                    socket
+                        .clone()
+                        .unwrap_or(get_app_path()?.join("daemon.sock")),
+                ),
+                #[cfg(not(unix))]
+                (None, Some(_)) => {
+                    return Err(anyhow!("Unix sockets aren't supported on this platform"))
+                }
+                #[cfg(not(unix))]
+                (None, None) => DaemonAddress::Tcp(0),
+            };
+            run_daemon(config.clone(), address).await?;
+        }
         Commands::Open { identifier } => {
             info!("Opening {}", identifier);
-            let local_db = LocalDatabase::fetch(&config.owml_path)?;
+            let local_db = LocalDatabase::fetch(config.get_owml_path())?;
             open_shortcut(identifier, &config, &local_db)?;
         }
         Commands::Readme { unique_name } => {
             info!("Opening README for {}", unique_name);
-            let remote_db = RemoteDatabase::fetch(&config.database_url).await?;
+            let remote_db = RemoteDatabase::fetch_from_config(&config).await?;
             open_readme(unique_name, &remote_db)?;
         }
         Commands::Validate { fix } => {
-            let mut local_db = LocalDatabase::fetch(&config.owml_path)?;
-            let remote_db = RemoteDatabase::fetch(&config.database_url).await?;
+            let mut local_db = LocalDatabase::fetch(config.get_owml_path())?;
+            let remote_db = RemoteDatabase::fetch_from_config(&config).await?;
             local_db.validate_updates(&remote_db);
             if *fix {
                 info!("Trying to fix dependency issues...");
                 for local_mod in local_db.active() {
                     fix_deps(local_mod, &config, &local_db, &remote_db).await?;
                 }
-                local_db = LocalDatabase::fetch(&config.owml_path)?;
+                local_db = LocalDatabase::fetch(config.get_owml_path())?;
                 info!("Done! Checking for other issues...")
             } else {
                 info!("Checking for issues...");
@@ -403,6 +997,92 @@ async fn run_from_cli(cli: BaseCli) -> Result<()> {
             new_config.save()?;
             info!("Warnings Cleared");
         }
+        Commands::Dev { command } => match command {
+            DevCommands::Pack {
+                folder,
+                output,
+                check,
+                strict,
+            } => {
+                let diagnostics = validate_mod_folder(folder)?;
+                let mut errors = 0;
+                for diagnostic in diagnostics.iter() {
+                    match diagnostic.level {
+                        DiagnosticLevel::Error => {
+                            errors += 1;
+                            error!("{}", diagnostic);
+                        }
+                        DiagnosticLevel::Warning => warn!("{}", diagnostic),
+                    }
+                }
+                let warnings = diagnostics.len() - errors;
+                if errors > 0 || (*strict && warnings > 0) {
+                    error!(
+                        "Found {} error(s) and {} warning(s) in {}",
+                        errors,
+                        warnings,
+                        folder.to_str().unwrap()
+                    );
+                    process::exit(2);
+                }
+                if *check {
+                    info!("Mod is valid with {} warning(s)", warnings);
+                } else {
+                    let output = match output {
+                        Some(output) => output.clone(),
+                        None => PathBuf::from(get_pack_name(folder)?),
+                    };
+                    let manifest = pack_mod(folder, &output)?;
+                    info!(
+                        "Packed {} v{} to {}",
+                        manifest.name,
+                        manifest.version,
+                        output.to_str().unwrap()
+                    );
+                }
+            }
+            DevCommands::Link { folder } => {
+                let local_db = LocalDatabase::fetch(config.get_owml_path())?;
+                let linked = link_mod(folder, &config, &local_db)?;
+                info!(
+                    "{} is now linked, run `owmods dev unlink {}` to undo",
+                    linked.manifest.name, linked.manifest.unique_name
+                );
+            }
+            DevCommands::Unlink { unique_name } => {
+                let local_db = LocalDatabase::fetch(config.get_owml_path())?;
+                let local_mod = local_db
+                    .get_mod(unique_name)
+                    .ok_or_else(|| anyhow!("Mod {} Is Not Installed", unique_name))?;
+                unlink_mod(local_mod, &config)?;
+                info!("Unlinked {}", local_mod.manifest.name);
+            }
+        },
+        Commands::Db { command } => {
+            match command {
+                DbCommands::Build {
+                    dir,
+                    base_url,
+                    output,
+                } => {
+                    let mods = build_database(dir, base_url)?;
+                    let count = mods.len();
+                    let output = output.clone().unwrap_or(dir.join("database.json"));
+                    write_database(mods, &output)?;
+                    info!("Wrote {} mod(s) to {}", count, output.to_str().unwrap());
+                }
+                DbCommands::Serve { dir, port } => {
+                    let server = DatabaseServer::new(dir, *port)?;
+                    info!(
+                        "Serving mods in {} at {}",
+                        dir.to_str().unwrap(),
+                        server.database_url()?
+                    );
+                    info!("Set database_url in your config to this URL to use it, press Ctrl+C to stop");
+                    tokio::task::spawn_blocking(move || server.serve()).await??;
+                }
+            }
+        }
         Commands::GenerateCompletions { shell } => {
             let mut cmd = BaseCli::command();
             let name = cmd.get_name().to_string();
@@ -412,6 +1092,104 @@ async fn run_from_cli(cli: BaseCli) -> Result<()> {
     Ok(())
 }
 
+const ADDON_PREFIX: &str = "  └ ";
+
+/// Order mods so addons come right after their parent, along with whether each mod is being shown as an addon.
+/// Addons whose parent isn't in the list are shown normally.
+fn group_addons<'a, T>(
+    mods: Vec<&'a T>,
+    get_unique_name: impl Fn(&'a T) -> &'a str,
+    get_parent: impl Fn(&'a T) -> Option<&'a str>,
+) -> Vec<(&'a T, bool)> {
+    let names: Vec<&str> = mods.iter().map(|m| get_unique_name(m)).collect();
+    let is_addon = |m: &'a T| get_parent(m).is_some_and(|p| names.contains(&p));
+    let mut grouped: Vec<(&T, bool)> = vec![];
+    for top in mods.iter().copied().filter(|m| !is_addon(m)) {
+        grouped.push((top, false));
+        for addon in mods
+            .iter()
+            .copied()
+            .filter(|m| get_parent(m) == Some(get_unique_name(top)))
+        {
+            grouped.push((addon, true));
+        }
+    }
+    // Addons of addons don't have a top level mod to go under
+    for m in mods.iter().copied() {
+        if !grouped.iter().any(|(g, _)| std::ptr::eq(*g, m)) {
+            grouped.push((m, false));
+        }
+    }
+    grouped
+}
+
+/// Reverse the pre-patches of mods that are about to be uninstalled, returns the names of the mods that were unpatched
+async fn unpatch_mods(config: &Config, mods: &[&LocalMod], db: &LocalDatabase) -> Vec<String> {
+    let patches = Patches::from_config(config);
+    let mut unpatched = vec![];
+    for local_mod in mods {
+        match patches
+            .unpatch(config, &local_mod.manifest.unique_name, Some(db))
+            .await
+        {
+            Ok(true) => unpatched.push(local_mod.manifest.name.clone()),
+            Ok(false) => {}
+            Err(why) => error!("Couldn't unpatch {}: {:?}", local_mod.manifest.name, why),
+        }
+    }
+    unpatched
+}
+
+async fn show_changelog(local_mod: &LocalMod, remote_mod: &RemoteMod) {
+    info!(
+        "========== {}: {} -> {} ==========",
+        local_mod.manifest.name,
+        local_mod.manifest.version,
+        remote_mod.get_channel_release(local_mod.channel).0
+    );
+    match fetch_changelog(
+        GITHUB_API_URL,
+        remote_mod,
+        &local_mod.manifest.version,
+        local_mod.channel,
+    )
+    .await
+    {
+        Ok(entries) if entries.is_empty() => info!("No release notes found"),
+        Ok(entries) => {
+            for entry in entries {
+                info!("{} ({})", entry.title.bold(), entry.version);
+                for line in entry.body.lines() {
+                    info!("  {}", line);
+                }
+                info!("{}", entry.url.dimmed());
+            }
+        }
+        Err(why) => warn!("Couldn't get the changelog: {:?}", why),
+    }
+}
+
+fn to_game(game: GameArg) -> Game {
+    match game {
+        GameArg::OuterWilds => Game::OuterWilds,
+        GameArg::Alpha => Game::Alpha,
+    }
+}
+
+fn game_name(game: Game) -> &'static str {
+    match game {
+        Game::OuterWilds => "Outer Wilds",
+        Game::Alpha => "Outer Wilds Alpha",
+    }
+}
+
+fn channel_name(channel: ReleaseChannel) -> &'static str {
+    match channel {
+        ReleaseChannel::Stable => "stable",
+        ReleaseChannel::Prerelease => "prerelease",
+    }
+}
+
 fn yes_no(v: bool) -> String {
     if v {
         "Yes".to_string()
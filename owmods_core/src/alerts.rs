@@ -26,7 +26,7 @@ pub struct Alert {
 ///
 pub async fn fetch_alert(url: &str) -> Result<Alert> {
     debug!("Fetching {}", url);
-    let alert: Alert = reqwest::get(url).await?.json().await?;
+    let alert: Alert = reqwest::get(url).await?.error_for_status()?.json().await?;
     Ok(alert)
 }
 
@@ -61,13 +61,24 @@ pub fn get_warnings<'a>(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::constants::DEFAULT_ALERT_URL;
+    use crate::test_utils::{MockFailure, MockServer};
 
     #[test]
     pub fn test_get_alert() {
+        let server = MockServer::start();
         tokio_test::block_on(async {
-            let alert = fetch_alert(DEFAULT_ALERT_URL).await;
-            assert!(alert.is_ok());
+            let alert = fetch_alert(&server.alert_url()).await.unwrap();
+            assert!(alert.enabled);
+            assert_eq!(alert.message.unwrap(), "This is a test alert");
+        });
+    }
+
+    #[test]
+    pub fn test_get_alert_server_error() {
+        let server = MockServer::start();
+        server.fail("alert.json", MockFailure::Status(500));
+        tokio_test::block_on(async {
+            assert!(fetch_alert(&server.alert_url()).await.is_err());
         });
     }
 
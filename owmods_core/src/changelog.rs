@@ -0,0 +1,253 @@
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, Result};
+use log::debug;
+use reqwest::header::{ACCEPT, USER_AGENT};
+use serde::{Deserialize, Serialize};
+use typeshare::typeshare;
+
+use crate::{
+    mods::{local::ReleaseChannel, remote::RemoteMod},
+    version::Version,
+};
+
+/// The release notes for one version of a mod
+#[typeshare]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangelogEntry {
+    pub version: String,
+    /// The title of the release, falls back to the version if the release doesn't have one
+    pub title: String,
+    /// The release notes, usually markdown
+    pub body: String,
+    /// A link to the release on GitHub
+    pub url: String,
+    /// When the release was published, as an RFC 3339 timestamp
+    pub published_at: Option<String>,
+    pub prerelease: bool,
+}
+
+/// A release as returned by the GitHub API, only the fields we need
+#[derive(Deserialize)]
+struct GitHubRelease {
+    tag_name: String,
+    name: Option<String>,
+    body: Option<String>,
+    html_url: String,
+    published_at: Option<String>,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+}
+
+/// Get the owner and name of a repo from its GitHub URL
+fn get_repo_path(repo_url: &str) -> Option<(&str, &str)> {
+    let path = repo_url
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .strip_prefix("github.com/")?;
+    let mut parts = path.trim_end_matches('/').split('/');
+    let owner = parts.next().filter(|p| !p.is_empty())?;
+    let name = parts.next().filter(|p| !p.is_empty())?;
+    Some((owner, name.trim_end_matches(".git")))
+}
+
+/// Fetch the release notes for every version of a mod after `from_version`, up to and including the version `channel` would update to (see [RemoteMod::get_channel_release]).
+/// Release notes come from the GitHub releases of the mod's repo, drafts are skipped and prereleases are only included if they're the version being updated to.
+/// Only the 100 newest releases are checked.
+///
+/// `api_url` is the base URL of the GitHub API, normally [crate::constants::GITHUB_API_URL].
+///
+/// ## Returns
+///
+/// The entries, newest first. Releases with tags that aren't versions are skipped.
+///
+/// ## Errors
+///
+/// - If the mod's repo isn't on GitHub
+/// - If `from_version` or the version being updated to can't be parsed
+/// - If the request fails or the response isn't a list of releases
+///
+pub async fn fetch_changelog(
+    api_url: &str,
+    remote_mod: &RemoteMod,
+    from_version: &str,
+    channel: ReleaseChannel,
+) -> Result<Vec<ChangelogEntry>> {
+    let (to_version, _) = remote_mod.get_channel_release(channel);
+    let from_version: Version = from_version.parse()?;
+    let to_version: Version = to_version.parse()?;
+    let (owner, name) = get_repo_path(&remote_mod.repo).ok_or_else(|| {
+        anyhow!(
+            "{}'s repo isn't on GitHub, can't get its changelog",
+            remote_mod.name
+        )
+    })?;
+    let url = format!(
+        "{}/repos/{owner}/{name}/releases?per_page=100",
+        api_url.trim_end_matches('/')
+    );
+    debug!(
+        "Fetching changelog for {} from {}",
+        remote_mod.unique_name, url
+    );
+    let releases: Vec<GitHubRelease> = reqwest::Client::new()
+        .get(&url)
+        .header(USER_AGENT, concat!("owmods/", env!("CARGO_PKG_VERSION")))
+        .header(ACCEPT, "application/vnd.github+json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let mut entries: Vec<(Version, ChangelogEntry)> = releases
+        .into_iter()
+        .filter(|r| !r.draft)
+        .filter_map(|r| {
+            let parsed: Version = r.tag_name.parse().ok()?;
+            let wanted = parsed > from_version
+                && match parsed.cmp(&to_version) {
+                    Ordering::Less => !r.prerelease,
+                    Ordering::Equal => true,
+                    Ordering::Greater => false,
+                };
+            if !wanted {
+                return None;
+            }
+            let version = r.tag_name.trim_start_matches('v').to_string();
+            Some((
+                parsed,
+                ChangelogEntry {
+                    title: r
+                        .name
+                        .filter(|n| !n.trim().is_empty())
+                        .unwrap_or_else(|| version.clone()),
+                    version,
+                    body: r.body.unwrap_or_default(),
+                    url: r.html_url,
+                    published_at: r.published_at,
+                    prerelease: r.prerelease,
+                },
+            ))
+        })
+        .collect();
+    entries.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{db::RemoteDatabase, mods::remote::ModPrerelease, test_utils::MockServer};
+
+    #[test]
+    fn test_get_repo_path() {
+        assert_eq!(
+            get_repo_path("https://github.com/Bwc9876/OW-TimeSaver"),
+            Some(("Bwc9876", "OW-TimeSaver"))
+        );
+        assert_eq!(
+            get_repo_path("https://github.com/Bwc9876/OW-TimeSaver.git/"),
+            Some(("Bwc9876", "OW-TimeSaver"))
+        );
+        assert_eq!(get_repo_path("https://gitlab.com/Bwc9876/Mod"), None);
+        assert_eq!(get_repo_path("https://github.com/Bwc9876"), None);
+        assert_eq!(get_repo_path(""), None);
+    }
+
+    #[test]
+    fn test_fetch_changelog() {
+        let server = MockServer::start();
+        tokio_test::block_on(async {
+            let db = RemoteDatabase::fetch(&server.database_url()).await.unwrap();
+            let remote_mod = db.get_mod("Bwc9876.TimeSaver").unwrap();
+            let entries = fetch_changelog(
+                &server.github_api_url(),
+                remote_mod,
+                "1.0.0",
+                ReleaseChannel::Stable,
+            )
+            .await
+            .unwrap();
+            let versions = entries
+                .iter()
+                .map(|e| e.version.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(versions, vec!["1.1.1", "1.1.0"]);
+            assert_eq!(entries[0].title, "Bug Fixes");
+            assert_eq!(entries[0].body, "- Fixed skipping the credits");
+            assert_eq!(entries[1].title, "1.1.0");
+            assert_eq!(entries[1].body, "");
+
+            let entries = fetch_changelog(
+                &server.github_api_url(),
+                remote_mod,
+                "1.1.1",
+                ReleaseChannel::Stable,
+            )
+            .await
+            .unwrap();
+            assert!(entries.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_fetch_changelog_prerelease() {
+        let server = MockServer::start();
+        tokio_test::block_on(async {
+            let db = RemoteDatabase::fetch(&server.database_url()).await.unwrap();
+            let mut remote_mod = db.get_mod("Bwc9876.TimeSaver").unwrap().clone();
+            remote_mod.prerelease = Some(ModPrerelease {
+                download_url: String::new(),
+                version: "1.2.0-beta".to_string(),
+            });
+            let entries = fetch_changelog(
+                &server.github_api_url(),
+                &remote_mod,
+                "1.1.0",
+                ReleaseChannel::Prerelease,
+            )
+            .await
+            .unwrap();
+            let versions = entries
+                .iter()
+                .map(|e| e.version.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(versions, vec!["1.2.0-beta", "1.1.1"]);
+            assert!(entries[0].prerelease);
+        });
+    }
+
+    #[test]
+    fn test_fetch_changelog_errors() {
+        let server = MockServer::start();
+        tokio_test::block_on(async {
+            let db = RemoteDatabase::fetch(&server.database_url()).await.unwrap();
+            // SaveEditor has no releases on the mock server
+            let remote_mod = db.get_mod("Bwc9876.SaveEditor").unwrap();
+            assert!(fetch_changelog(
+                &server.github_api_url(),
+                remote_mod,
+                "0.1.0",
+                ReleaseChannel::Stable
+            )
+            .await
+            .is_err());
+            let mut remote_mod = remote_mod.clone();
+            remote_mod.repo = "https://example.com/mod".to_string();
+            let err = fetch_changelog(
+                &server.github_api_url(),
+                &remote_mod,
+                "0.1.0",
+                ReleaseChannel::Stable,
+            )
+            .await
+            .unwrap_err();
+            assert!(err.to_string().contains("isn't on GitHub"));
+        });
+    }
+}
@@ -1,27 +1,136 @@
-use std::path::{Path, PathBuf};
+use std::{cmp::Reverse, path::PathBuf};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use log::debug;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use typeshare::typeshare;
 
 use crate::{
     constants::{
-        CONFIG_FILE_NAME, DEFAULT_ALERT_URL, DEFAULT_DB_URL, OWML_DEFAULT_CONFIG_NAME,
-        OWML_EXE_NAME, OWML_MANIFEST_NAME,
+        CONFIG_FILE_NAME, DEFAULT_ALERT_URL, DEFAULT_DB_URL, DEFAULT_INSTANCE_NAME,
+        OWML_DEFAULT_CONFIG_NAME, OWML_EXE_NAME, OWML_MANIFEST_NAME,
     },
-    file::{deserialize_from_json, get_app_path, get_default_owml_path, serialize_to_json},
+    file::{get_app_path, get_default_alpha_owml_path, get_default_owml_path},
+    versioned::{current_version, load_versioned, save_versioned, Loaded, Migration},
 };
 
+/// Upgrades configs from before they were versioned.
+/// Some of those are from before every field existed, so fill in the ones that can't be missing.
+fn migrate_v0(obj: &mut Map<String, Value>) -> Result<()> {
+    let defaults = [
+        (
+            "owmlPath",
+            Value::from(get_default_owml_path()?.to_str().unwrap()),
+        ),
+        ("databaseUrl", Value::from(DEFAULT_DB_URL)),
+        ("alertUrl", Value::from(DEFAULT_ALERT_URL)),
+        ("viewedAlerts", Value::Array(vec![])),
+    ];
+    for (key, value) in defaults {
+        obj.entry(key).or_insert(value);
+    }
+    Ok(())
+}
+
+const MIGRATIONS: &[Migration] = &[migrate_v0];
+
+/// The version of the config this version of the manager uses, see [crate::versioned]
+pub const CONFIG_VERSION: u32 = current_version(MIGRATIONS);
+
+/// An extra database to get mods from, on top of the one in `database_url`
+#[typeshare]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseSource {
+    /// A name for the source, shown on mods that come from it
+    pub name: String,
+    /// The URL or local file path of the database
+    pub url: String,
+    /// When multiple sources have the same mod the one with the highest priority is used, the default database has a priority of `0`
+    #[serde(default)]
+    pub priority: i32,
+    /// Whether to use this source
+    #[serde(default = "default_source_enabled")]
+    pub enabled: bool,
+}
+
+fn default_source_enabled() -> bool {
+    true
+}
+
+/// Which game mods are managed for
+#[typeshare]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Game {
+    #[default]
+    OuterWilds,
+    /// Outer Wilds Alpha, it has its own mods in the database and needs its own OWML install
+    Alpha,
+}
+
+/// Where OWML and the game are for Outer Wilds Alpha
+#[typeshare]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AlphaConfig {
+    pub owml_path: String,
+    /// Where Outer Wilds Alpha is installed, this is written to OWML's config when OWML is installed for Alpha
+    pub game_path: String,
+}
+
+/// A named install of OWML and the game, so multiple copies of the game can be modded side by side.
+/// The instance made from [Config::owml_path], [Config::game] and [Config::alpha] is called `default`.
+#[typeshare]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Instance {
+    pub name: String,
+    pub owml_path: String,
+    /// Where the game is installed, this is written to the instance's OWML config. Leave empty to use the game path already in OWML's config
+    #[serde(default)]
+    pub game_path: String,
+    /// Overrides [Config::game_runner] for this instance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_runner: Option<String>,
+    #[serde(default)]
+    pub game: Game,
+}
+
 /// Represents the core config, contains critical info needed by the core API
 #[typeshare]
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Config {
+    /// The version of the config's format, configs from older versions are migrated when they're loaded
+    #[serde(default)]
+    pub version: u32,
+    /// The OWML install for Outer Wilds, use [Config::get_owml_path] to get the one for the selected game
     pub owml_path: String,
     pub database_url: String,
+    #[serde(default)]
+    pub database_sources: Vec<DatabaseSource>,
     pub alert_url: String,
     pub viewed_alerts: Vec<String>,
+    /// Which game to manage mods for, this decides which OWML install and which mods in the database are used
+    #[serde(default)]
+    pub game: Game,
+    #[serde(default)]
+    pub alpha: AlphaConfig,
+    /// A program to run OWML and pre-patchers with, like `wine`. By default they're run directly on Windows and with `mono` everywhere else
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_runner: Option<String>,
+    #[serde(default)]
+    pub instances: Vec<Instance>,
+    /// The name of the instance to use, `None` for the default instance. See [Config::get_instance]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    /// OWML installs that already got their mods' enabled states from the old manager, see [crate::migrate::migrate_old_manager]
+    #[serde(default)]
+    pub migrated_owml_paths: Vec<String>,
+    /// Use this instance instead of `instance` without saving it, see [Config::use_instance]
+    #[serde(skip)]
+    pub instance_override: Option<String>,
     #[serde(skip)]
     pub path: PathBuf,
 }
@@ -37,10 +146,22 @@ impl Config {
         let path = path.unwrap_or(Self::default_path()?);
         let owml_path = get_default_owml_path()?;
         Ok(Self {
+            version: CONFIG_VERSION,
             owml_path: String::from(owml_path.to_str().unwrap()),
             database_url: String::from(DEFAULT_DB_URL),
+            database_sources: vec![],
             alert_url: String::from(DEFAULT_ALERT_URL),
             viewed_alerts: vec![],
+            game: Game::OuterWilds,
+            alpha: AlphaConfig {
+                owml_path: String::from(get_default_alpha_owml_path()?.to_str().unwrap()),
+                game_path: String::new(),
+            },
+            game_runner: None,
+            instances: vec![],
+            instance: None,
+            migrated_owml_paths: vec![],
+            instance_override: None,
             path,
         })
     }
@@ -62,7 +183,7 @@ impl Config {
         Ok(app_path.join(CONFIG_FILE_NAME))
     }
 
-    /// Save the config
+    /// Save the config, the file is replaced atomically and the previous one is kept as a backup
     ///
     /// ## Errors
     ///
@@ -70,23 +191,194 @@ impl Config {
     ///
     pub fn save(&self) -> Result<()> {
         debug!("Writing Config To {}", self.path.to_str().unwrap());
-        serialize_to_json(self, &self.path, true)?;
+        save_versioned(self, &self.path, CONFIG_VERSION)?;
         Ok(())
     }
 
+    /// Get the databases to fetch mods from.
+    /// The database in `database_url` is included as a source with no name and a priority of `0`, unless it's empty.
+    ///
+    /// ## Returns
+    ///
+    /// All enabled sources, highest priority first. Sources with the same priority stay in the order they're configured in, after the default database.
+    ///
+    pub fn get_database_sources(&self) -> Vec<DatabaseSource> {
+        let default_source = DatabaseSource {
+            name: String::new(),
+            url: self.database_url.clone(),
+            priority: 0,
+            enabled: !self.database_url.trim().is_empty(),
+        };
+        let mut sources = std::iter::once(default_source)
+            .chain(self.database_sources.iter().cloned())
+            .filter(|s| s.enabled)
+            .collect::<Vec<_>>();
+        sources.sort_by_key(|s| Reverse(s.priority));
+        sources
+    }
+
+    fn selected_instance_name(&self) -> Option<&String> {
+        self.instance_override.as_ref().or(self.instance.as_ref())
+    }
+
+    /// Get the instance every operation should use, [Config::instance_override] takes priority over [Config::instance].
+    ///
+    /// ## Returns
+    ///
+    /// The selected instance, `None` if the default instance is selected or the selected one doesn't exist anymore.
+    ///
+    pub fn get_instance(&self) -> Option<&Instance> {
+        let name = self
+            .selected_instance_name()
+            .filter(|n| *n != DEFAULT_INSTANCE_NAME)?;
+        let instance = self.instances.iter().find(|i| &i.name == name);
+        if instance.is_none() {
+            debug!("Instance {} not found, using the default instance", name);
+        }
+        instance
+    }
+
+    /// Get the selected instance to change it, see [Config::get_instance]
+    pub fn get_instance_mut(&mut self) -> Option<&mut Instance> {
+        let name = self.selected_instance_name()?.clone();
+        self.instances.iter_mut().find(|i| i.name == name)
+    }
+
+    /// Get the name of the selected instance, `default` for the default instance
+    pub fn get_instance_name(&self) -> &str {
+        self.get_instance()
+            .map(|i| i.name.as_str())
+            .unwrap_or(DEFAULT_INSTANCE_NAME)
+    }
+
+    /// Use an instance for this session without saving it as the selected instance, pass `default` for the default instance.
+    ///
+    /// ## Errors
+    ///
+    /// If there's no instance with that name.
+    ///
+    pub fn use_instance(&mut self, name: &str) -> Result<()> {
+        if name == DEFAULT_INSTANCE_NAME || self.instances.iter().any(|i| i.name == name) {
+            self.instance_override = Some(name.to_string());
+        } else {
+            return Err(anyhow!("Instance {} not found", name));
+        }
+        Ok(())
+    }
+
+    /// Add a new instance, it isn't selected.
+    ///
+    /// ## Errors
+    ///
+    /// If the name is empty, `default`, or another instance already has it.
+    ///
+    pub fn add_instance(&mut self, instance: Instance) -> Result<()> {
+        if instance.name.trim().is_empty() || instance.name == DEFAULT_INSTANCE_NAME {
+            return Err(anyhow!("{} isn't a valid instance name", instance.name));
+        }
+        if self.instances.iter().any(|i| i.name == instance.name) {
+            return Err(anyhow!("Instance {} already exists", instance.name));
+        }
+        self.instances.push(instance);
+        Ok(())
+    }
+
+    /// Remove an instance, if it was selected the default instance is selected instead.
+    /// The instance's OWML install and mods aren't deleted.
+    ///
+    /// ## Errors
+    ///
+    /// If there's no instance with that name.
+    ///
+    pub fn remove_instance(&mut self, name: &str) -> Result<Instance> {
+        let index = self
+            .instances
+            .iter()
+            .position(|i| i.name == name)
+            .ok_or_else(|| anyhow!("Instance {} not found", name))?;
+        if self.instance.as_deref() == Some(name) {
+            self.instance = None;
+        }
+        if self.instance_override.as_deref() == Some(name) {
+            self.instance_override = None;
+        }
+        Ok(self.instances.remove(index))
+    }
+
+    /// Get the game the selected instance is for
+    pub fn get_game(&self) -> Game {
+        self.get_instance().map(|i| i.game).unwrap_or(self.game)
+    }
+
+    /// Get where the game the selected instance is for is installed, if it's set in the manager's config.
+    /// This is the instance's game path, or [AlphaConfig::game_path] for the default instance when managing Outer Wilds Alpha.
+    pub fn get_game_path(&self) -> Option<&str> {
+        let game_path = match (self.get_instance(), self.game) {
+            (Some(instance), _) => &instance.game_path,
+            (None, Game::Alpha) => &self.alpha.game_path,
+            (None, Game::OuterWilds) => return None,
+        };
+        Some(game_path.as_str()).filter(|p| !p.trim().is_empty())
+    }
+
+    /// Get the program to run OWML and pre-patchers with for the selected instance, see [Config::game_runner]
+    pub fn get_game_runner(&self) -> Option<&str> {
+        self.get_instance()
+            .and_then(|i| i.game_runner.as_deref())
+            .or(self.game_runner.as_deref())
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+    }
+
+    /// Get the path to the OWML install of the selected instance, for the default instance this depends on [Config::game]
+    pub fn get_owml_path(&self) -> &String {
+        if let Some(instance) = self.get_instance() {
+            return &instance.owml_path;
+        }
+        match self.game {
+            Game::OuterWilds => &self.owml_path,
+            Game::Alpha => &self.alpha.owml_path,
+        }
+    }
+
+    /// Set the path to the OWML install of the selected instance, for the default instance this depends on [Config::game]
+    pub fn set_owml_path(&mut self, owml_path: &str) {
+        if let Some(instance) = self.get_instance_mut() {
+            instance.owml_path = owml_path.to_string();
+            return;
+        }
+        match self.game {
+            Game::OuterWilds => self.owml_path = owml_path.to_string(),
+            Game::Alpha => self.alpha.owml_path = owml_path.to_string(),
+        }
+    }
+
     /// Set that a specific mod's warning was shown.
     pub fn set_warning_shown(&mut self, unique_name: &str) {
         self.viewed_alerts.push(unique_name.to_string());
     }
 
-    fn read(path: &Path) -> Result<Self> {
+    /// Load the config from the provided path (or default one), creating a default file if it doesn't exist.
+    /// Configs from older versions are migrated, and corrupted ones are quarantined and recovered, see [load_versioned].
+    ///
+    /// ## Returns
+    ///
+    /// The newly created or loaded config, and what was done to recover it if it was corrupted.
+    ///
+    /// ## Errors
+    ///
+    /// If we can't read the current config or create a new one, or the config is from a newer version of the manager.
+    ///
+    pub fn load(path: Option<PathBuf>) -> Result<Loaded<Self>> {
+        let path = path.unwrap_or(Self::default_path()?);
         debug!("Reading Config From {}", path.to_str().unwrap());
-        let mut new_conf: Config = deserialize_from_json(path)?;
-        new_conf.path = path.to_path_buf();
-        Ok(new_conf)
+        let mut loaded = load_versioned(&path, MIGRATIONS, || Self::default(Some(path.clone())))?;
+        loaded.value.path = path;
+        Ok(loaded)
     }
 
     /// Get the config from the provided path (or default one), creating a default file if it doesn't exist.
+    /// Like [Config::load], but recoveries are only logged.
     ///
     /// ## Returns
     ///
@@ -94,20 +386,13 @@ impl Config {
     ///
     /// ## Errors
     ///
-    /// If we can't read the current config or create a new one.
+    /// If we can't read the current config or create a new one, or the config is from a newer version of the manager.
     ///
     pub fn get(path: Option<PathBuf>) -> Result<Self> {
-        let path = path.unwrap_or(Self::default_path()?);
-        if path.is_file() {
-            Self::read(&path)
-        } else {
-            let new_config = Self::default(Some(path))?;
-            new_config.save()?;
-            Ok(new_config)
-        }
+        Ok(Self::load(path)?.value)
     }
 
-    /// Checks that the path in `owml_path` is a valid OWML install (at least for our uses)
+    /// Checks that the OWML path for the selected game ([Config::get_owml_path]) is a valid OWML install (at least for our uses)
     ///
     /// ## Returns
     ///
@@ -115,10 +400,11 @@ impl Config {
     /// Other files that make OWML work are not checked, only the ones the manager needs.
     ///
     pub fn check_owml(&self) -> bool {
-        if self.owml_path.trim().is_empty() {
+        let owml_path = self.get_owml_path();
+        if owml_path.trim().is_empty() {
             false
         } else {
-            let path = PathBuf::from(&self.owml_path);
+            let path = PathBuf::from(owml_path);
             path.is_dir()
                 && path.join(OWML_DEFAULT_CONFIG_NAME).is_file()
                 && path.join(OWML_EXE_NAME).is_file()
@@ -130,7 +416,7 @@ impl Config {
 #[cfg(test)]
 mod tests {
 
-    use crate::test_utils::make_test_dir;
+    use crate::{test_utils::make_test_dir, versioned::get_backup_path};
 
     use super::*;
 
@@ -149,7 +435,7 @@ mod tests {
         config.database_url = "test".to_string();
         config.save().unwrap();
         assert!(path.is_file());
-        let new_config = Config::read(&path).unwrap();
+        let new_config = Config::get(Some(path)).unwrap();
         assert_eq!(config.database_url, new_config.database_url);
         dir.close().unwrap();
     }
@@ -176,6 +462,51 @@ mod tests {
         dir.close().unwrap();
     }
 
+    #[test]
+    pub fn test_config_get_migrate() {
+        let dir = make_test_dir();
+        let path = dir.path().join("settings.json");
+        std::fs::write(
+            &path,
+            r#"{ "owmlPath": "/owml", "databaseUrl": "https://example.com/database.json" }"#,
+        )
+        .unwrap();
+        let config = Config::get(Some(path.clone())).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.owml_path, "/owml");
+        assert_eq!(config.alert_url, DEFAULT_ALERT_URL);
+        assert!(config.viewed_alerts.is_empty());
+        assert!(get_backup_path(&path).is_file());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    pub fn test_config_get_corrupted() {
+        let dir = make_test_dir();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, "{ \"owmlPath\": ").unwrap();
+        let loaded = Config::load(Some(path.clone())).unwrap();
+        let recovery = loaded.recovery.unwrap();
+        assert!(!recovery.restored_backup);
+        assert!(recovery.quarantined_path.is_file());
+        assert_eq!(loaded.value.database_url, DEFAULT_DB_URL);
+        assert_eq!(loaded.value.path, path);
+        dir.close().unwrap();
+    }
+
+    #[test]
+    pub fn test_config_get_newer() {
+        let dir = make_test_dir();
+        let path = dir.path().join("settings.json");
+        let contents = format!(r#"{{ "version": {} }}"#, CONFIG_VERSION + 1);
+        std::fs::write(&path, &contents).unwrap();
+        assert!(Config::get(Some(path.clone())).is_err());
+        let config = Config::default(Some(path.clone())).unwrap();
+        assert!(config.save().is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), contents);
+        dir.close().unwrap();
+    }
+
     #[test]
     pub fn test_check_owml_no_folder() {
         let dir = make_test_dir();
@@ -185,4 +516,98 @@ mod tests {
         assert!(!config.check_owml());
         dir.close().unwrap();
     }
+
+    #[test]
+    pub fn test_config_game() {
+        let mut config = Config::default(Some(PathBuf::from("/test/path"))).unwrap();
+        config.owml_path = "/owml".to_string();
+        assert_eq!(config.get_owml_path(), "/owml");
+        config.game = Game::Alpha;
+        config.set_owml_path("/alpha/owml");
+        assert_eq!(config.get_owml_path(), "/alpha/owml");
+        assert_eq!(config.owml_path, "/owml");
+        // Configs from before Alpha was supported should still load
+        let old: Config = serde_json::from_str(
+            r#"{ "owmlPath": "/owml", "databaseUrl": "", "alertUrl": "", "viewedAlerts": [] }"#,
+        )
+        .unwrap();
+        assert_eq!(old.game, Game::OuterWilds);
+        assert_eq!(old.alpha, AlphaConfig::default());
+    }
+
+    #[test]
+    pub fn test_config_instances() {
+        let mut config = Config::default(Some(PathBuf::from("/test/path"))).unwrap();
+        config.owml_path = "/owml".to_string();
+        let instance = |name: &str| Instance {
+            name: name.to_string(),
+            owml_path: format!("/{name}/owml"),
+            game_path: format!("/{name}/game"),
+            game_runner: Some("wine".to_string()),
+            game: Game::OuterWilds,
+        };
+        config.add_instance(instance("steam")).unwrap();
+        config.add_instance(instance("epic")).unwrap();
+        assert!(config.add_instance(instance("steam")).is_err());
+        assert!(config.add_instance(instance("default")).is_err());
+        assert_eq!(config.get_instance_name(), "default");
+        assert_eq!(config.get_owml_path(), "/owml");
+        assert_eq!(config.get_game_path(), None);
+        assert_eq!(config.get_game_runner(), None);
+
+        config.instance = Some("steam".to_string());
+        assert_eq!(config.get_owml_path(), "/steam/owml");
+        assert_eq!(config.get_game_path(), Some("/steam/game"));
+        assert_eq!(config.get_game_runner(), Some("wine"));
+        config.use_instance("epic").unwrap();
+        assert_eq!(config.get_owml_path(), "/epic/owml");
+        config.set_owml_path("/epic/new");
+        assert_eq!(config.instances[1].owml_path, "/epic/new");
+        assert_eq!(config.owml_path, "/owml");
+        assert!(config.use_instance("gog").is_err());
+
+        // The override isn't saved
+        let saved: Config = serde_json::from_str(&serde_json::to_string(&config).unwrap()).unwrap();
+        assert_eq!(saved.get_instance_name(), "steam");
+
+        config.remove_instance("epic").unwrap();
+        assert_eq!(config.get_instance_name(), "steam");
+        config.use_instance("default").unwrap();
+        assert_eq!(config.get_owml_path(), "/owml");
+        assert_eq!(config.instance, Some("steam".to_string()));
+        assert!(config.remove_instance("epic").is_err());
+    }
+
+    #[test]
+    pub fn test_get_database_sources() {
+        let mut config = Config::default(Some(PathBuf::from("/test/path"))).unwrap();
+        let source = |name: &str, priority: i32, enabled: bool| DatabaseSource {
+            name: name.to_string(),
+            url: format!("https://{name}.example.com/database.json"),
+            priority,
+            enabled,
+        };
+        config.database_sources = vec![
+            source("low", -1, true),
+            source("team", 10, true),
+            source("same", 0, true),
+            source("off", 20, false),
+        ];
+        let names = config
+            .get_database_sources()
+            .into_iter()
+            .map(|s| s.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["team", "", "same", "low"]);
+        config.database_url = "".to_string();
+        assert_eq!(config.get_database_sources().len(), 3);
+    }
+
+    #[test]
+    pub fn test_database_source_defaults() {
+        let source: DatabaseSource =
+            serde_json::from_str(r#"{ "name": "team", "url": "/srv/database.json" }"#).unwrap();
+        assert_eq!(source.priority, 0);
+        assert!(source.enabled);
+    }
 }
@@ -10,3 +10,17 @@ pub const OWML_DEFAULT_CONFIG_NAME: &str = "OWML.DefaultConfig.json";
 pub const OWML_EXE_NAME: &str = "OWML.Launcher.exe";
 pub const WEBSITE_URL: &str = "https://outerwildsmods.com";
 pub const OLD_MANAGER_FOLDER_NAME: &str = "OuterWildsModManager";
+pub const LINKED_BACKUPS_FOLDER_NAME: &str = "LinkedBackups";
+pub const MAX_EXTRACT_SIZE: u64 = 8 * 1024 * 1024 * 1024;
+pub const MAX_EXTRACT_ENTRIES: usize = 65_536;
+pub const MAX_COMPRESSION_RATIO: u64 = 250;
+pub const MOD_INSTALL_INFO_NAME: &str = ".owmods.json";
+pub const HISTORY_FILE_NAME: &str = "history.jsonl";
+pub const HISTORY_ARCHIVES_DIR_NAME: &str = "history_archives";
+
+/// How many archives of removed or replaced mods the history keeps, the oldest ones are deleted past this
+pub const HISTORY_ARCHIVES_LIMIT: usize = 25;
+pub const PATCHES_FILE_NAME: &str = "patches.json";
+pub const PATCH_BACKUPS_DIR_NAME: &str = "patch_backups";
+pub const DEFAULT_INSTANCE_NAME: &str = "default";
+pub const GITHUB_API_URL: &str = "https://api.github.com";
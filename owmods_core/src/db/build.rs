@@ -0,0 +1,153 @@
+use std::{collections::HashMap, fs::File, io::BufWriter, path::Path};
+
+use anyhow::{anyhow, Result};
+use log::{debug, warn};
+use reqwest::Url;
+
+use crate::{download::get_manifest_from_zip, mods::remote::RemoteMod, version::is_newer};
+
+use super::remote::RawRemoteDatabase;
+
+/// Build a database from a folder of mod zips, the manifest in each zip is read the same way it would be when installing it.
+/// Only zips directly in the folder are included, if two zips contain the same mod the one with the higher version is used.
+///
+/// ## Returns
+///
+/// The mods in the database, sorted by unique name.
+///
+/// ## Errors
+///
+/// If we can't read the folder or one of the zips doesn't contain a valid manifest.
+/// If `base_url` isn't a valid URL.
+///
+pub fn build_database(zips_path: &Path, base_url: &str) -> Result<Vec<RemoteMod>> {
+    // Url::join drops the last segment if there's no trailing slash, which isn't what anyone wants here
+    let base_url = if base_url.ends_with('/') {
+        Url::parse(base_url)?
+    } else {
+        Url::parse(&format!("{base_url}/"))?
+    };
+    let mut zips = zips_path
+        .read_dir()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == "zip"))
+        .collect::<Vec<_>>();
+    zips.sort();
+    let mut mods: HashMap<String, RemoteMod> = HashMap::new();
+    for zip_path in zips {
+        let file_name = zip_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow!("Invalid file name {:?}", zip_path))?;
+        let manifest = get_manifest_from_zip(&zip_path)
+            .map_err(|e| anyhow!("Couldn't read manifest in {}: {:?}", file_name, e))?;
+        let download_url = base_url.join(file_name)?.to_string();
+        debug!("Adding {} from {}", manifest.unique_name, file_name);
+        let new_mod = RemoteMod::from_manifest(&manifest, download_url);
+        if let Some(existing) = mods.get(&new_mod.unique_name) {
+            warn!(
+                "Found {} more than once, using the one with the higher version",
+                new_mod.unique_name
+            );
+            if !is_newer(&new_mod.version, &existing.version) {
+                continue;
+            }
+        }
+        mods.insert(new_mod.unique_name.clone(), new_mod);
+    }
+    let mut mods = mods.into_values().collect::<Vec<_>>();
+    mods.sort_by(|a, b| a.unique_name.cmp(&b.unique_name));
+    Ok(mods)
+}
+
+/// Write mods to a file in the same format as the remote database, so it can be used as the `database_url` in the config
+///
+/// ## Errors
+///
+/// If we can't create or write to the file.
+///
+pub fn write_database(mods: Vec<RemoteMod>, path: &Path) -> Result<()> {
+    let file = File::create(path)?;
+    let writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(
+        writer,
+        &RawRemoteDatabase {
+            releases: mods,
+            alpha_releases: vec![],
+        },
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+
+    use super::*;
+    use crate::{
+        db::RemoteDatabase,
+        test_utils::{get_test_file, make_test_dir},
+    };
+
+    fn copy_test_zips(dir: &Path) {
+        for name in [
+            "Bwc9876.TimeSaver.zip",
+            "Bwc9876.SaveEditor.zip",
+            "Bwc9876.NestedManifest.zip",
+        ] {
+            fs::copy(get_test_file(name), dir.join(name)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_build_database() {
+        let dir = make_test_dir();
+        copy_test_zips(dir.path());
+        fs::write(dir.path().join("notes.txt"), "not a mod").unwrap();
+        let mods = build_database(dir.path(), "http://localhost:8080/mods").unwrap();
+        let names = mods
+            .iter()
+            .map(|m| m.unique_name.as_str())
+            .collect::<Vec<_>>();
+        // NestedManifest contains the same version of TimeSaver, so the first one found is kept
+        assert_eq!(names, vec!["Bwc9876.SaveEditor", "Bwc9876.TimeSaver"]);
+        let save_editor = &mods[0];
+        assert_eq!(
+            save_editor.download_url,
+            "http://localhost:8080/mods/Bwc9876.SaveEditor.zip"
+        );
+        assert_eq!(save_editor.name, "Save Editor");
+        let time_saver = &mods[1];
+        assert_eq!(
+            time_saver.download_url,
+            "http://localhost:8080/mods/Bwc9876.NestedManifest.zip"
+        );
+        assert_eq!(time_saver.name, "TimeSaver");
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_build_database_invalid_zip() {
+        let dir = make_test_dir();
+        fs::write(dir.path().join("Broken.zip"), "not a zip").unwrap();
+        assert!(build_database(dir.path(), "http://localhost:8080/").is_err());
+        assert!(build_database(dir.path().join("Nope").as_path(), "http://localhost/").is_err());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_write_database() {
+        let dir = make_test_dir();
+        copy_test_zips(dir.path());
+        let mods = build_database(dir.path(), "http://localhost:8080/").unwrap();
+        let db_path = dir.path().join("database.json");
+        write_database(mods, &db_path).unwrap();
+        let raw: RawRemoteDatabase =
+            serde_json::from_str(&fs::read_to_string(&db_path).unwrap()).unwrap();
+        let db = RemoteDatabase::from(raw);
+        assert!(db.get_mod("Bwc9876.SaveEditor").is_some());
+        dir.close().unwrap();
+    }
+}
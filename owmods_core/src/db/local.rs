@@ -1,22 +1,86 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    fs::{metadata, read_dir, read_to_string, write},
     path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
 };
 
 use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
 use log::{debug, warn};
+use rayon::prelude::*;
 
 use crate::{
-    file::{deserialize_from_json, fix_json_file},
-    mods::local::{FailedMod, LocalMod, ModManifest, UnsafeLocalMod},
-    search::search_list,
+    file::{deserialize_from_json, fix_json, fix_json_file},
+    mods::local::{
+        read_channel, read_install_reason, FailedMod, InstallReason, LocalMod, ModManifest,
+        ReleaseChannel, UnsafeLocalMod,
+    },
+    search::{query_list, search_list, QueryInfo, SearchQuery},
     toggle::get_mod_enabled,
     updates::check_mod_needs_update,
     validate::{check_mod, ModValidationError},
+    watcher::LocalDatabaseChange,
 };
 
 use super::{fix_version, RemoteDatabase};
 
+type ManifestStamp = (SystemTime, u64);
+
+lazy_static! {
+    // Parsed manifests keyed by their path, along with the mtime and length of the file they were parsed from
+    static ref MANIFEST_CACHE: Mutex<HashMap<PathBuf, (ManifestStamp, ModManifest)>> =
+        Mutex::new(HashMap::new());
+}
+
+fn get_stamp(path: &Path) -> Result<ManifestStamp> {
+    let meta = metadata(path)?;
+    Ok((meta.modified()?, meta.len()))
+}
+
+fn read_manifest(manifest_path: &Path) -> Result<ModManifest> {
+    let stamp = get_stamp(manifest_path)?;
+    if let Some((cached_stamp, manifest)) = MANIFEST_CACHE.lock().unwrap().get(manifest_path) {
+        if *cached_stamp == stamp {
+            return Ok(manifest.clone());
+        }
+    }
+    let txt = read_to_string(manifest_path)?;
+    let fixed = fix_json(&txt);
+    let stamp = if fixed != txt && write(manifest_path, &fixed).is_ok() {
+        get_stamp(manifest_path)?
+    } else {
+        stamp
+    };
+    let mut manifest: ModManifest = serde_json::from_str(&fixed)?;
+    manifest.version = fix_version(&manifest.version).to_string();
+    MANIFEST_CACHE
+        .lock()
+        .unwrap()
+        .insert(manifest_path.to_path_buf(), (stamp, manifest.clone()));
+    Ok(manifest)
+}
+
+/// Find every manifest under `dir`, we don't go any deeper into a folder once we find a manifest in it
+fn find_manifests(dir: &Path, manifests: &mut Vec<PathBuf>) -> Result<()> {
+    let manifest_path = dir.join("manifest.json");
+    if manifest_path.is_file() {
+        manifests.push(manifest_path);
+        return Ok(());
+    }
+    let mut sub_dirs: Vec<PathBuf> = read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    sub_dirs.sort();
+    for sub_dir in sub_dirs {
+        find_manifests(&sub_dir, manifests)?;
+    }
+    Ok(())
+}
+
 /// Represents the local (on the local PC) database of mods.
 #[derive(Default)]
 pub struct LocalDatabase {
@@ -98,6 +162,9 @@ impl LocalDatabase {
             manifest: owml_manifest,
             mod_path: owml_path.to_string(),
             errors: vec![],
+            linked: false,
+            install_reason: InstallReason::Explicit,
+            channel: ReleaseChannel::Stable,
         })
     }
 
@@ -121,14 +188,15 @@ impl LocalDatabase {
             return Err(anyhow!("Mod Path Not Found"));
         }
         let folder_path = folder_path.unwrap(); // <- Unwrap is safe, .is_none() check is above
-        fix_json_file(manifest_path).ok();
-        let mut manifest: ModManifest = deserialize_from_json(manifest_path)?;
-        manifest.version = fix_version(&manifest.version).to_string();
+        let manifest = read_manifest(manifest_path)?;
         Ok(LocalMod {
             enabled: get_mod_enabled(folder_path)?,
             manifest,
             mod_path: String::from(folder_path.to_str().unwrap()),
             errors: vec![],
+            linked: folder_path.is_symlink(),
+            install_reason: read_install_reason(folder_path),
+            channel: read_channel(folder_path),
         })
     }
 
@@ -195,6 +263,26 @@ impl LocalDatabase {
         })
     }
 
+    /// Returns an iterator over all installed addons of the given mod.
+    /// Local manifests don't say what mod they're an addon of, so the remote database is used to find out.
+    ///
+    /// ## Returns
+    ///
+    /// An iterator over all installed mods that have the given mod as their parent
+    ///
+    pub fn addons<'a>(
+        &'a self,
+        unique_name: &'a str,
+        remote_db: &'a RemoteDatabase,
+    ) -> impl Iterator<Item = &'a LocalMod> {
+        self.valid().filter(move |m| {
+            remote_db
+                .get_mod(&m.manifest.unique_name)
+                .and_then(|m| m.parent.as_ref())
+                .is_some_and(|p| p == unique_name)
+        })
+    }
+
     /// Search the database with the given query, pulls from various fields of the mod
     ///
     /// ## Returns
@@ -206,6 +294,47 @@ impl LocalDatabase {
         search_list(mods, search)
     }
 
+    /// Filter and sort the database with a [SearchQuery].
+    /// The remote database is used to get info local mods don't have, like tags and download counts.
+    ///
+    /// ## Returns
+    ///
+    /// A Vec of [UnsafeLocalMod]s that match the query, sorted by the query's sort or by name if it doesn't have one
+    ///
+    pub fn query<'a>(
+        &'a self,
+        query: &SearchQuery,
+        remote_db: Option<&'a RemoteDatabase>,
+    ) -> Vec<&'a UnsafeLocalMod> {
+        let mut mods: Vec<&UnsafeLocalMod> = self.all().collect();
+        mods.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+        query_list(mods, query, |m| {
+            let remote_mod = remote_db.and_then(|db| db.get_mod(m.get_unique_name()));
+            let local_mod = match m {
+                UnsafeLocalMod::Valid(m) => Some(m),
+                UnsafeLocalMod::Invalid(_) => None,
+            };
+            QueryInfo {
+                name: m.get_name(),
+                authors: local_mod
+                    .map(|m| vec![m.manifest.author.as_str()])
+                    .unwrap_or_default(),
+                tags: remote_mod
+                    .and_then(|m| m.tags.as_deref())
+                    .unwrap_or_default(),
+                download_count: remote_mod.map(|m| m.download_count).unwrap_or(0),
+                has_prerelease: remote_mod.is_some_and(|m| m.prerelease.is_some()),
+                has_parent: remote_mod.is_some_and(|m| m.parent.is_some()),
+                has_update: m
+                    .get_errs()
+                    .iter()
+                    .any(|e| matches!(e, ModValidationError::Outdated(_))),
+                installed: true,
+                enabled: local_mod.is_some_and(|m| m.enabled),
+            }
+        })
+    }
+
     /// Validates deps, conflicts, etc for all mods in the DB and places errors in each mods' errors Vec
     fn validate(&mut self) {
         let names: Vec<String> = self
@@ -231,54 +360,148 @@ impl LocalDatabase {
         }) {
             let (needs_update, remote) = check_mod_needs_update(local_mod, db);
             if needs_update {
-                local_mod.errors.push(ModValidationError::Outdated(
-                    remote.unwrap().version.clone(),
-                ));
+                let (version, _) = remote.unwrap().get_channel_release(local_mod.channel);
+                local_mod
+                    .errors
+                    .push(ModValidationError::Outdated(version.clone()));
             }
         }
     }
 
-    fn get_local_mods(mods_path: &Path) -> Result<HashMap<String, UnsafeLocalMod>> {
-        let mut mods: HashMap<String, UnsafeLocalMod> = HashMap::new();
-        let glob_matches =
-            glob::glob(mods_path.join("**").join("manifest.json").to_str().unwrap())?;
-        for entry in glob_matches {
-            let entry = entry?;
-            let parent = entry.parent().ok_or_else(|| anyhow!("Invalid Manifest!"))?;
-            let path = parent.to_str().unwrap().to_string();
-            let display_path = parent
-                .strip_prefix(mods_path)
-                .unwrap_or(parent)
-                .to_str()
-                .unwrap()
-                .to_string();
-            let local_mod = Self::read_local_mod(&entry);
-            if let Ok(local_mod) = local_mod {
-                if let Some(UnsafeLocalMod::Valid(other)) =
-                    mods.get(&local_mod.manifest.unique_name)
-                {
-                    let failed_mod = FailedMod {
-                        mod_path: path.to_string(),
-                        display_path,
-                        error: ModValidationError::DuplicateMod(other.mod_path.to_string()),
-                    };
-                    mods.insert(path.to_string(), UnsafeLocalMod::Invalid(failed_mod));
-                } else {
-                    mods.insert(
-                        local_mod.manifest.unique_name.to_owned(),
-                        UnsafeLocalMod::Valid(local_mod),
-                    );
-                }
+    /// Re-read only the mods affected by the given changed paths, used to keep the database live while watching the disk.
+    /// Paths can be any file in a mod, or entire mod folders that were added, moved, or deleted.
+    ///
+    /// ## Returns
+    ///
+    /// What changed in the database, if a path didn't affect any mods nothing will be returned for it.
+    ///
+    pub fn apply_changes(
+        &mut self,
+        mods_path: &Path,
+        paths: &[PathBuf],
+    ) -> Vec<LocalDatabaseChange> {
+        let known_dirs: Vec<PathBuf> = self.all().map(|m| PathBuf::from(m.get_path())).collect();
+        let mut dirs: Vec<PathBuf> = vec![];
+        for path in paths.iter().filter(|p| p.starts_with(mods_path)) {
+            // The closest folder that is or was a mod is the one that changed
+            let owner = path
+                .ancestors()
+                .take_while(|a| *a != mods_path)
+                .find(|a| a.join("manifest.json").is_file() || known_dirs.iter().any(|d| d == a));
+            if let Some(owner) = owner {
+                dirs.push(owner.to_path_buf());
+            }
+            // New folders may have been filled before we could start watching them
+            if path.is_dir() {
+                let mut manifests: Vec<PathBuf> = vec![];
+                find_manifests(path, &mut manifests).ok();
+                dirs.extend(
+                    manifests
+                        .iter()
+                        .filter_map(|m| m.parent())
+                        .map(|p| p.to_path_buf()),
+                );
+            }
+            // Folders that get moved or deleted won't report every file inside them
+            dirs.extend(known_dirs.iter().filter(|d| d.starts_with(path)).cloned());
+        }
+        dirs.sort();
+        dirs.dedup();
+        let changes: Vec<LocalDatabaseChange> = dirs
+            .iter()
+            .filter_map(|d| self.refresh_mod_dir(mods_path, d))
+            .collect();
+        if !changes.is_empty() {
+            self.validate();
+        }
+        changes
+    }
+
+    fn refresh_mod_dir(&mut self, mods_path: &Path, dir: &Path) -> Option<LocalDatabaseChange> {
+        let old_key = self
+            .mods
+            .iter()
+            .find(|(_, m)| Path::new(m.get_path()) == dir)
+            .map(|(k, _)| k.clone());
+        let old = old_key.and_then(|k| self.mods.remove(&k));
+        let manifest_path = dir.join("manifest.json");
+        if manifest_path.is_file() {
+            let local_mod = Self::read_local_mod(&manifest_path);
+            let key =
+                Self::insert_mod(&mut self.mods, mods_path, &manifest_path, local_mod).ok()?;
+            Some(if old.is_some() {
+                LocalDatabaseChange::Updated(key)
             } else {
-                let err = format!("{:?}", local_mod.err().unwrap());
-                warn!("Failed to load mod at {}: {:?}", path, err);
+                LocalDatabaseChange::Added(key)
+            })
+        } else {
+            old.map(|m| LocalDatabaseChange::Removed(m.get_unique_name().clone()))
+        }
+    }
+
+    /// Place the mod loaded from the given manifest path in `mods`, returning the key it was inserted under
+    fn insert_mod(
+        mods: &mut HashMap<String, UnsafeLocalMod>,
+        mods_path: &Path,
+        manifest_path: &Path,
+        local_mod: Result<LocalMod>,
+    ) -> Result<String> {
+        let parent = manifest_path
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid Manifest!"))?;
+        let path = parent.to_str().unwrap().to_string();
+        let display_path = parent
+            .strip_prefix(mods_path)
+            .unwrap_or(parent)
+            .to_str()
+            .unwrap()
+            .to_string();
+        if let Ok(local_mod) = local_mod {
+            if let Some(UnsafeLocalMod::Valid(other)) = mods.get(&local_mod.manifest.unique_name) {
                 let failed_mod = FailedMod {
                     mod_path: path.to_string(),
                     display_path,
-                    error: ModValidationError::InvalidManifest(err),
+                    error: ModValidationError::DuplicateMod(other.mod_path.to_string()),
                 };
                 mods.insert(path.to_string(), UnsafeLocalMod::Invalid(failed_mod));
+                Ok(path)
+            } else {
+                let key = local_mod.manifest.unique_name.to_owned();
+                mods.insert(key.clone(), UnsafeLocalMod::Valid(local_mod));
+                Ok(key)
             }
+        } else {
+            let err = format!("{:?}", local_mod.err().unwrap());
+            warn!("Failed to load mod at {}: {:?}", path, err);
+            let failed_mod = FailedMod {
+                mod_path: path.to_string(),
+                display_path,
+                error: ModValidationError::InvalidManifest(err),
+            };
+            mods.insert(path.to_string(), UnsafeLocalMod::Invalid(failed_mod));
+            Ok(path)
+        }
+    }
+
+    fn get_local_mods(mods_path: &Path) -> Result<HashMap<String, UnsafeLocalMod>> {
+        let mut mods: HashMap<String, UnsafeLocalMod> = HashMap::new();
+        let mut manifests: Vec<PathBuf> = vec![];
+        find_manifests(mods_path, &mut manifests)?;
+        // Forget mods that were deleted, manifests outside this folder are kept only while they exist so switching instances stays fast
+        let scanned: HashSet<&PathBuf> = manifests.iter().collect();
+        MANIFEST_CACHE.lock().unwrap().retain(|path, _| {
+            scanned.contains(path) || (!path.starts_with(mods_path) && path.is_file())
+        });
+        let loaded: Vec<(PathBuf, Result<LocalMod>)> = manifests
+            .into_par_iter()
+            .map(|manifest_path| {
+                let local_mod = Self::read_local_mod(&manifest_path);
+                (manifest_path, local_mod)
+            })
+            .collect();
+        // Inserting in order keeps which mod gets marked as a duplicate consistent
+        for (manifest_path, local_mod) in loaded {
+            Self::insert_mod(&mut mods, mods_path, &manifest_path, local_mod)?;
         }
         Ok(mods)
     }
@@ -287,7 +510,15 @@ impl LocalDatabase {
 #[cfg(test)]
 mod tests {
 
-    use crate::test_utils::get_test_file;
+    use std::{
+        fs::{copy, create_dir_all, remove_dir_all, write, File},
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        mods::remote::RemoteMod,
+        test_utils::{get_test_file, make_test_dir},
+    };
 
     use super::*;
 
@@ -302,6 +533,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_local_mod_install_size() {
+        let mods_path = get_test_file("");
+        let db = LocalDatabase::fetch(mods_path.to_str().unwrap()).unwrap();
+        let mut local_mod = db.get_mod("Bwc9876.TimeSaver").unwrap().clone();
+        let manifest_size = std::fs::metadata(Path::new(&local_mod.mod_path).join("manifest.json"))
+            .unwrap()
+            .len();
+        assert!(local_mod.get_install_size().unwrap() > manifest_size);
+        local_mod.mod_path = mods_path.join("Nope").to_str().unwrap().to_string();
+        assert!(local_mod.get_install_size().is_err());
+    }
+
     #[test]
     fn test_local_db_get_owml() {
         let mods_path = get_test_file("");
@@ -328,6 +572,152 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_local_db_manifest_cache() {
+        let dir = make_test_dir();
+        let manifest_path = dir.path().join("manifest.json");
+        write(
+            &manifest_path,
+            r#"{"uniqueName": "Test.Mod", "name": "Test", "author": "Test", "version": "1.0.0"}"#,
+        )
+        .unwrap();
+        assert_eq!(read_manifest(&manifest_path).unwrap().version, "1.0.0");
+        assert!(MANIFEST_CACHE.lock().unwrap().contains_key(&manifest_path));
+        let modified = metadata(&manifest_path).unwrap().modified().unwrap();
+        let set_modified = |time| {
+            File::options()
+                .write(true)
+                .open(&manifest_path)
+                .unwrap()
+                .set_modified(time)
+                .unwrap()
+        };
+
+        // The same mtime and length means the file isn't read again
+        write(
+            &manifest_path,
+            r#"{"uniqueName": "Test.Mod", "name": "Test", "author": "Test", "version": "2.0.0"}"#,
+        )
+        .unwrap();
+        set_modified(modified);
+        assert_eq!(read_manifest(&manifest_path).unwrap().version, "1.0.0");
+
+        set_modified(modified + Duration::from_secs(1));
+        assert_eq!(read_manifest(&manifest_path).unwrap().version, "2.0.0");
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_local_db_manifest_cache_evicted() {
+        let dir = make_test_dir();
+        let mod_path = dir.path().join("Mods").join("Bwc9876.TimeSaver");
+        create_dir_all(&mod_path).unwrap();
+        copy(
+            get_test_file("Mods/Bwc9876.TimeSaver/manifest.json"),
+            mod_path.join("manifest.json"),
+        )
+        .unwrap();
+        let owml_path = dir.path().to_str().unwrap();
+        LocalDatabase::fetch(owml_path).unwrap();
+        let manifest_path = mod_path.join("manifest.json");
+        assert!(MANIFEST_CACHE.lock().unwrap().contains_key(&manifest_path));
+        remove_dir_all(&mod_path).unwrap();
+        LocalDatabase::fetch(owml_path).unwrap();
+        assert!(!MANIFEST_CACHE.lock().unwrap().contains_key(&manifest_path));
+
+        // Deleted mods from other folders are forgotten too
+        create_dir_all(&mod_path).unwrap();
+        copy(
+            get_test_file("Mods/Bwc9876.TimeSaver/manifest.json"),
+            &manifest_path,
+        )
+        .unwrap();
+        LocalDatabase::fetch(owml_path).unwrap();
+        remove_dir_all(&mod_path).unwrap();
+        let other_dir = make_test_dir();
+        create_dir_all(other_dir.path().join("Mods")).unwrap();
+        LocalDatabase::fetch(other_dir.path().to_str().unwrap()).unwrap();
+        assert!(!MANIFEST_CACHE.lock().unwrap().contains_key(&manifest_path));
+        other_dir.close().unwrap();
+        dir.close().unwrap();
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with --ignored --nocapture to see the timings"]
+    fn test_local_db_scan_bench() {
+        const MOD_COUNT: usize = 200;
+        let dir = make_test_dir();
+        let mods_path = dir.path().join("Mods");
+        for i in 0..MOD_COUNT {
+            let mod_path = mods_path.join(format!("Bench.Mod{i}"));
+            // Stand-in for a big asset folder, none of this should be looked at
+            let assets_path = mod_path.join("assets").join("nested").join("deeper");
+            create_dir_all(&assets_path).unwrap();
+            for j in 0..10 {
+                write(assets_path.join(format!("asset{j}.bin")), [0_u8; 64]).unwrap();
+            }
+            write(
+                assets_path.join("manifest.json"),
+                r#"{"uniqueName": "Bench.Asset", "name": "Asset", "author": "Bench", "version": "1.0.0"}"#,
+            )
+            .unwrap();
+            write(
+                mod_path.join("manifest.json"),
+                format!(
+                    r#"{{"uniqueName": "Bench.Mod{i}", "name": "Mod {i}", "author": "Bench", "version": "1.0.0"}}"#
+                ),
+            )
+            .unwrap();
+        }
+        let owml_path = dir.path().to_str().unwrap();
+        let start = Instant::now();
+        let db = LocalDatabase::fetch(owml_path).unwrap();
+        let cold = start.elapsed();
+        assert_eq!(db.all().count(), MOD_COUNT);
+        assert!(db.get_mod("Bench.Asset").is_none());
+        let start = Instant::now();
+        let db = LocalDatabase::fetch(owml_path).unwrap();
+        let warm = start.elapsed();
+        assert_eq!(db.all().count(), MOD_COUNT);
+        println!("Scanned {MOD_COUNT} mods in {cold:?} cold and {warm:?} with the manifest cache");
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_local_db_apply_changes() {
+        let dir = make_test_dir();
+        let mods_path = dir.path().join("Mods");
+        let mod_path = mods_path.join("Bwc9876.TimeSaver");
+        let mut db = LocalDatabase::fetch(dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(db.all().count(), 0);
+        create_dir_all(&mod_path).unwrap();
+        copy(
+            get_test_file("Mods/Bwc9876.TimeSaver/manifest.json"),
+            mod_path.join("manifest.json"),
+        )
+        .unwrap();
+        let changes = db.apply_changes(&mods_path, &[mod_path.join("manifest.json")]);
+        assert!(
+            matches!(&changes[..], [LocalDatabaseChange::Added(n)] if n == "Bwc9876.TimeSaver")
+        );
+        assert!(db.get_mod("Bwc9876.TimeSaver").is_some());
+        copy(
+            get_test_file("Mods/Bwc9876.TimeSaver/config.json"),
+            mod_path.join("config.json"),
+        )
+        .unwrap();
+        let changes = db.apply_changes(&mods_path, &[mod_path.join("config.json")]);
+        assert!(matches!(&changes[..], [LocalDatabaseChange::Updated(_)]));
+        assert!(db.get_mod("Bwc9876.TimeSaver").unwrap().enabled);
+        remove_dir_all(&mod_path).unwrap();
+        let changes = db.apply_changes(&mods_path, &[mod_path]);
+        assert!(
+            matches!(&changes[..], [LocalDatabaseChange::Removed(n)] if n == "Bwc9876.TimeSaver")
+        );
+        assert_eq!(db.all().count(), 0);
+        dir.close().unwrap();
+    }
+
     #[test]
     fn test_local_db_dupe_mods() {
         let mods_path = get_test_file("Invalid");
@@ -346,4 +736,18 @@ mod tests {
             panic!("Mod valid when it shouldn't be!");
         }
     }
+
+    #[test]
+    fn test_local_db_addons() {
+        let db = LocalDatabase::fetch(get_test_file("").to_str().unwrap()).unwrap();
+        let mut remote_db = RemoteDatabase::default();
+        let mut addon = RemoteMod::get_test(1);
+        addon.unique_name = "Bwc9876.SaveEditor".to_string();
+        addon.parent = Some("Bwc9876.TimeSaver".to_string());
+        remote_db.mods.insert(addon.unique_name.clone(), addon);
+        let addons: Vec<&LocalMod> = db.addons("Bwc9876.TimeSaver", &remote_db).collect();
+        assert_eq!(addons.len(), 1);
+        assert_eq!(addons[0].manifest.unique_name, "Bwc9876.SaveEditor");
+        assert_eq!(db.addons("Bwc9876.SaveEditor", &remote_db).count(), 0);
+    }
 }
@@ -1,5 +1,10 @@
+mod build;
 mod local;
 mod remote;
+mod serve;
+
+/// Build a database from a folder of mod zips
+pub use build::{build_database, write_database};
 
 /// Work with the local database of mods
 pub use local::LocalDatabase;
@@ -7,6 +12,9 @@ pub use local::LocalDatabase;
 /// Query the remote database of mods
 pub use remote::RemoteDatabase;
 
+/// Serve a database built from a folder of mod zips over HTTP
+pub use serve::{DatabaseServer, DATABASE_SERVE_PATH};
+
 fn fix_version(version: &str) -> &str {
     version.trim_start_matches('v')
 }
@@ -1,21 +1,32 @@
-use std::collections::HashMap;
+use std::{cmp::Reverse, collections::HashMap, path::PathBuf};
 
-use anyhow::Result;
-use log::debug;
-use serde::Deserialize;
+use anyhow::{anyhow, Result};
+use futures::future::join_all;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
 
-use crate::{constants::OWML_UNIQUE_NAME, mods::remote::RemoteMod, search::search_list};
+use crate::{
+    config::{Config, DatabaseSource, Game},
+    constants::OWML_UNIQUE_NAME,
+    file::deserialize_from_json,
+    mods::remote::RemoteMod,
+    search::{query_list, search_list, QueryInfo, SearchQuery},
+    validate::ModValidationError,
+};
 
-use super::fix_version;
+use super::{fix_version, LocalDatabase};
 
 /// Used internally to construct an actual [RemoteDatabase]
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct RawRemoteDatabase {
+pub(super) struct RawRemoteDatabase {
     pub releases: Vec<RemoteMod>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alpha_releases: Vec<RemoteMod>,
 }
 
 /// Represents the remote (on the website) database of mods.
+/// A database only has the mods for one [Game], Alpha mods are kept apart so they can't be installed into the wrong OWML.
 #[derive(Default)]
 pub struct RemoteDatabase {
     pub mods: HashMap<String, RemoteMod>,
@@ -23,13 +34,59 @@ pub struct RemoteDatabase {
 
 impl From<RawRemoteDatabase> for RemoteDatabase {
     fn from(raw: RawRemoteDatabase) -> Self {
+        Self::from_raw(raw, Game::OuterWilds)
+    }
+}
+
+/// Get the path a database URL points to if it's a local file (a `file://` URL or a plain path) instead of a server
+fn get_local_path(url: &str) -> Option<PathBuf> {
+    if let Some(path) = url.strip_prefix("file://") {
+        Some(PathBuf::from(path))
+    } else if url.contains("://") {
+        None
+    } else {
+        Some(PathBuf::from(url))
+    }
+}
+
+async fn fetch_raw(url: &str) -> Result<RawRemoteDatabase> {
+    debug!("Fetching Remote DB At {}", url);
+    if let Some(path) = get_local_path(url) {
+        return deserialize_from_json(&path);
+    }
+    let resp = reqwest::get(url).await?.error_for_status()?;
+    Ok(resp.json().await?)
+}
+
+fn get_source_display(source: &DatabaseSource) -> &str {
+    if source.name.is_empty() {
+        "the default database"
+    } else {
+        &source.name
+    }
+}
+
+impl RemoteDatabase {
+    /// Make a database with the mods for `game`, mods for Alpha are either in `alphaReleases` or marked with `alpha` in `releases`.
+    /// OWML is used for both games, unless `alphaReleases` has its own.
+    fn from_raw(raw: RawRemoteDatabase, game: Game) -> Self {
+        let releases = raw.releases.into_iter().filter(|m| {
+            m.unique_name == OWML_UNIQUE_NAME
+                || match game {
+                    Game::OuterWilds => !m.is_alpha(),
+                    Game::Alpha => m.is_alpha(),
+                }
+        });
+        let alpha_releases = raw
+            .alpha_releases
+            .into_iter()
+            .filter(|_| game == Game::Alpha);
         // Creating a hash map is O(N) but access is O(1).
         // In a cli context this doesn't rly matter since we usually only get one or two mods in the entire run of the program.
         // But I'm guessing for the GUI this will help out with performance.
         // Same thing for the local DB.
-        let mut mods = raw
-            .releases
-            .into_iter()
+        let mut mods = releases
+            .chain(alpha_releases)
             .map(|m| (m.unique_name.to_owned(), m))
             .collect::<HashMap<_, _>>();
 
@@ -38,10 +95,8 @@ impl From<RawRemoteDatabase> for RemoteDatabase {
         }
         Self { mods }
     }
-}
 
-impl RemoteDatabase {
-    /// Fetch the database of remote mods.
+    /// Fetch the database of remote mods, `url` can also be a path to a local file.
     ///
     /// ## Returns
     ///
@@ -52,14 +107,12 @@ impl RemoteDatabase {
     /// If we can't fetch the JSON file for whatever reason.
     ///
     pub async fn fetch(url: &str) -> Result<RemoteDatabase> {
-        debug!("Fetching Remote DB At {}", url);
-        let resp = reqwest::get(url).await?;
-        let raw_db: RawRemoteDatabase = resp.json().await?;
+        let raw_db = fetch_raw(url).await?;
         debug!("Success, Constructing Remote Mod Map");
         Ok(Self::from(raw_db))
     }
 
-    /// Fetch the database but block the current thread while doing so
+    /// Fetch the database but block the current thread while doing so, `url` can also be a path to a local file.
     ///
     /// ## Returns
     ///
@@ -71,12 +124,78 @@ impl RemoteDatabase {
     ///
     pub fn fetch_blocking(url: &str) -> Result<RemoteDatabase> {
         debug!("Fetching Remote DB At {}", url);
-        let resp = reqwest::blocking::get(url)?;
-        let raw_db: RawRemoteDatabase = resp.json()?;
+        let raw_db: RawRemoteDatabase = if let Some(path) = get_local_path(url) {
+            deserialize_from_json(&path)?
+        } else {
+            reqwest::blocking::get(url)?.error_for_status()?.json()?
+        };
         debug!("Success, Constructing Remote Mod Map");
         Ok(Self::from(raw_db))
     }
 
+    /// Fetch every database source in the config and merge them into one database, see [Config::get_database_sources].
+    /// Only mods for the selected game ([Config::game]) are included.
+    /// If a mod is in more than one source the one from the source with the highest priority is used, the other copies are ignored.
+    /// Sources that can't be fetched are skipped with a warning.
+    ///
+    /// ## Returns
+    ///
+    /// The merged database, mods from sources other than the default database have [RemoteMod::source] set.
+    ///
+    /// ## Errors
+    ///
+    /// If no sources are enabled or none of them could be fetched.
+    ///
+    pub async fn fetch_from_config(config: &Config) -> Result<RemoteDatabase> {
+        let sources = config.get_database_sources();
+        if sources.is_empty() {
+            return Err(anyhow!("No database sources are enabled"));
+        }
+        let results = join_all(sources.iter().map(|s| fetch_raw(&s.url))).await;
+        let mut fetched = vec![];
+        let mut last_error = None;
+        for (source, result) in sources.into_iter().zip(results) {
+            match result {
+                Ok(raw_db) => fetched.push((source, raw_db)),
+                Err(why) => {
+                    warn!(
+                        "Couldn't fetch {}, skipping it: {:?}",
+                        get_source_display(&source),
+                        why
+                    );
+                    last_error = Some(why);
+                }
+            }
+        }
+        match last_error {
+            Some(why) if fetched.is_empty() => Err(why),
+            _ => Ok(Self::from_sources(fetched, config.get_game())),
+        }
+    }
+
+    /// Merge databases from multiple sources, `sources` should be sorted highest priority first
+    fn from_sources(sources: Vec<(DatabaseSource, RawRemoteDatabase)>, game: Game) -> Self {
+        let mut mods: HashMap<String, RemoteMod> = HashMap::new();
+        let mut used_sources: HashMap<String, String> = HashMap::new();
+        for (source, raw_db) in sources {
+            let display = get_source_display(&source).to_string();
+            let source_name = (!source.name.is_empty()).then_some(source.name);
+            for (unique_name, mut remote_mod) in Self::from_raw(raw_db, game).mods {
+                if let Some(used) = used_sources.get(&unique_name) {
+                    debug!(
+                        "Ignoring {} from {}, using the one from {}",
+                        unique_name, display, used
+                    );
+                    continue;
+                }
+                remote_mod.source = source_name.clone();
+                used_sources.insert(unique_name.clone(), display.clone());
+                mods.insert(unique_name, remote_mod);
+            }
+        }
+        Self { mods }
+    }
+
     /// Get a mod by unique name, **will not return OWML**.
     ///
     /// ## Returns
@@ -100,6 +219,31 @@ impl RemoteDatabase {
         self.mods.get(OWML_UNIQUE_NAME)
     }
 
+    /// Get the mods the database marks as required, these should always be installed.
+    /// OWML isn't included even if it's marked.
+    ///
+    /// ## Returns
+    ///
+    /// An iterator over the required mods
+    ///
+    pub fn required(&self) -> impl Iterator<Item = &RemoteMod> {
+        self.mods
+            .values()
+            .filter(|m| m.is_required() && m.unique_name != OWML_UNIQUE_NAME)
+    }
+
+    /// Returns an iterator over all addons of the given mod
+    ///
+    /// ## Returns
+    ///
+    /// An iterator over all mods in the database that have the given mod as their parent
+    ///
+    pub fn addons<'a>(&'a self, unique_name: &'a str) -> impl Iterator<Item = &'a RemoteMod> {
+        self.mods
+            .values()
+            .filter(move |m| m.parent.as_ref().is_some_and(|p| p == unique_name))
+    }
+
     /// Search the database with the given query, pulls from various fields of the mod
     ///
     /// ## Returns
@@ -110,21 +254,80 @@ impl RemoteDatabase {
         let mods: Vec<&RemoteMod> = self.mods.values().collect();
         search_list(mods, search)
     }
+
+    /// Filter and sort the database with a [SearchQuery], OWML is never included.
+    /// The local database is used to check if mods are installed, enabled, or have updates.
+    ///
+    /// ## Returns
+    ///
+    /// A Vec of [RemoteMod]s that match the query, sorted by the query's sort or by downloads if it doesn't have one
+    ///
+    pub fn query<'a>(
+        &'a self,
+        query: &SearchQuery,
+        local_db: Option<&'a LocalDatabase>,
+    ) -> Vec<&'a RemoteMod> {
+        let mut mods: Vec<&RemoteMod> = self
+            .mods
+            .values()
+            .filter(|m| m.unique_name != OWML_UNIQUE_NAME)
+            .collect();
+        mods.sort_by_key(|m| Reverse(m.download_count));
+        query_list(mods, query, |m| {
+            let local_mod = local_db.and_then(|db| db.get_mod(&m.unique_name));
+            QueryInfo {
+                name: &m.name,
+                authors: vec![&m.author, m.get_author()],
+                tags: m.tags.as_deref().unwrap_or_default(),
+                download_count: m.download_count,
+                has_prerelease: m.prerelease.is_some(),
+                has_parent: m.parent.is_some(),
+                has_update: local_mod.is_some_and(|local_mod| {
+                    local_mod
+                        .errors
+                        .iter()
+                        .any(|e| matches!(e, ModValidationError::Outdated(_)))
+                }),
+                installed: local_mod.is_some(),
+                enabled: local_mod.is_some_and(|m| m.enabled),
+            }
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use crate::constants::DEFAULT_DB_URL;
+    use crate::{
+        file::serialize_to_json,
+        test_utils::{make_test_dir, MockFailure, MockServer},
+    };
 
     use super::*;
 
     #[test]
     fn test_remote_db_fetch() {
+        let server = MockServer::start();
         tokio_test::block_on(async {
-            let db = RemoteDatabase::fetch(DEFAULT_DB_URL).await.unwrap();
-            // Yes this will make all tests depend on my mod existing, I win!
+            let db = RemoteDatabase::fetch(&server.database_url()).await.unwrap();
             assert!(db.get_mod("Bwc9876.TimeSaver").is_some());
+            assert!(db.get_owml().is_some());
+        });
+        let db = RemoteDatabase::fetch_blocking(&server.database_url()).unwrap();
+        assert!(db.get_mod("Bwc9876.SaveEditor").is_some());
+    }
+
+    #[test]
+    fn test_remote_db_fetch_failures() {
+        let server = MockServer::start();
+        server.fail("database.json", MockFailure::Status(500));
+        tokio_test::block_on(async {
+            assert!(RemoteDatabase::fetch(&server.database_url()).await.is_err());
+        });
+        assert!(RemoteDatabase::fetch_blocking(&server.database_url()).is_err());
+        server.fail("database.json", MockFailure::Truncated);
+        tokio_test::block_on(async {
+            assert!(RemoteDatabase::fetch(&server.database_url()).await.is_err());
         });
     }
 
@@ -134,6 +337,7 @@ mod tests {
         let mod2 = RemoteMod::get_test(2);
         let raw_db = RawRemoteDatabase {
             releases: vec![mod1, mod2],
+            alpha_releases: vec![],
         };
         let db = RemoteDatabase::from(raw_db);
         assert_eq!(db.mods.len(), 2);
@@ -141,13 +345,180 @@ mod tests {
         assert!(db.get_mod("Example.TestMod2").is_some());
     }
 
+    fn make_source(name: &str, url: &str, priority: i32) -> DatabaseSource {
+        DatabaseSource {
+            name: name.to_string(),
+            url: url.to_string(),
+            priority,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_remote_db_from_sources() {
+        let mut team_mod = RemoteMod::get_test(1);
+        team_mod.version = "2.0.0".to_string();
+        let team = RawRemoteDatabase {
+            releases: vec![team_mod],
+            alpha_releases: vec![],
+        };
+        let public = RawRemoteDatabase {
+            releases: vec![RemoteMod::get_test(1), RemoteMod::get_test(2)],
+            alpha_releases: vec![],
+        };
+        let db = RemoteDatabase::from_sources(
+            vec![
+                (make_source("Team", "", 10), team),
+                (make_source("", "", 0), public),
+            ],
+            Game::OuterWilds,
+        );
+        assert_eq!(db.mods.len(), 2);
+        let mod1 = db.get_mod("Example.TestMod1").unwrap();
+        assert_eq!(mod1.version, "2.0.0");
+        assert_eq!(mod1.source.as_deref(), Some("Team"));
+        assert!(db.get_mod("Example.TestMod2").unwrap().source.is_none());
+    }
+
+    #[test]
+    fn test_remote_db_fetch_from_config() {
+        let dir = make_test_dir();
+        let team_path = dir.path().join("team.json");
+        let mut team_mod = RemoteMod::get_test(3);
+        team_mod.source = Some("Ignored".to_string());
+        serialize_to_json(
+            &RawRemoteDatabase {
+                releases: vec![team_mod],
+                alpha_releases: vec![],
+            },
+            &team_path,
+            false,
+        )
+        .unwrap();
+        let public_path = dir.path().join("public.json");
+        serialize_to_json(
+            &RawRemoteDatabase {
+                releases: vec![RemoteMod::get_test(1)],
+                alpha_releases: vec![],
+            },
+            &public_path,
+            false,
+        )
+        .unwrap();
+        let mut config = Config::default(Some(dir.path().join("settings.json"))).unwrap();
+        config.database_url = format!("file://{}", public_path.to_str().unwrap());
+        config.database_sources = vec![
+            make_source("Team", team_path.to_str().unwrap(), 1),
+            make_source("Missing", dir.path().join("nope.json").to_str().unwrap(), 2),
+        ];
+        let db = tokio_test::block_on(RemoteDatabase::fetch_from_config(&config)).unwrap();
+        assert_eq!(db.mods.len(), 2);
+        assert_eq!(
+            db.get_mod("Example.TestMod3").unwrap().source.as_deref(),
+            Some("Team")
+        );
+        assert!(db.get_mod("Example.TestMod1").unwrap().source.is_none());
+
+        config.database_url = String::new();
+        config.database_sources.remove(0);
+        assert!(tokio_test::block_on(RemoteDatabase::fetch_from_config(&config)).is_err());
+        config.database_sources.clear();
+        assert!(tokio_test::block_on(RemoteDatabase::fetch_from_config(&config)).is_err());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_remote_db_games() {
+        let mut marked_alpha: RemoteMod = serde_json::from_str(
+            r#"{ "downloadUrl": "", "downloadCount": 0, "version": "0.1.0", "name": "Alpha Mod",
+            "uniqueName": "Example.Alpha", "description": "", "slug": "", "repo": "", "author": "",
+            "alpha": true, "required": true }"#,
+        )
+        .unwrap();
+        assert!(marked_alpha.is_alpha());
+        assert!(marked_alpha.is_required());
+        marked_alpha.unique_name = "Example.MarkedAlpha".to_string();
+        let mut owml = RemoteMod::get_test(3);
+        owml.unique_name = OWML_UNIQUE_NAME.to_string();
+        let raw = || RawRemoteDatabase {
+            releases: vec![RemoteMod::get_test(1), marked_alpha.clone(), owml.clone()],
+            alpha_releases: vec![RemoteMod::get_test(2)],
+        };
+        let db = RemoteDatabase::from_raw(raw(), Game::OuterWilds);
+        assert_eq!(db.mods.len(), 2);
+        assert!(db.get_owml().is_some());
+        assert!(db.get_mod("Example.TestMod1").is_some());
+        assert_eq!(db.required().count(), 0);
+        let db = RemoteDatabase::from_raw(raw(), Game::Alpha);
+        assert_eq!(db.mods.len(), 3);
+        assert!(db.get_owml().is_some());
+        assert!(db.get_mod("Example.MarkedAlpha").is_some());
+        assert!(db.get_mod("Example.TestMod2").is_some());
+        let required = db
+            .required()
+            .map(|m| m.unique_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(required, vec!["Example.MarkedAlpha"]);
+    }
+
     #[test]
     fn test_remote_db_get_owml() {
         let mut mod1 = RemoteMod::get_test(1);
         mod1.unique_name = OWML_UNIQUE_NAME.to_string();
         let db = RemoteDatabase::from(RawRemoteDatabase {
             releases: vec![mod1],
+            alpha_releases: vec![],
         });
         assert!(db.get_mod(OWML_UNIQUE_NAME).is_none());
     }
+
+    #[test]
+    fn test_remote_db_query() {
+        let mut mod1 = RemoteMod::get_test(1);
+        mod1.download_count = 5;
+        mod1.tags = Some(vec!["Story".to_string()]);
+        let mut mod2 = RemoteMod::get_test(2);
+        mod2.download_count = 10;
+        mod2.tags = Some(vec!["Story".to_string()]);
+        mod2.author = "Xen".to_string();
+        let mut mod3 = RemoteMod::get_test(3);
+        mod3.download_count = 20;
+        let db = RemoteDatabase::from(RawRemoteDatabase {
+            releases: vec![mod1, mod2, mod3],
+            alpha_releases: vec![],
+        });
+        let names = |query: &str| -> Vec<String> {
+            db.query(&SearchQuery::parse(query), None)
+                .into_iter()
+                .map(|m| m.unique_name.clone())
+                .collect()
+        };
+        assert_eq!(
+            names("tag:story"),
+            vec!["Example.TestMod2", "Example.TestMod1"]
+        );
+        assert_eq!(
+            names("tag:story sort:name"),
+            vec!["Example.TestMod1", "Example.TestMod2"]
+        );
+        assert_eq!(names("author:xe"), vec!["Example.TestMod2"]);
+        assert_eq!(names("-tag:story"), vec!["Example.TestMod3"]);
+        assert!(names("installed").is_empty());
+        assert_eq!(names("-installed").len(), 3);
+    }
+
+    #[test]
+    fn test_remote_db_addons() {
+        let mod1 = RemoteMod::get_test(1);
+        let mut mod2 = RemoteMod::get_test(2);
+        mod2.parent = Some("Example.TestMod1".to_string());
+        let db = RemoteDatabase::from(RawRemoteDatabase {
+            releases: vec![mod1, mod2],
+            alpha_releases: vec![],
+        });
+        let addons: Vec<&RemoteMod> = db.addons("Example.TestMod1").collect();
+        assert_eq!(addons.len(), 1);
+        assert_eq!(addons[0].unique_name, "Example.TestMod2");
+        assert_eq!(db.addons("Example.TestMod2").count(), 0);
+    }
 }
@@ -0,0 +1,292 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    thread,
+};
+
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+
+use super::{build::build_database, remote::RawRemoteDatabase};
+
+/// The path the database is served at
+pub const DATABASE_SERVE_PATH: &str = "database.json";
+
+/// A tiny HTTP server that serves a database built from a folder of mod zips, along with the zips themselves.
+/// The database is rebuilt on every request so zips can be added or replaced while the server is running.
+/// This only listens on localhost and is meant for testing mods and small private registries.
+pub struct DatabaseServer {
+    listener: TcpListener,
+    zips_path: PathBuf,
+}
+
+impl DatabaseServer {
+    /// Bind a new server to the given port on localhost, pass `0` to let the OS pick a free port
+    ///
+    /// ## Errors
+    ///
+    /// If we can't bind to the port.
+    ///
+    pub fn new(zips_path: &Path, port: u16) -> Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        Ok(Self {
+            listener,
+            zips_path: zips_path.to_path_buf(),
+        })
+    }
+
+    /// Get the port the server is listening on
+    ///
+    /// ## Errors
+    ///
+    /// If the listener's address can't be determined.
+    ///
+    pub fn port(&self) -> Result<u16> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    /// Get the URL that the database is served at, this can be used as the `database_url` in the config
+    ///
+    /// ## Errors
+    ///
+    /// If the listener's address can't be determined.
+    ///
+    pub fn database_url(&self) -> Result<String> {
+        Ok(format!("{}{}", self.base_url()?, DATABASE_SERVE_PATH))
+    }
+
+    fn base_url(&self) -> Result<String> {
+        Ok(format!("http://127.0.0.1:{}/", self.port()?))
+    }
+
+    /// Serve requests, this blocks the current thread forever.
+    /// Each connection is handled on its own thread so parallel downloads work.
+    ///
+    /// ## Errors
+    ///
+    /// If the listener's address can't be determined.
+    ///
+    pub fn serve(&self) -> Result<()> {
+        let base_url = self.base_url()?;
+        for stream in self.listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let zips_path = self.zips_path.clone();
+                    let base_url = base_url.clone();
+                    thread::spawn(move || {
+                        if let Err(why) = handle_connection(stream, &zips_path, &base_url) {
+                            warn!("Error handling request: {:?}", why);
+                        }
+                    });
+                }
+                Err(why) => warn!("Couldn't accept connection: {:?}", why),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn decode_path(path: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut chars = path.bytes();
+    while let Some(b) = chars.next() {
+        if b == b'%' {
+            let hex = [chars.next()?, chars.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Get the zip a request path points to, only files directly in the folder can be served
+fn resolve_zip(zips_path: &Path, path: &str) -> Option<PathBuf> {
+    let name = decode_path(path.strip_prefix('/')?)?;
+    if name.is_empty()
+        || name.contains('/')
+        || name.contains('\\')
+        || name.contains("..")
+        || !name.ends_with(".zip")
+    {
+        return None;
+    }
+    let zip_path = zips_path.join(name);
+    zip_path.is_file().then_some(zip_path)
+}
+
+fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    len: u64,
+    head_only: bool,
+    body: &mut dyn Read,
+) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {len}\r\nConnection: close\r\n\r\n"
+    )?;
+    if !head_only {
+        std::io::copy(body, stream)?;
+    }
+    stream.flush()?;
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream, zips_path: &Path, base_url: &str) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // We don't care about any headers, but they need to be read before responding
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().ok_or_else(|| anyhow!("Empty request"))?;
+    let path = parts.next().ok_or_else(|| anyhow!("No path in request"))?;
+    let path = path.split('?').next().unwrap_or(path);
+    debug!("{} {}", method, path);
+
+    let head_only = method == "HEAD";
+    if method != "GET" && !head_only {
+        let msg = b"Method Not Allowed";
+        return write_response(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            msg.len() as u64,
+            head_only,
+            &mut &msg[..],
+        );
+    }
+
+    if path == "/" || path.trim_start_matches('/') == DATABASE_SERVE_PATH {
+        match build_database(zips_path, base_url) {
+            Ok(releases) => {
+                info!("Serving database with {} mods", releases.len());
+                let body = serde_json::to_vec(&RawRemoteDatabase {
+                    releases,
+                    alpha_releases: vec![],
+                })?;
+                write_response(
+                    &mut stream,
+                    "200 OK",
+                    "application/json",
+                    body.len() as u64,
+                    head_only,
+                    &mut &body[..],
+                )
+            }
+            Err(why) => {
+                warn!("Couldn't build database: {:?}", why);
+                let msg = format!("Couldn't build database: {why}");
+                write_response(
+                    &mut stream,
+                    "500 Internal Server Error",
+                    "text/plain",
+                    msg.len() as u64,
+                    head_only,
+                    &mut msg.as_bytes(),
+                )
+            }
+        }
+    } else if let Some(zip_path) = resolve_zip(zips_path, path) {
+        info!("Serving {}", zip_path.display());
+        let mut file = File::open(&zip_path)?;
+        let len = file.metadata()?.len();
+        write_response(
+            &mut stream,
+            "200 OK",
+            "application/zip",
+            len,
+            head_only,
+            &mut file,
+        )
+    } else {
+        let msg = b"Not Found";
+        write_response(
+            &mut stream,
+            "404 Not Found",
+            "text/plain",
+            msg.len() as u64,
+            head_only,
+            &mut &msg[..],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+
+    use super::*;
+    use crate::{
+        config::Config,
+        db::{LocalDatabase, RemoteDatabase},
+        download::install_mod_from_db,
+        test_utils::{get_test_file, make_test_dir},
+    };
+
+    #[test]
+    fn test_resolve_zip() {
+        let dir = make_test_dir();
+        fs::copy(
+            get_test_file("Bwc9876.TimeSaver.zip"),
+            dir.path().join("Bwc9876.TimeSaver.zip"),
+        )
+        .unwrap();
+        assert!(resolve_zip(dir.path(), "/Bwc9876.TimeSaver.zip").is_some());
+        assert!(resolve_zip(dir.path(), "/Bwc9876%2ETimeSaver.zip").is_some());
+        assert!(resolve_zip(dir.path(), "/../Bwc9876.TimeSaver.zip").is_none());
+        assert!(resolve_zip(dir.path(), "/..%2FBwc9876.TimeSaver.zip").is_none());
+        assert!(resolve_zip(dir.path(), "/Missing.zip").is_none());
+        assert!(resolve_zip(dir.path(), "/").is_none());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_serve_and_install() {
+        let zips_dir = make_test_dir();
+        fs::copy(
+            get_test_file("Bwc9876.TimeSaver.zip"),
+            zips_dir.path().join("Bwc9876.TimeSaver.zip"),
+        )
+        .unwrap();
+        let server = DatabaseServer::new(zips_dir.path(), 0).unwrap();
+        let url = server.database_url().unwrap();
+        thread::spawn(move || server.serve());
+
+        let remote_db = RemoteDatabase::fetch_blocking(&url).unwrap();
+        let remote_mod = remote_db.get_mod("Bwc9876.TimeSaver").unwrap();
+        assert!(remote_mod.download_url.starts_with("http://127.0.0.1:"));
+
+        let owml_dir = make_test_dir();
+        let mut config = Config::default(None).unwrap();
+        config.owml_path = owml_dir.path().to_str().unwrap().to_string();
+        tokio_test::block_on(install_mod_from_db(
+            &"Bwc9876.TimeSaver".to_string(),
+            &config,
+            &remote_db,
+            &LocalDatabase::default(),
+            false,
+            false,
+        ))
+        .unwrap();
+        assert!(owml_dir
+            .path()
+            .join("Mods")
+            .join("Bwc9876.TimeSaver")
+            .join("manifest.json")
+            .is_file());
+        owml_dir.close().unwrap();
+        zips_dir.close().unwrap();
+    }
+}
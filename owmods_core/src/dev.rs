@@ -0,0 +1,756 @@
+use std::{
+    ffi::OsStr,
+    fmt::Display,
+    fs::{read_dir, rename, File},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use typeshare::typeshare;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    config::Config,
+    constants::LINKED_BACKUPS_FOLDER_NAME,
+    db::LocalDatabase,
+    file::create_all_parents,
+    mods::local::{LocalMod, ModManifest},
+    version::Version,
+};
+
+const REQUIRED_MANIFEST_FIELDS: [&str; 4] = ["uniqueName", "name", "author", "version"];
+const KNOWN_SETTING_TYPES: [&str; 6] =
+    ["toggle", "selector", "slider", "text", "input", "separator"];
+
+/// How serious a problem found in a mod folder is
+#[typeshare]
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiagnosticLevel {
+    /// The mod will work, but something is probably wrong
+    Warning,
+    /// The mod won't install or work correctly
+    Error,
+}
+
+/// A problem found when validating a mod folder
+#[typeshare]
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    /// How serious the problem is
+    pub level: DiagnosticLevel,
+    /// The file the problem is in, relative to the mod folder
+    pub file: String,
+    /// What the problem is
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(file: &str, message: String) -> Self {
+        Self {
+            level: DiagnosticLevel::Error,
+            file: file.to_string(),
+            message,
+        }
+    }
+
+    fn warning(file: &str, message: String) -> Self {
+        Self {
+            level: DiagnosticLevel::Warning,
+            file: file.to_string(),
+            message,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let level = match self.level {
+            DiagnosticLevel::Warning => "warning",
+            DiagnosticLevel::Error => "error",
+        };
+        write!(f, "{}: {}: {}", level, self.file, self.message)
+    }
+}
+
+fn read_json(path: &Path) -> std::result::Result<Value, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|e| e.to_string())
+}
+
+fn check_version(
+    version: &str,
+    field: &str,
+    level: DiagnosticLevel,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    // Use the same parsing as updates, so anything that passes here can be compared
+    if version.parse::<Version>().is_err() {
+        diagnostics.push(Diagnostic {
+            level,
+            file: "manifest.json".to_string(),
+            message: format!("`{field}` \"{version}\" isn't a valid version (ex: \"1.0.0\")"),
+        });
+    } else if let Some(stripped) = version.strip_prefix('v') {
+        diagnostics.push(Diagnostic::warning(
+            "manifest.json",
+            format!("`{field}` shouldn't start with a \"v\", use \"{stripped}\" instead"),
+        ));
+    }
+}
+
+fn check_mod_list(
+    manifest: &ModManifest,
+    list: &Option<Vec<String>>,
+    field: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut seen: Vec<&String> = vec![];
+    for name in list.iter().flatten() {
+        if name.trim().is_empty() || name.contains(char::is_whitespace) {
+            diagnostics.push(Diagnostic::error(
+                "manifest.json",
+                format!("`{field}` has a malformed unique name \"{name}\""),
+            ));
+        } else if *name == manifest.unique_name {
+            diagnostics.push(Diagnostic::error(
+                "manifest.json",
+                format!("`{field}` can't contain the mod itself"),
+            ));
+        } else if seen.contains(&name) {
+            diagnostics.push(Diagnostic::warning(
+                "manifest.json",
+                format!("`{field}` lists \"{name}\" more than once"),
+            ));
+        }
+        seen.push(name);
+    }
+}
+
+fn check_manifest(mod_path: &Path, diagnostics: &mut Vec<Diagnostic>) {
+    let manifest_path = mod_path.join("manifest.json");
+    if !manifest_path.is_file() {
+        diagnostics.push(Diagnostic::error(
+            "manifest.json",
+            "No manifest.json found at the root of the mod folder".to_string(),
+        ));
+        return;
+    }
+    let raw = match read_json(&manifest_path) {
+        Ok(raw) => raw,
+        Err(why) => {
+            diagnostics.push(Diagnostic::error(
+                "manifest.json",
+                format!("Invalid JSON: {why}"),
+            ));
+            return;
+        }
+    };
+    if !raw.is_object() {
+        diagnostics.push(Diagnostic::error(
+            "manifest.json",
+            "Manifest should be a JSON object".to_string(),
+        ));
+        return;
+    }
+    let mut valid = true;
+    for field in REQUIRED_MANIFEST_FIELDS {
+        let message = match raw.get(field) {
+            None => format!("Missing required field `{field}`"),
+            Some(Value::String(value)) if !value.trim().is_empty() => continue,
+            Some(_) => format!("`{field}` should be a non-empty string"),
+        };
+        diagnostics.push(Diagnostic::error("manifest.json", message));
+        valid = false;
+    }
+    if !valid {
+        return;
+    }
+    let manifest: ModManifest = match serde_json::from_value(raw) {
+        Ok(manifest) => manifest,
+        Err(why) => {
+            diagnostics.push(Diagnostic::error(
+                "manifest.json",
+                format!("Invalid manifest: {why}"),
+            ));
+            return;
+        }
+    };
+
+    if manifest.unique_name.contains(char::is_whitespace) {
+        diagnostics.push(Diagnostic::error(
+            "manifest.json",
+            "`uniqueName` can't contain whitespace".to_string(),
+        ));
+    } else if !manifest.unique_name.contains('.') {
+        diagnostics.push(Diagnostic::warning(
+            "manifest.json",
+            "`uniqueName` should be in the form \"Author.ModName\"".to_string(),
+        ));
+    }
+
+    check_version(
+        &manifest.version,
+        "version",
+        DiagnosticLevel::Error,
+        diagnostics,
+    );
+    if let Some(owml_version) = manifest.owml_version.as_ref() {
+        check_version(
+            owml_version,
+            "owmlVersion",
+            DiagnosticLevel::Warning,
+            diagnostics,
+        );
+    }
+
+    if let Some(filename) = manifest.filename.as_ref() {
+        if !mod_path.join(filename).is_file() {
+            diagnostics.push(Diagnostic::error(
+                "manifest.json",
+                format!("`filename` points to \"{filename}\", which doesn't exist"),
+            ));
+        } else if Path::new(filename).extension() != Some(OsStr::new("dll")) {
+            diagnostics.push(Diagnostic::warning(
+                "manifest.json",
+                format!("`filename` \"{filename}\" isn't a DLL"),
+            ));
+        }
+    }
+
+    check_mod_list(
+        &manifest,
+        &manifest.dependencies,
+        "dependencies",
+        diagnostics,
+    );
+    check_mod_list(&manifest, &manifest.conflicts, "conflicts", diagnostics);
+    for dep in manifest.dependencies.iter().flatten() {
+        if manifest.conflicts.iter().flatten().any(|c| c == dep) {
+            diagnostics.push(Diagnostic::error(
+                "manifest.json",
+                format!("\"{dep}\" is both a dependency and a conflict"),
+            ));
+        }
+    }
+}
+
+fn check_setting(name: &str, setting: &Value, diagnostics: &mut Vec<Diagnostic>) {
+    const FILE: &str = "default-config.json";
+    let setting = match setting {
+        Value::Bool(_) | Value::Number(_) | Value::String(_) => return,
+        Value::Object(setting) => setting,
+        _ => {
+            diagnostics.push(Diagnostic::warning(
+                FILE,
+                format!("Setting \"{name}\" should be a value or an object with a `type`"),
+            ));
+            return;
+        }
+    };
+    let setting_type = match setting.get("type").and_then(|t| t.as_str()) {
+        Some(setting_type) => setting_type,
+        None => {
+            diagnostics.push(Diagnostic::error(
+                FILE,
+                format!("Setting \"{name}\" is missing a `type`"),
+            ));
+            return;
+        }
+    };
+    if !KNOWN_SETTING_TYPES.contains(&setting_type) {
+        diagnostics.push(Diagnostic::warning(
+            FILE,
+            format!("Setting \"{name}\" has an unknown type \"{setting_type}\""),
+        ));
+    }
+    let value = setting.get("value");
+    if value.is_none() && setting_type != "separator" {
+        diagnostics.push(Diagnostic::error(
+            FILE,
+            format!("Setting \"{name}\" is missing a `value`"),
+        ));
+    }
+    match setting_type {
+        "selector" => {
+            let options: Option<Vec<&str>> = setting
+                .get("options")
+                .and_then(|o| o.as_array())
+                .and_then(|o| o.iter().map(|o| o.as_str()).collect());
+            match options {
+                Some(options) if !options.is_empty() => {
+                    if let Some(value) = value.and_then(|v| v.as_str()) {
+                        if !options.contains(&value) {
+                            diagnostics.push(Diagnostic::warning(
+                                FILE,
+                                format!(
+                                    "Setting \"{name}\" has a value that isn't one of its options"
+                                ),
+                            ));
+                        }
+                    }
+                }
+                _ => diagnostics.push(Diagnostic::error(
+                    FILE,
+                    format!("Selector \"{name}\" needs `options` to be a list of strings"),
+                )),
+            }
+        }
+        "slider" => {
+            let min = setting.get("min").and_then(|m| m.as_f64());
+            let max = setting.get("max").and_then(|m| m.as_f64());
+            if let (Some(min), Some(max)) = (min, max) {
+                let value = value.and_then(|v| v.as_f64());
+                if min > max || value.is_some_and(|v| v < min || v > max) {
+                    diagnostics.push(Diagnostic::warning(
+                        FILE,
+                        format!("Slider \"{name}\" has a value outside of its range"),
+                    ));
+                }
+            } else {
+                diagnostics.push(Diagnostic::error(
+                    FILE,
+                    format!("Slider \"{name}\" needs a numeric `min` and `max`"),
+                ));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_default_config(mod_path: &Path, diagnostics: &mut Vec<Diagnostic>) {
+    const FILE: &str = "default-config.json";
+    let config_path = mod_path.join(FILE);
+    if !config_path.is_file() {
+        return;
+    }
+    let config = match read_json(&config_path) {
+        Ok(Value::Object(config)) => config,
+        Ok(_) => {
+            diagnostics.push(Diagnostic::error(
+                FILE,
+                "Config should be a JSON object".to_string(),
+            ));
+            return;
+        }
+        Err(why) => {
+            diagnostics.push(Diagnostic::error(FILE, format!("Invalid JSON: {why}")));
+            return;
+        }
+    };
+    if config.get("enabled").is_some_and(|e| !e.is_boolean()) {
+        diagnostics.push(Diagnostic::error(
+            FILE,
+            "`enabled` should be true or false".to_string(),
+        ));
+    }
+    match config.get("settings") {
+        Some(Value::Object(settings)) => {
+            for (name, setting) in settings.iter() {
+                check_setting(name, setting, diagnostics);
+            }
+        }
+        Some(_) => diagnostics.push(Diagnostic::error(
+            FILE,
+            "`settings` should be an object".to_string(),
+        )),
+        None => {}
+    }
+}
+
+// Files that shouldn't end up in a packed mod, `config.json` is generated from `default-config.json` on install
+fn should_pack(rel_path: &Path) -> bool {
+    let hidden = rel_path
+        .components()
+        .any(|c| c.as_os_str().to_str().is_some_and(|c| c.starts_with('.')));
+    !hidden && rel_path != Path::new("config.json")
+}
+
+fn collect_files(mod_path: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries: Vec<PathBuf> = read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        // Unwrap is safe, everything we walk is under mod_path
+        let rel_path = path.strip_prefix(mod_path).unwrap().to_path_buf();
+        if !should_pack(&rel_path) {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(mod_path, &path, files)?;
+        } else {
+            files.push(rel_path);
+        }
+    }
+    Ok(())
+}
+
+/// Validate a mod folder, checking the manifest, the DLL it points to, and `default-config.json`
+///
+/// ## Returns
+///
+/// Every problem found, if none of them are errors the mod is safe to pack.
+///
+/// ## Errors
+///
+/// If we can't read the mod folder.
+///
+pub fn validate_mod_folder(mod_path: &Path) -> Result<Vec<Diagnostic>> {
+    let mut diagnostics: Vec<Diagnostic> = vec![];
+    check_manifest(mod_path, &mut diagnostics);
+    check_default_config(mod_path, &mut diagnostics);
+    if mod_path.join("config.json").is_file() {
+        diagnostics.push(Diagnostic::warning(
+            "config.json",
+            "config.json won't be packed, it's generated from default-config.json on install"
+                .to_string(),
+        ));
+    }
+    let mut files: Vec<PathBuf> = vec![];
+    collect_files(mod_path, mod_path, &mut files)?;
+    for nested in files.iter().filter(|f| {
+        f.file_name() == Some(OsStr::new("manifest.json")) && f.parent() != Some(Path::new(""))
+    }) {
+        diagnostics.push(Diagnostic::warning(
+            &nested.to_string_lossy(),
+            "Nested manifest.json, some tools may pick this one instead of the mod's manifest"
+                .to_string(),
+        ));
+    }
+    Ok(diagnostics)
+}
+
+/// Package a mod folder into a zip that can be installed with [crate::download::install_mod_from_file].
+/// The manifest is placed at the root of the zip before anything else so it's always the one that gets picked when installing.
+/// Hidden files and `config.json` are left out.
+///
+/// This doesn't validate the mod, use [validate_mod_folder] for that first.
+///
+/// ## Returns
+///
+/// The manifest of the mod that was packed
+///
+/// ## Errors
+///
+/// If we can't read the mod's manifest or files, or we can't write the zip.
+///
+pub fn pack_mod(mod_path: &Path, output_path: &Path) -> Result<ModManifest> {
+    let manifest: ModManifest =
+        serde_json::from_reader(BufReader::new(File::open(mod_path.join("manifest.json"))?))?;
+    let mut files: Vec<PathBuf> = vec![];
+    collect_files(mod_path, mod_path, &mut files)?;
+    let output_canonical = output_path.canonicalize().ok();
+    files.retain(|f| {
+        // Don't try to pack the zip into itself if it's being output into the mod folder
+        let full = mod_path.join(f).canonicalize().ok();
+        f != Path::new("manifest.json") && (full.is_none() || full != output_canonical)
+    });
+    files.insert(0, PathBuf::from("manifest.json"));
+
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(output_path)?));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    for rel_path in files {
+        let name: Vec<String> = rel_path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        zip.start_file(name.join("/"), options)?;
+        let mut file = File::open(mod_path.join(&rel_path))?;
+        std::io::copy(&mut file, &mut zip)?;
+    }
+    zip.finish()?;
+    Ok(manifest)
+}
+
+/// Get the default file name for a packed mod, which is `<uniqueName>.zip`
+pub fn get_pack_name(mod_path: &Path) -> Result<String> {
+    let manifest: Map<String, Value> =
+        serde_json::from_reader(BufReader::new(File::open(mod_path.join("manifest.json"))?))?;
+    let unique_name = manifest
+        .get("uniqueName")
+        .and_then(|n| n.as_str())
+        .unwrap_or("mod");
+    Ok(format!("{unique_name}.zip"))
+}
+
+fn get_backup_path(config: &Config, unique_name: &str) -> PathBuf {
+    PathBuf::from(config.get_owml_path())
+        .join(LINKED_BACKUPS_FOLDER_NAME)
+        .join(unique_name)
+}
+
+#[cfg(unix)]
+fn make_link(source: &Path, target: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(source, target)?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn make_link(source: &Path, target: &Path) -> Result<()> {
+    std::os::windows::fs::symlink_dir(source, target).map_err(|e| {
+        anyhow!("Couldn't create link, you may need to enable Developer Mode in Windows settings: {e:?}")
+    })
+}
+
+#[cfg(unix)]
+fn remove_link(target: &Path) -> Result<()> {
+    std::fs::remove_file(target)?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn remove_link(target: &Path) -> Result<()> {
+    // Directory symlinks on Windows are removed like directories, this won't touch the source
+    std::fs::remove_dir(target)?;
+    Ok(())
+}
+
+/// Link a folder outside of the `Mods` folder (like a mod's build output) into it as an installed mod.
+/// If the mod is already installed the installed copy is moved aside and restored by [unlink_mod].
+///
+/// Linked mods are skipped when updating and can't be uninstalled or installed over.
+///
+/// ## Returns
+///
+/// The newly linked mod
+///
+/// ## Errors
+///
+/// - If the folder doesn't have a valid manifest
+/// - If the mod is already linked
+/// - If we can't move the installed copy or create the link
+///
+pub fn link_mod(source_path: &Path, config: &Config, local_db: &LocalDatabase) -> Result<LocalMod> {
+    let source_path = source_path.canonicalize()?;
+    let source_mod = LocalDatabase::read_local_mod(&source_path.join("manifest.json"))?;
+    let unique_name = &source_mod.manifest.unique_name;
+    let installed = local_db.get_mod(unique_name);
+    if installed.is_some_and(|m| m.linked) {
+        return Err(anyhow!("{} is already linked", unique_name));
+    }
+    let target_path = installed
+        .map(|m| PathBuf::from(&m.mod_path))
+        .unwrap_or_else(|| {
+            PathBuf::from(config.get_owml_path())
+                .join("Mods")
+                .join(unique_name)
+        });
+    if target_path.exists() {
+        let backup_path = get_backup_path(config, unique_name);
+        if backup_path.exists() {
+            return Err(anyhow!(
+                "A backup of {} already exists at {}",
+                unique_name,
+                backup_path.to_str().unwrap()
+            ));
+        }
+        debug!(
+            "Moving installed copy of {} to {}",
+            unique_name,
+            backup_path.to_str().unwrap()
+        );
+        create_all_parents(&backup_path)?;
+        rename(&target_path, &backup_path)?;
+    }
+    create_all_parents(&target_path)?;
+    make_link(&source_path, &target_path)?;
+    info!(
+        "Linked {} to {}",
+        target_path.to_str().unwrap(),
+        source_path.to_str().unwrap()
+    );
+    LocalDatabase::read_local_mod(&target_path.join("manifest.json"))
+}
+
+/// Remove a link made with [link_mod], restoring the installed copy of the mod if there was one.
+/// The linked folder itself is never touched.
+///
+/// ## Errors
+///
+/// - If the mod isn't linked
+/// - If we can't remove the link or restore the installed copy
+///
+pub fn unlink_mod(local_mod: &LocalMod, config: &Config) -> Result<()> {
+    if !local_mod.linked {
+        return Err(anyhow!("{} isn't linked", local_mod.manifest.unique_name));
+    }
+    let target_path = PathBuf::from(&local_mod.mod_path);
+    remove_link(&target_path)?;
+    let backup_path = get_backup_path(config, &local_mod.manifest.unique_name);
+    if backup_path.is_dir() {
+        debug!(
+            "Restoring installed copy of {} from {}",
+            local_mod.manifest.unique_name,
+            backup_path.to_str().unwrap()
+        );
+        rename(&backup_path, &target_path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs::{copy, create_dir_all, write};
+
+    use crate::{
+        download::install_mod_from_file,
+        remove::remove_mod,
+        test_utils::{get_test_file, make_test_dir},
+    };
+
+    use super::*;
+
+    fn errors(diagnostics: &[Diagnostic]) -> Vec<&Diagnostic> {
+        diagnostics
+            .iter()
+            .filter(|d| d.level == DiagnosticLevel::Error)
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_valid_mod() {
+        let diagnostics = validate_mod_folder(&get_test_file("Mods/Bwc9876.TimeSaver")).unwrap();
+        assert!(errors(&diagnostics).is_empty());
+        // config.json is in the test folder
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn test_validate_bad_manifest() {
+        let dir = make_test_dir();
+        write(
+            dir.path().join("manifest.json"),
+            r#"{"uniqueName": "Test.Mod", "name": "Test", "author": "Test", "version": "banana",
+                "filename": "Test.dll", "dependencies": ["Test.Mod", "Other.Mod"], "conflicts": ["Other.Mod"]}"#,
+        )
+        .unwrap();
+        let diagnostics = validate_mod_folder(dir.path()).unwrap();
+        assert_eq!(errors(&diagnostics).len(), 4);
+        write(dir.path().join("manifest.json"), r#"{"name": "Test"}"#).unwrap();
+        let diagnostics = validate_mod_folder(dir.path()).unwrap();
+        assert_eq!(errors(&diagnostics).len(), 3);
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_validate_lenient_versions() {
+        let dir = make_test_dir();
+        let check = |version: &str| {
+            write(
+                dir.path().join("manifest.json"),
+                format!(r#"{{"uniqueName": "Test.Mod", "name": "Test", "author": "Test", "version": "{version}"}}"#),
+            )
+            .unwrap();
+            validate_mod_folder(dir.path())
+                .unwrap()
+                .into_iter()
+                .filter(|d| d.message.contains("`version`"))
+                .map(|d| d.level)
+                .collect::<Vec<_>>()
+        };
+        assert!(check("1.2").is_empty());
+        assert!(check("1.2.0.1").is_empty());
+        assert_eq!(check("v1.2"), vec![DiagnosticLevel::Warning]);
+        assert_eq!(check("1.x"), vec![DiagnosticLevel::Error]);
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_validate_default_config() {
+        let dir = make_test_dir();
+        copy(
+            get_test_file("Mods/Bwc9876.TimeSaver/manifest.json"),
+            dir.path().join("manifest.json"),
+        )
+        .unwrap();
+        copy(
+            get_test_file("Mods/Bwc9876.TimeSaver/TimeSaver.dll"),
+            dir.path().join("TimeSaver.dll"),
+        )
+        .unwrap();
+        write(
+            dir.path().join("default-config.json"),
+            r#"{"enabled": true, "settings": {"Ok": true, "Bad Selector": {"type": "selector", "value": "a"},
+                "Bad Slider": {"type": "slider", "value": 20, "min": 0, "max": 10}}}"#,
+        )
+        .unwrap();
+        let diagnostics = validate_mod_folder(dir.path()).unwrap();
+        assert_eq!(errors(&diagnostics).len(), 1);
+        assert_eq!(diagnostics.len(), 2);
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_pack_mod() {
+        let dir = make_test_dir();
+        let mod_path = dir.path().join("Mod");
+        create_dir_all(mod_path.join("assets")).unwrap();
+        for file in ["manifest.json", "TimeSaver.dll", "config.json"] {
+            copy(
+                get_test_file(&format!("Mods/Bwc9876.TimeSaver/{file}")),
+                mod_path.join(file),
+            )
+            .unwrap();
+        }
+        write(mod_path.join("assets").join("asset.txt"), "Hello").unwrap();
+        let zip_path = dir.path().join(get_pack_name(&mod_path).unwrap());
+        let manifest = pack_mod(&mod_path, &zip_path).unwrap();
+        assert_eq!(manifest.unique_name, "Bwc9876.TimeSaver");
+        assert!(zip_path.ends_with("Bwc9876.TimeSaver.zip"));
+
+        let owml_path = dir.path().join("OWML");
+        let mut config = Config::default(None).unwrap();
+        config.owml_path = owml_path.to_str().unwrap().to_string();
+        let local_db = LocalDatabase::default();
+        let new_mod = install_mod_from_file(&zip_path, &config, &local_db).unwrap();
+        let installed_path = PathBuf::from(&new_mod.mod_path);
+        assert_eq!(new_mod.manifest.unique_name, "Bwc9876.TimeSaver");
+        assert!(installed_path.join("TimeSaver.dll").is_file());
+        assert!(installed_path.join("assets").join("asset.txt").is_file());
+        dir.close().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_link_and_unlink_mod() {
+        let dir = make_test_dir();
+        let owml_path = dir.path().join("OWML");
+        let installed_path = owml_path.join("Mods").join("Bwc9876.TimeSaver");
+        let source_path = dir.path().join("Build");
+        create_dir_all(&installed_path).unwrap();
+        create_dir_all(&source_path).unwrap();
+        for file in ["manifest.json", "TimeSaver.dll", "config.json"] {
+            let test_file = get_test_file(&format!("Mods/Bwc9876.TimeSaver/{file}"));
+            copy(&test_file, installed_path.join(file)).unwrap();
+            copy(&test_file, source_path.join(file)).unwrap();
+        }
+        write(installed_path.join("installed.txt"), "Installed").unwrap();
+        let mut config = Config::default(None).unwrap();
+        config.owml_path = owml_path.to_str().unwrap().to_string();
+
+        let local_db = LocalDatabase::fetch(&config.owml_path).unwrap();
+        let linked = link_mod(&source_path, &config, &local_db).unwrap();
+        assert!(linked.linked);
+        let local_db = LocalDatabase::fetch(&config.owml_path).unwrap();
+        let local_mod = local_db.get_mod("Bwc9876.TimeSaver").unwrap();
+        assert!(local_mod.linked);
+        assert!(!installed_path.join("installed.txt").exists());
+        assert!(remove_mod(local_mod, &local_db, false).is_err());
+        assert!(link_mod(&source_path, &config, &local_db).is_err());
+
+        unlink_mod(local_mod, &config).unwrap();
+        assert!(source_path.join("manifest.json").is_file());
+        let local_db = LocalDatabase::fetch(&config.owml_path).unwrap();
+        let local_mod = local_db.get_mod("Bwc9876.TimeSaver").unwrap();
+        assert!(!local_mod.linked);
+        assert!(installed_path.join("installed.txt").is_file());
+        dir.close().unwrap();
+    }
+}
@@ -1,47 +1,61 @@
 use std::{
+    collections::VecDeque,
     ffi::OsStr,
-    fs::File,
-    io::{BufReader, BufWriter, Read, Write},
-    path::{Path, PathBuf},
+    fs::{self, create_dir_all, File},
+    io::{self, BufReader, BufWriter, Read, Seek, Write},
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
 };
 
 use anyhow::anyhow;
 use anyhow::Result;
+use flate2::read::GzDecoder;
 use futures::{stream::FuturesUnordered, StreamExt};
 use log::{debug, info};
+use rayon::prelude::*;
 use tempfile::TempDir;
 use zip::ZipArchive;
 
 use crate::{
     analytics::{send_analytics_event, AnalyticsEventName},
     config::Config,
+    constants::{MAX_COMPRESSION_RATIO, MAX_EXTRACT_ENTRIES, MAX_EXTRACT_SIZE},
     db::{LocalDatabase, RemoteDatabase},
-    file::{check_file_matches_paths, create_all_parents, fix_json},
-    mods::local::{get_paths_to_preserve, LocalMod, ModManifest},
+    file::{
+        check_file_matches_paths, check_free_space, create_all_parents, fix_json, get_dir_size,
+    },
+    mods::local::{
+        get_paths_to_preserve, write_channel, write_install_reason, InstallReason, LocalMod,
+        ModManifest, ReleaseChannel,
+    },
     mods::remote::RemoteMod,
-    progress::{ProgressAction, ProgressBar, ProgressType},
+    owml::OWMLConfig,
+    progress::{ProgressAction, ProgressBar, ProgressType, ProgressValue},
     remove::remove_old_mod_files,
     toggle::generate_config,
 };
 
-fn get_end_of_url(url: &str) -> &str {
+pub(crate) fn get_end_of_url(url: &str) -> &str {
     url.split('/').last().unwrap_or(url)
 }
 
-async fn download_zip(url: &str, target_path: &Path) -> Result<()> {
+pub(crate) async fn download_file(url: &str, target_path: &Path) -> Result<()> {
     debug!(
         "Begin download of {} to {}",
         url,
         target_path.to_str().unwrap()
     );
     let client = reqwest::Client::new();
-    let zip_name = get_end_of_url(url);
+    let file_name = get_end_of_url(url);
     let request = client.get(url);
 
-    let mut stream = File::create(target_path)?;
     let mut download = request.send().await?.error_for_status()?;
 
     let file_size = download.content_length().unwrap_or(0);
+    if file_size > 0 {
+        check_free_space(target_path, file_size, file_name)?;
+    }
+    let mut stream = File::create(target_path)?;
 
     let progress_type = if file_size > 0 {
         ProgressType::Definite
@@ -52,8 +66,8 @@ async fn download_zip(url: &str, target_path: &Path) -> Result<()> {
     let mut progress = ProgressBar::new(
         target_path.to_str().unwrap(),
         file_size.try_into().unwrap_or(u32::MAX), // Fallback for HUGE files, means files >4GB will get progress reported incorrectly
-        &format!("Downloading {}", zip_name),
-        &format!("Failed to download {}", zip_name),
+        &format!("Downloading {}", file_name),
+        &format!("Failed to download {}", file_name),
         progress_type,
         ProgressAction::Download,
     );
@@ -63,150 +77,864 @@ async fn download_zip(url: &str, target_path: &Path) -> Result<()> {
         stream.write_all(&chunk)?;
     }
 
-    progress.finish(true, &format!("Downloaded {}", zip_name));
+    progress.finish(true, &format!("Downloaded {}", file_name));
 
     Ok(())
 }
 
-// Does this mean that i'll have to re-open the archive to do anything with it? Yes.
-// Do I really care? No.
-// You want a better one make it pls thx.
-fn get_manifest_path_from_zip(zip_path: &PathBuf) -> Result<(String, PathBuf)> {
-    let file = File::open(zip_path)?;
-    let mut archive = ZipArchive::new(file)?;
+/// The kinds of archive a mod can be installed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+    TarZst,
+    /// Only supported when the `7z` feature is enabled
+    SevenZip,
+}
+
+impl ArchiveFormat {
+    /// Detect the format of an archive from its first few bytes, the extension is ignored since mods are often uploaded with the wrong one
+    ///
+    /// ## Errors
+    ///
+    /// If we can't read the file or it isn't an archive we know about.
+    ///
+    pub fn detect(path: &Path) -> Result<Self> {
+        let mut magic = [0; 6];
+        let mut file = File::open(path)?;
+        let mut read = 0;
+        while read < magic.len() {
+            let n = file.read(&mut magic[read..])?;
+            if n == 0 {
+                break;
+            }
+            read += n;
+        }
+        let magic = &magic[..read];
+        if magic.starts_with(b"PK\x03\x04") || magic.starts_with(b"PK\x05\x06") {
+            Ok(Self::Zip)
+        } else if magic.starts_with(&[0x1f, 0x8b]) {
+            Ok(Self::TarGz)
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Ok(Self::TarZst)
+        } else if magic.starts_with(&[0x37, 0x7a, 0xbc, 0xaf, 0x27, 0x1c]) {
+            Ok(Self::SevenZip)
+        } else {
+            Err(anyhow!(
+                "{} isn't a supported archive, mods can be installed from .zip, .tar.gz, .tar.zst, and .7z files or folders",
+                path.file_name().and_then(|n| n.to_str()).unwrap_or("File")
+            ))
+        }
+    }
+}
+
+/// A mod zip that's been opened once, with its manifest already found and read
+struct ModZip {
+    archive: ZipArchive<BufReader<File>>,
+    zip_path: PathBuf,
+    /// Where the manifest is in the zip, everything next to it is the mod
+    manifest_path: PathBuf,
+    manifest: ModManifest,
+}
+
+impl ModZip {
+    /// Open a mod zip and read the shallowest `manifest.json` in it, like [ModDir::open] does for folders
+    ///
+    /// ## Errors
+    ///
+    /// If we can't open the zip, there's no manifest in it, or the manifest isn't valid
+    ///
+    fn open(zip_path: &Path) -> Result<Self> {
+        let file = BufReader::new(File::open(zip_path)?);
+        let mut archive = ZipArchive::new(file)?;
+        let mut manifest_entry: Option<(usize, PathBuf)> = None;
+        for index in 0..archive.len() {
+            // Raw so we don't start decompressing every file just to look at its name
+            let zip_file = archive.by_index_raw(index)?;
+            if let Some(path) = zip_file.enclosed_name() {
+                if path.file_name() != Some(OsStr::new("manifest.json")) {
+                    continue;
+                }
+                // Entries can be in any order, so compare by depth then by path to match how folders are searched
+                let key = (path.components().count(), path);
+                let is_better = match &manifest_entry {
+                    Some((_, best)) => key < (best.components().count(), best.as_path()),
+                    None => true,
+                };
+                if is_better {
+                    manifest_entry = Some((index, path.to_path_buf()));
+                }
+            }
+        }
+        let (index, manifest_path) =
+            manifest_entry.ok_or_else(|| anyhow!("Manifest not found in zip archive"))?;
+        let mut buf = String::new();
+        archive.by_index(index)?.read_to_string(&mut buf)?;
+        let manifest: ModManifest = serde_json::from_str(&fix_json(&buf))?;
+        Ok(Self {
+            archive,
+            zip_path: zip_path.to_path_buf(),
+            manifest_path,
+            manifest,
+        })
+    }
+
+    /// Get the total uncompressed size of the files next to the manifest, this is what the mod will take up once extracted
+    fn get_extracted_size(&mut self) -> Result<u64> {
+        let parent_path = self.manifest_path.parent().unwrap_or_else(|| Path::new(""));
+        let mut total: u64 = 0;
+        for index in 0..self.archive.len() {
+            let zip_file = self.archive.by_index_raw(index)?;
+            if zip_file
+                .enclosed_name()
+                .is_some_and(|p| p.starts_with(parent_path))
+            {
+                total = total.saturating_add(zip_file.size());
+            }
+        }
+        Ok(total)
+    }
+
+    /// Extract the folder the manifest is in to `target_path`, skipping anything in `exclude_paths`
+    ///
+    /// ## Returns
+    ///
+    /// The newly extracted mod
+    ///
+    /// ## Errors
+    ///
+    /// If the zip isn't safe to extract or we can't write the files
+    ///
+    fn extract(mut self, target_path: &Path, exclude_paths: Vec<PathBuf>) -> Result<LocalMod> {
+        debug!(
+            "Begin extraction of {} to {}, found manifest at {}",
+            self.zip_path.to_str().unwrap(),
+            target_path.to_str().unwrap(),
+            self.manifest_path.to_str().unwrap()
+        );
+        let parent_path = self.manifest_path.parent().unwrap_or_else(|| Path::new(""));
+        let zip_name = self.zip_path.file_name().unwrap().to_str().unwrap();
+
+        let (entries, total_size) = check_zip_entries(
+            &mut self.archive,
+            zip_name,
+            &ExtractLimits::default(),
+            |path| {
+                // Only files next to the manifest, folders are created as needed
+                let rel_path = path.strip_prefix(parent_path).ok()?;
+                (path.file_name().is_some()
+                    && !rel_path.as_os_str().is_empty()
+                    && !check_file_matches_paths(rel_path, &exclude_paths))
+                .then(|| rel_path.to_path_buf())
+            },
+        )?;
+        let entries = entries.into_iter().filter(|e| !e.is_dir).collect();
+
+        let mut progress = ProgressBar::new(
+            self.zip_path.to_str().unwrap(),
+            total_size.try_into().unwrap_or(ProgressValue::MAX),
+            &format!("Extracting {}", zip_name),
+            &format!("Failed To Extract {}", zip_name),
+            ProgressType::Definite,
+            ProgressAction::Extract,
+        );
+
+        extract_zip_entries(
+            &self.zip_path,
+            &mut self.archive,
+            entries,
+            total_size,
+            target_path,
+            &mut progress,
+        )?;
+
+        let new_mod = LocalDatabase::read_local_mod(&target_path.join("manifest.json"))?;
+        progress.finish(true, &format!("Installed {}", new_mod.manifest.name));
+        Ok(new_mod)
+    }
+}
+
+/// Read the manifest of the mod in a zip, this is the same manifest that will be used when installing it
+pub(crate) fn get_manifest_from_zip(zip_path: &Path) -> Result<ModManifest> {
+    Ok(ModZip::open(zip_path)?.manifest)
+}
+
+/// Read the manifest of the mod in an archive or folder, this is the same manifest that will be used when installing it.
+/// Archives other than zips are unpacked to a temporary folder to find it.
+///
+/// ## Errors
+///
+/// If the archive isn't supported or safe to unpack, or there's no valid manifest in it
+///
+pub fn get_manifest_from_file(path: &Path) -> Result<ModManifest> {
+    Ok(ModSource::open(path)?.manifest().clone())
+}
+
+/// A mod in a folder, either one the user picked or one we unpacked an archive into
+struct ModDir {
+    /// The folder the manifest is in, everything in it is the mod
+    root: PathBuf,
+    /// What the user is installing from, used for messages
+    source_path: PathBuf,
+    manifest: ModManifest,
+    /// Kept so the folder an archive was unpacked to lives as long as this does
+    _temp_dir: Option<TempDir>,
+}
+
+impl ModDir {
+    /// Find the shallowest `manifest.json` in `search_path` and read it
+    ///
+    /// ## Errors
+    ///
+    /// If we can't read the folder, there's no manifest in it, or the manifest isn't valid
+    ///
+    fn open(search_path: &Path, source_path: &Path, temp_dir: Option<TempDir>) -> Result<Self> {
+        let mut dirs = VecDeque::from([search_path.to_path_buf()]);
+        let mut manifest_path = None;
+        while let Some(dir) = dirs.pop_front() {
+            let path = dir.join("manifest.json");
+            if path.is_file() {
+                manifest_path = Some(path);
+                break;
+            }
+            let mut children = fs::read_dir(&dir)?
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+                .collect::<Vec<_>>();
+            children.sort();
+            dirs.extend(children);
+        }
+        let manifest_path = manifest_path.ok_or_else(|| {
+            anyhow!(
+                "Manifest not found in {}",
+                source_path.to_str().unwrap_or("folder")
+            )
+        })?;
+        let manifest: ModManifest =
+            serde_json::from_str(&fix_json(&fs::read_to_string(&manifest_path)?))?;
+        Ok(Self {
+            root: manifest_path.parent().unwrap().to_path_buf(),
+            source_path: source_path.to_path_buf(),
+            manifest,
+            _temp_dir: temp_dir,
+        })
+    }
+
+    /// Copy the folder the manifest is in to `target_path`, skipping anything in `exclude_paths`
+    ///
+    /// ## Returns
+    ///
+    /// The newly installed mod
+    ///
+    /// ## Errors
+    ///
+    /// If we can't read or copy the files
+    ///
+    fn extract(self, target_path: &Path, exclude_paths: Vec<PathBuf>) -> Result<LocalMod> {
+        debug!(
+            "Begin copy of {} to {}",
+            self.root.to_str().unwrap(),
+            target_path.to_str().unwrap()
+        );
+        let source_name = self
+            .source_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("mod");
+        let mut files = vec![];
+        collect_files(&self.root, &self.root, &mut files)?;
+        files.retain(|(rel_path, _)| !check_file_matches_paths(rel_path, &exclude_paths));
+        let total_size: u64 = files.iter().map(|(_, size)| size).sum();
+
+        let mut progress = ProgressBar::new(
+            self.source_path.to_str().unwrap(),
+            total_size.try_into().unwrap_or(ProgressValue::MAX),
+            &format!("Copying {}", source_name),
+            &format!("Failed To Copy {}", source_name),
+            ProgressType::Definite,
+            ProgressAction::Extract,
+        );
+        for (rel_path, size) in files {
+            let output_path = target_path.join(&rel_path);
+            create_all_parents(&output_path)?;
+            fs::copy(self.root.join(&rel_path), &output_path)?;
+            if let Some(file_name) = rel_path.file_name().and_then(|n| n.to_str()) {
+                progress.set_msg(&format!("Copied {}", file_name));
+            }
+            progress.inc(size.try_into().unwrap_or(ProgressValue::MAX));
+        }
+
+        let new_mod = LocalDatabase::read_local_mod(&target_path.join("manifest.json"))?;
+        progress.finish(true, &format!("Installed {}", new_mod.manifest.name));
+        Ok(new_mod)
+    }
+}
+
+/// Get every file in `dir` along with its size, symlinks are skipped so we never copy something from outside the folder
+pub(crate) fn collect_files(
+    root: &Path,
+    dir: &Path,
+    files: &mut Vec<(PathBuf, u64)>,
+) -> Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect::<Vec<_>>();
+    entries.sort();
+    for path in entries {
+        let metadata = path.symlink_metadata()?;
+        if metadata.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if metadata.is_file() {
+            // Unwrap is safe, everything we walk is under root
+            files.push((
+                path.strip_prefix(root).unwrap().to_path_buf(),
+                metadata.len(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Something a mod can be installed from, zips are extracted straight from the archive and everything else is unpacked to a folder first
+enum ModSource {
+    Zip(ModZip),
+    Dir(ModDir),
+}
+
+impl ModSource {
+    /// Open a mod archive or folder, the archive format is detected with [ArchiveFormat::detect]
+    ///
+    /// ## Errors
+    ///
+    /// If the archive isn't supported or safe to unpack, or there's no valid manifest in it
+    ///
+    fn open(path: &Path) -> Result<Self> {
+        if path.is_dir() {
+            return Ok(Self::Dir(ModDir::open(path, path, None)?));
+        }
+        let format = ArchiveFormat::detect(path)?;
+        if format == ArchiveFormat::Zip {
+            return Ok(Self::Zip(ModZip::open(path)?));
+        }
+        let temp_dir = TempDir::new()?;
+        let unpacked_path = temp_dir.path().to_path_buf();
+        unpack_archive(path, format, &unpacked_path)?;
+        Ok(Self::Dir(ModDir::open(
+            &unpacked_path,
+            path,
+            Some(temp_dir),
+        )?))
+    }
+
+    fn manifest(&self) -> &ModManifest {
+        match self {
+            Self::Zip(mod_zip) => &mod_zip.manifest,
+            Self::Dir(mod_dir) => &mod_dir.manifest,
+        }
+    }
+
+    /// Get how much space the mod will take up once it's installed
+    fn get_extracted_size(&mut self) -> Result<u64> {
+        match self {
+            Self::Zip(mod_zip) => mod_zip.get_extracted_size(),
+            Self::Dir(mod_dir) => get_dir_size(&mod_dir.root),
+        }
+    }
+
+    fn extract(self, target_path: &Path, exclude_paths: Vec<PathBuf>) -> Result<LocalMod> {
+        match self {
+            Self::Zip(mod_zip) => mod_zip.extract(target_path, exclude_paths),
+            Self::Dir(mod_dir) => mod_dir.extract(target_path, exclude_paths),
+        }
+    }
+}
+
+/// Limits on what an archive can contain, so a crafted archive can't fill the disk
+struct ExtractLimits {
+    /// Max total uncompressed size of the entries we extract
+    max_size: u64,
+    /// Max number of entries in the archive
+    max_entries: usize,
+    /// Max ratio of uncompressed to compressed size of an entry, only checked for entries bigger than [RATIO_CHECK_MIN_SIZE]
+    max_ratio: u64,
+}
+
+/// Archives smaller than this are extracted on one thread, it isn't worth opening more handles to them
+const PARALLEL_EXTRACT_MIN_SIZE: u64 = 16 * 1024 * 1024;
+
+const EXTRACT_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Small files can have huge compression ratios without being a problem (ex: a file full of spaces)
+const RATIO_CHECK_MIN_SIZE: u64 = 1024 * 1024;
+
+// Mode bits zip uses to mark a symlink, same as `S_IFLNK`
+const SYMLINK_MODE: u32 = 0o120000;
+const FILE_TYPE_MASK: u32 = 0o170000;
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        Self {
+            max_size: MAX_EXTRACT_SIZE,
+            max_entries: MAX_EXTRACT_ENTRIES,
+            max_ratio: MAX_COMPRESSION_RATIO,
+        }
+    }
+}
+
+/// An entry in a zip that's been checked and will be extracted
+struct ZipEntry {
+    index: usize,
+    rel_path: PathBuf,
+    size: u64,
+    is_dir: bool,
+}
 
+/// Check every entry in an archive before anything is extracted.
+/// `select` is given the path of each entry and returns where it should go relative to the target, or `None` to skip it.
+///
+/// ## Returns
+///
+/// The entries to extract and their total uncompressed size.
+///
+/// ## Errors
+///
+/// If the archive has a symlink or a path that would escape the target folder, or it's over any of the limits.
+///
+fn check_zip_entries<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    zip_name: &str,
+    limits: &ExtractLimits,
+    select: impl Fn(&Path) -> Option<PathBuf>,
+) -> Result<(Vec<ZipEntry>, u64)> {
+    if archive.len() > limits.max_entries {
+        return Err(anyhow!(
+            "{} has too many files ({}, the limit is {})",
+            zip_name,
+            archive.len(),
+            limits.max_entries
+        ));
+    }
+    let mut entries = vec![];
+    let mut total_size: u64 = 0;
     for index in 0..archive.len() {
-        let zip_file = archive.by_index(index)?;
-        let path = zip_file.enclosed_name();
-
-        if let Some(path) = path {
-            let name = path.file_name();
-            if name == Some(OsStr::new("manifest.json")) {
-                return Ok((
-                    zip_file.name().to_string(),
-                    zip_file
-                        .enclosed_name()
-                        .ok_or_else(|| anyhow!("Error reading zip file"))?
-                        .to_path_buf(),
+        let zip_file = archive.by_index_raw(index)?;
+        if zip_file
+            .unix_mode()
+            .is_some_and(|mode| mode & FILE_TYPE_MASK == SYMLINK_MODE)
+        {
+            return Err(anyhow!(
+                "{} contains a symlink ({}), which isn't allowed",
+                zip_name,
+                zip_file.name()
+            ));
+        }
+        let path = zip_file.enclosed_name().ok_or_else(|| {
+            anyhow!(
+                "{} contains a file with an unsafe path ({}), it may be trying to write outside of the folder it's extracted to",
+                zip_name,
+                zip_file.name()
+            )
+        })?;
+        let size = zip_file.size();
+        if size > RATIO_CHECK_MIN_SIZE
+            && size / zip_file.compressed_size().max(1) > limits.max_ratio
+        {
+            return Err(anyhow!(
+                "{} contains a file ({}) that's compressed suspiciously well, it may be a zip bomb",
+                zip_name,
+                zip_file.name()
+            ));
+        }
+        if let Some(rel_path) = select(path) {
+            total_size = total_size.saturating_add(size);
+            if total_size > limits.max_size {
+                return Err(anyhow!(
+                    "{} is too big when extracted (the limit is {} MB)",
+                    zip_name,
+                    limits.max_size / 1024 / 1024
                 ));
             }
+            entries.push(ZipEntry {
+                index,
+                rel_path,
+                size,
+                is_dir: zip_file.is_dir(),
+            });
         }
     }
-    Err(anyhow!("Manifest not found in zip archive"))
+    Ok((entries, total_size))
 }
 
-fn get_unique_name_from_zip(zip_path: &PathBuf) -> Result<String> {
-    let (manifest_name, _) = get_manifest_path_from_zip(zip_path)?;
-    let file = File::open(zip_path)?;
-    let mut archive = ZipArchive::new(file)?;
-    let mut manifest = archive.by_name(&manifest_name)?;
-    let mut buf = String::new();
-    manifest.read_to_string(&mut buf)?;
-    let txt = fix_json(&buf);
-    let manifest: ModManifest = serde_json::from_str(&txt)?;
-    Ok(manifest.unique_name)
+/// Extract one entry checked with [check_zip_entries], it can't be bigger than its header says so the limits checked before hold
+///
+/// ## Returns
+///
+/// How many bytes were written
+///
+fn extract_zip_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    entry: &ZipEntry,
+    target_path: &Path,
+) -> Result<u64> {
+    let output_path = target_path.join(&entry.rel_path);
+    if entry.is_dir {
+        create_dir_all(&output_path)?;
+        return Ok(0);
+    }
+    let mut zip_file = archive.by_index(entry.index)?;
+    write_entry(&mut zip_file, entry.size, &output_path)
+}
+
+/// Write an entry of an archive to `output_path`, erroring if it's bigger than the archive said it would be
+///
+/// ## Returns
+///
+/// How many bytes were written
+///
+fn write_entry(reader: &mut dyn Read, size: u64, output_path: &Path) -> Result<u64> {
+    create_all_parents(output_path)?;
+    let mut writer = BufWriter::with_capacity(EXTRACT_BUFFER_SIZE, File::create(output_path)?);
+    let written = io::copy(&mut reader.take(size + 1), &mut writer)?;
+    if written > size {
+        return Err(anyhow!(
+            "{} is bigger than the archive says it is, the archive may be corrupt or malicious",
+            output_path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("A file")
+        ));
+    }
+    writer.flush()?;
+    Ok(written)
+}
+
+fn report_entry(progress: &mut ProgressBar, entry: &ZipEntry, written: u64) {
+    if let Some(file_name) = entry.rel_path.file_name().and_then(|n| n.to_str()) {
+        progress.set_msg(&format!("Extracted {}", file_name));
+    }
+    progress.inc(written.try_into().unwrap_or(ProgressValue::MAX));
+}
+
+/// Extract entries checked with [check_zip_entries] to `target_path`.
+/// Big archives are extracted in parallel, with each thread opening its own handle to the zip at `zip_path`.
+fn extract_zip_entries<R: Read + Seek>(
+    zip_path: &Path,
+    archive: &mut ZipArchive<R>,
+    entries: Vec<ZipEntry>,
+    total_size: u64,
+    target_path: &Path,
+    progress: &mut ProgressBar,
+) -> Result<()> {
+    if total_size < PARALLEL_EXTRACT_MIN_SIZE || entries.len() < 2 {
+        for entry in entries {
+            let written = extract_zip_entry(archive, &entry, target_path)?;
+            report_entry(progress, &entry, written);
+        }
+        return Ok(());
+    }
+    // Folders first so threads don't race to create them
+    for entry in entries.iter().filter(|e| e.is_dir) {
+        extract_zip_entry(archive, entry, target_path)?;
+    }
+    let progress = Mutex::new(progress);
+    entries
+        .into_par_iter()
+        .filter(|e| !e.is_dir)
+        .try_for_each_init(
+            || -> Result<ZipArchive<BufReader<File>>> {
+                Ok(ZipArchive::new(BufReader::new(File::open(zip_path)?))?)
+            },
+            |archive, entry| {
+                let archive = archive
+                    .as_mut()
+                    .map_err(|e| anyhow!("Couldn't open zip: {:?}", e))?;
+                let written = extract_zip_entry(archive, &entry, target_path)?;
+                report_entry(&mut progress.lock().unwrap(), &entry, written);
+                Ok(())
+            },
+        )
 }
 
-fn extract_zip(zip_path: &PathBuf, target_path: &PathBuf, display_name: &str) -> Result<()> {
+fn extract_zip(zip_path: &Path, target_path: &Path, display_name: &str) -> Result<()> {
     debug!(
         "Begin extraction of {} to {}",
         zip_path.to_str().unwrap(),
         target_path.to_str().unwrap()
     );
+    let file = BufReader::new(File::open(zip_path)?);
+    let mut archive = ZipArchive::new(file)?;
+    let (entries, total_size) =
+        check_zip_entries(&mut archive, display_name, &ExtractLimits::default(), |p| {
+            Some(p.to_path_buf())
+        })?;
+    check_free_space(target_path, total_size, display_name)?;
     let mut progress = ProgressBar::new(
         zip_path.to_str().unwrap(),
-        0,
+        total_size.try_into().unwrap_or(ProgressValue::MAX),
         &format!("Extracting {display_name}"),
         &format!("Failed To Extract {display_name}"),
-        ProgressType::Indefinite,
+        ProgressType::Definite,
         ProgressAction::Extract,
     );
-    let file = File::open(zip_path)?;
-    let mut archive = ZipArchive::new(file)?;
-    archive.extract(target_path)?;
+    extract_zip_entries(
+        zip_path,
+        &mut archive,
+        entries,
+        total_size,
+        target_path,
+        &mut progress,
+    )?;
     progress.finish(true, &format!("Extracted {display_name}!"));
     Ok(())
 }
 
-fn extract_mod_zip(
-    zip_path: &PathBuf,
-    target_path: &Path,
-    exclude_paths: Vec<PathBuf>,
-) -> Result<LocalMod> {
-    debug!(
-        "Begin extraction of {} to {}",
-        zip_path.to_str().unwrap(),
-        target_path.to_str().unwrap()
-    );
-    let (_, manifest_path) = get_manifest_path_from_zip(zip_path)?;
-    debug!(
-        "Found manifest at {} in zip, extracting siblings",
-        manifest_path.to_str().unwrap()
-    );
-    let parent_path = manifest_path.parent().unwrap_or_else(|| Path::new(""));
-    let zip_name = zip_path.file_name().unwrap().to_str().unwrap();
+/// Get the path of an archive entry relative to the folder it's extracted to, or `None` if it would end up outside of it.
+/// This is the same check as [zip::read::ZipFile::enclosed_name], for archives that don't have one.
+fn get_enclosed_path(path: &Path) -> Option<PathBuf> {
+    let mut enclosed = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => return None,
+            Component::ParentDir => {
+                if !enclosed.pop() {
+                    return None;
+                }
+            }
+            Component::CurDir => {}
+            Component::Normal(part) => enclosed.push(part),
+        }
+    }
+    Some(enclosed)
+}
 
-    let file = File::open(zip_path)?;
-    let mut archive = ZipArchive::new(file)?;
+/// Checks entries of archives that have to be read in order (tar, 7z) as they're unpacked, with the same limits as [check_zip_entries].
+/// The compression ratio is checked for the archive as a whole since these formats don't store it per entry.
+struct StreamChecker<'a> {
+    archive_name: &'a str,
+    archive_size: u64,
+    limits: &'a ExtractLimits,
+    entries: usize,
+    total_size: u64,
+}
 
-    let mut progress = ProgressBar::new(
-        zip_path.to_str().unwrap(),
-        archive.len().try_into().unwrap(),
-        &format!("Extracting {}", zip_name),
-        &format!("Failed To Extract {}", zip_name),
-        ProgressType::Definite,
-        ProgressAction::Extract,
-    );
+impl<'a> StreamChecker<'a> {
+    fn new(archive_name: &'a str, archive_size: u64, limits: &'a ExtractLimits) -> Self {
+        Self {
+            archive_name,
+            archive_size,
+            limits,
+            entries: 0,
+            total_size: 0,
+        }
+    }
 
-    for idx in 0..archive.len() {
-        progress.inc(1);
-        let zip_file = archive.by_index(idx)?;
-        if zip_file.is_file() {
-            let file_path = zip_file
-                .enclosed_name()
-                .ok_or_else(|| anyhow!("Can't Read Zip File"))?;
-            if file_path.starts_with(parent_path) {
-                // Unwrap is safe bc we know it's a file and OsStr.to_str shouldn't fail
-                let file_name = file_path.file_name().unwrap().to_str().unwrap();
-                progress.set_msg(&format!("Extracting {}", file_name));
-                // Unwrap is safe bc we just checked if it starts with the parent path
-                let rel_path = file_path.strip_prefix(parent_path).unwrap();
-                if !check_file_matches_paths(rel_path, &exclude_paths) {
+    /// Check the next entry before it's written
+    ///
+    /// ## Returns
+    ///
+    /// Where the entry should go relative to the target folder
+    ///
+    /// ## Errors
+    ///
+    /// If the entry's path would escape the target folder or the archive is now over any of the limits
+    ///
+    fn check(&mut self, path: &Path, size: u64) -> Result<PathBuf> {
+        self.entries += 1;
+        if self.entries > self.limits.max_entries {
+            return Err(anyhow!(
+                "{} has too many files (the limit is {})",
+                self.archive_name,
+                self.limits.max_entries
+            ));
+        }
+        let rel_path = get_enclosed_path(path).ok_or_else(|| {
+            anyhow!(
+                "{} contains a file with an unsafe path ({}), it may be trying to write outside of the folder it's extracted to",
+                self.archive_name,
+                path.to_string_lossy()
+            )
+        })?;
+        self.total_size = self.total_size.saturating_add(size);
+        if self.total_size > self.limits.max_size {
+            return Err(anyhow!(
+                "{} is too big when extracted (the limit is {} MB)",
+                self.archive_name,
+                self.limits.max_size / 1024 / 1024
+            ));
+        }
+        if self.total_size > RATIO_CHECK_MIN_SIZE
+            && self.total_size / self.archive_size.max(1) > self.limits.max_ratio
+        {
+            return Err(anyhow!(
+                "{} is compressed suspiciously well, it may be an archive bomb",
+                self.archive_name
+            ));
+        }
+        Ok(rel_path)
+    }
+}
+
+/// Unpack a tar archive to `target_path`, checking each entry before it's written
+///
+/// ## Errors
+///
+/// If the archive can't be read, has a link or a path that would escape the target folder, or it's over any of the limits.
+///
+fn unpack_tar(
+    reader: impl Read,
+    archive_name: &str,
+    archive_size: u64,
+    target_path: &Path,
+    limits: &ExtractLimits,
+) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    let mut checker = StreamChecker::new(archive_name, archive_size, limits);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_type = entry.header().entry_type();
+        let path = entry.path()?.to_path_buf();
+        if entry_type.is_symlink() || entry_type.is_hard_link() {
+            return Err(anyhow!(
+                "{} contains a link ({}), which isn't allowed",
+                archive_name,
+                path.to_string_lossy()
+            ));
+        }
+        if !entry_type.is_file() && !entry_type.is_dir() {
+            // Devices, fifos, and such, nothing a mod needs
+            continue;
+        }
+        let size = entry.size();
+        let rel_path = checker.check(&path, size)?;
+        let output_path = target_path.join(rel_path);
+        if entry_type.is_dir() {
+            create_dir_all(&output_path)?;
+        } else {
+            write_entry(&mut entry, size, &output_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Unpack a 7z archive to `target_path`, checking each entry before it's written
+///
+/// ## Errors
+///
+/// If the archive can't be read, has a path that would escape the target folder, or it's over any of the limits.
+///
+#[cfg(feature = "7z")]
+fn unpack_7z(
+    archive_path: &Path,
+    archive_name: &str,
+    target_path: &Path,
+    limits: &ExtractLimits,
+) -> Result<()> {
+    let archive_size = archive_path.metadata()?.len();
+    let mut archive = sevenz_rust::SevenZReader::open(archive_path, sevenz_rust::Password::empty())
+        .map_err(|e| anyhow!("Couldn't open {}: {}", archive_name, e))?;
+    let mut checker = StreamChecker::new(archive_name, archive_size, limits);
+    // The callback can only return 7z errors, so ours are kept here and the unpack is stopped
+    let mut failure = None;
+    archive
+        .for_each_entries(|entry, reader| {
+            if entry.is_anti_item() {
+                return Ok(true);
+            }
+            let result = checker
+                .check(Path::new(entry.name()), entry.size())
+                .and_then(|rel_path| {
                     let output_path = target_path.join(rel_path);
-                    create_all_parents(&output_path)?;
-                    let out_file = File::create(&output_path)?;
-                    let reader = BufReader::new(zip_file);
-                    let mut writer = BufWriter::new(out_file);
-                    for byte in reader.bytes() {
-                        writer.write_all(&[byte?])?;
+                    if entry.is_directory() {
+                        Ok(create_dir_all(&output_path)?)
+                    } else {
+                        write_entry(reader, entry.size(), &output_path).map(|_| ())
                     }
+                });
+            match result {
+                Ok(_) => Ok(true),
+                Err(why) => {
+                    failure = Some(why);
+                    Ok(false)
                 }
             }
-        }
-    }
+        })
+        .map_err(|e| anyhow!("Couldn't unpack {}: {}", archive_name, e))?;
+    failure.map_or(Ok(()), Err)
+}
 
-    let new_mod = LocalDatabase::read_local_mod(&target_path.join("manifest.json"))?;
-    progress.finish(true, &format!("Installed {}", new_mod.manifest.name));
-    Ok(new_mod)
+/// Unpack an archive that isn't a zip to `target_path`
+///
+/// ## Errors
+///
+/// If the archive can't be read or isn't safe to unpack, or it's a 7z and the `7z` feature isn't enabled.
+///
+fn unpack_archive(archive_path: &Path, format: ArchiveFormat, target_path: &Path) -> Result<()> {
+    let archive_name = archive_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("Archive");
+    debug!(
+        "Begin unpacking {} ({:?}) to {}",
+        archive_path.to_str().unwrap(),
+        format,
+        target_path.to_str().unwrap()
+    );
+    let limits = ExtractLimits::default();
+    let file = BufReader::new(File::open(archive_path)?);
+    let archive_size = archive_path.metadata()?.len();
+    match format {
+        ArchiveFormat::Zip => extract_zip(archive_path, target_path, archive_name),
+        ArchiveFormat::TarGz => unpack_tar(
+            GzDecoder::new(file),
+            archive_name,
+            archive_size,
+            target_path,
+            &limits,
+        ),
+        ArchiveFormat::TarZst => unpack_tar(
+            zstd::Decoder::with_buffer(file)?,
+            archive_name,
+            archive_size,
+            target_path,
+            &limits,
+        ),
+        #[cfg(feature = "7z")]
+        ArchiveFormat::SevenZip => unpack_7z(archive_path, archive_name, target_path, &limits),
+        #[cfg(not(feature = "7z"))]
+        ArchiveFormat::SevenZip => Err(anyhow!(
+            "{} is a 7z archive, which this build of the manager doesn't support",
+            archive_name
+        )),
+    }
 }
 
-/// Downloads and install OWML to the path specified in config.owml_path
+/// Downloads and install OWML to the path for the selected game ([Config::get_owml_path]).
+/// When installing for Outer Wilds Alpha the Alpha game path in the config is written to OWML's config.
 ///
 /// ## Errors
 ///
-/// If we can't download or extract the OWML zip for any reason.
+/// If we can't download or extract the OWML zip for any reason, including the zip being unsafe to extract or there not being enough free space.
 ///
 pub async fn download_and_install_owml(config: &Config, owml: &RemoteMod) -> Result<()> {
     let url = &owml.download_url;
-    let target_path = PathBuf::from(&config.owml_path);
+    let target_path = PathBuf::from(config.get_owml_path());
     let temp_dir = TempDir::new()?;
     let download_path = temp_dir.path().join("OWML.zip");
-    download_zip(url, &download_path).await?;
+    download_file(url, &download_path).await?;
     extract_zip(&download_path, &target_path, "OWML")?;
 
-    if config.owml_path.is_empty() {
+    // OWML finds Outer Wilds by itself, but it has no idea where Alpha or the game of an instance is
+    if let Some(game_path) = config.get_game_path() {
+        let mut owml_config = OWMLConfig::get(config)?;
+        owml_config.game_path = game_path.to_string();
+        owml_config.save(config)?;
+    }
+
+    if config.get_owml_path().is_empty() {
         let mut new_config = config.clone();
-        new_config.owml_path = String::from(target_path.to_str().unwrap());
+        new_config.set_owml_path(target_path.to_str().unwrap());
         new_config.save()?;
     }
 
@@ -215,7 +943,9 @@ pub async fn download_and_install_owml(config: &Config, owml: &RemoteMod) -> Res
     Ok(())
 }
 
-/// Install a mod from a local ZIP file
+/// Install a mod from a local archive or an unpacked mod folder.
+/// Zip, tar.gz, and tar.zst archives are supported, along with 7z when the `7z` feature is enabled.
+/// The format is detected from the file's contents, not its extension.
 ///
 /// ## Returns
 ///
@@ -223,32 +953,62 @@ pub async fn download_and_install_owml(config: &Config, owml: &RemoteMod) -> Res
 ///
 /// ## Errors
 ///
-/// - If we can't find a `manifest.json` file within the archive
-/// - If we can't extract the zip file
+/// - If we can't find a `manifest.json` file within the archive or folder
+/// - If the archive isn't a supported format or we can't extract it
+/// - If the archive has links, paths that escape the mod folder, or is over the size limits
+/// - If the mod is already installed and linked with [crate::dev::link_mod]
+/// - If the folder is the one the mod is already installed to
+/// - If there isn't enough free space for the mod
 ///
-pub fn install_mod_from_zip(
-    zip_path: &PathBuf,
+pub fn install_mod_from_file(
+    path: &Path,
     config: &Config,
     local_db: &LocalDatabase,
 ) -> Result<LocalMod> {
-    let unique_name = get_unique_name_from_zip(zip_path)?;
+    let mut source = ModSource::open(path)?;
+    let unique_name = source.manifest().unique_name.clone();
     let target_path = local_db
         .get_mod_unsafe(&unique_name)
         .map(|m| PathBuf::from(m.get_path().to_string()))
         .unwrap_or_else(|| {
-            PathBuf::from(&config.owml_path)
+            PathBuf::from(config.get_owml_path())
                 .join("Mods")
                 .join(&unique_name)
         });
     let local_mod = local_db.get_mod(&unique_name);
 
+    if let ModSource::Dir(mod_dir) = &source {
+        if mod_dir.root.canonicalize().ok() == target_path.canonicalize().ok() {
+            return Err(anyhow!(
+                "{} is already installed from {}",
+                unique_name,
+                path.to_str().unwrap()
+            ));
+        }
+    }
+
+    if let Some(local_mod) = local_mod.filter(|m| m.linked) {
+        return Err(anyhow!(
+            "{} is linked to a development folder, unlink it before installing over it",
+            local_mod.manifest.name
+        ));
+    }
+
+    // Check before removing anything so a full drive doesn't leave the mod half installed,
+    // the old files are replaced so only the difference needs to fit
+    let old_size = local_mod
+        .and_then(|m| m.get_install_size().ok())
+        .unwrap_or(0);
+    let needed = source.get_extracted_size()?.saturating_sub(old_size);
+    check_free_space(&target_path, needed, &source.manifest().name)?;
+
     if let Some(local_mod) = local_mod {
         remove_old_mod_files(local_mod)?;
     }
 
     let paths_to_preserve = get_paths_to_preserve(local_mod);
 
-    let new_mod = extract_mod_zip(zip_path, &target_path, paths_to_preserve)?;
+    let new_mod = source.extract(&target_path, paths_to_preserve)?;
     let config_path = target_path.join("config.json");
     if local_mod.is_none() || !config_path.is_file() {
         // First install, generate config
@@ -265,8 +1025,8 @@ pub fn install_mod_from_zip(
 ///
 /// ## Errors
 ///
-/// - We can't download the ZIP file
-/// - We can't extract the ZIP file
+/// - We can't download the archive
+/// - We can't extract the archive, see [install_mod_from_file]
 /// - There is no `manifest.json` present in the archive / it's not readable
 ///
 pub async fn install_mod_from_url(
@@ -274,13 +1034,16 @@ pub async fn install_mod_from_url(
     config: &Config,
     local_db: &LocalDatabase,
 ) -> Result<LocalMod> {
-    let zip_name = get_end_of_url(url).replace(".zip", "");
+    let file_name = match get_end_of_url(url) {
+        "" => "mod",
+        name => name,
+    };
 
     let temp_dir = TempDir::new()?;
-    let download_path = temp_dir.path().join(format!("{}.zip", zip_name));
+    let download_path = temp_dir.path().join(file_name);
 
-    download_zip(url, &download_path).await?;
-    let new_mod = install_mod_from_zip(&download_path, config, local_db)?;
+    download_file(url, &download_path).await?;
+    let new_mod = install_mod_from_file(&download_path, config, local_db)?;
 
     temp_dir.close()?;
 
@@ -324,7 +1087,10 @@ pub async fn install_mods_parallel(
 
 /// Install mod from the database with the given unique name.
 /// This should be the preferred method when installing a specific mod.
-/// It can also install prereleases and auto-install dependencies (recursively) as well.
+/// It can also install prereleases and auto-install dependencies (recursively) as well, addons will also get their parent mod installed this way.
+/// Mods installed as dependencies are marked with [InstallReason::Dependency] so [crate::remove::get_orphaned_mods] can find them later,
+/// the requested mod is always marked with [InstallReason::Explicit], even if it was already installed as a dependency.
+/// The mod remembers whether it was installed from its prerelease ([ReleaseChannel]) so later updates use the same channel.
 /// This will also send analytics events given you set `ANALYTICS_API_KEY`.
 ///
 /// ## Errors
@@ -361,9 +1127,24 @@ pub async fn install_mod_from_db(
         remote_mod.download_url.clone()
     };
     let new_mod = install_mod_from_url(&target_url, config, local_db).await?;
+    // So updates come from the same channel the mod was installed from
+    let channel = if prerelease {
+        ReleaseChannel::Prerelease
+    } else {
+        ReleaseChannel::Stable
+    };
+    write_channel(Path::new(&new_mod.mod_path), channel)?;
+    // Overwriting keeps the install reason, but asking for a mod by name means the user wants it
+    if new_mod.install_reason == InstallReason::Dependency {
+        write_install_reason(Path::new(&new_mod.mod_path), InstallReason::Explicit)?;
+    }
 
     if recursive {
         let mut to_install: Vec<String> = new_mod.manifest.dependencies.unwrap_or_default();
+        // Addons need their parent mod to do anything, so install it like a dependency
+        if let Some(parent) = remote_mod.parent.as_ref() {
+            to_install.push(parent.clone());
+        }
         let mut installed: Vec<String> = local_db
             .valid()
             .filter_map(|m| {
@@ -385,7 +1166,10 @@ pub async fn install_mod_from_db(
                 count,
                 installed.len()
             );
-            let newly_installed = install_mods_parallel(
+            // A parent is usually a dependency as well, don't install it twice
+            to_install.sort();
+            to_install.dedup();
+            let mut newly_installed = install_mods_parallel(
                 to_install
                     .drain(..)
                     .filter(|m| !installed.contains(m))
@@ -396,9 +1180,15 @@ pub async fn install_mod_from_db(
             )
             .await?;
             for installed_mod in newly_installed
-                .iter()
+                .iter_mut()
                 .filter(|m| &m.manifest.unique_name != unique_name)
             {
+                // So it can be cleaned up with autoremove once nothing needs it
+                write_install_reason(
+                    Path::new(&installed_mod.mod_path),
+                    InstallReason::Dependency,
+                )?;
+                installed_mod.install_reason = InstallReason::Dependency;
                 send_analytics_event(
                     AnalyticsEventName::ModRequiredInstall,
                     &installed_mod.manifest.unique_name,
@@ -412,6 +1202,12 @@ pub async fn install_mod_from_db(
                     .collect(),
             );
             for new_mod in newly_installed.into_iter() {
+                if let Some(parent) = remote_db
+                    .get_mod(&new_mod.manifest.unique_name)
+                    .and_then(|m| m.parent.as_ref())
+                {
+                    to_install.push(parent.clone());
+                }
                 if let Some(mut deps) = new_mod.manifest.dependencies {
                     to_install.append(&mut deps);
                 }
@@ -431,44 +1227,166 @@ pub async fn install_mod_from_db(
     send_analytics_event(mod_event, unique_name).await
 }
 
+/// Install the mods the database marks as required ([RemoteDatabase::required]) that aren't installed yet, along with their dependencies.
+/// This should be done when setting up OWML.
+///
+/// ## Returns
+///
+/// The unique names of the required mods that were installed, sorted
+///
+/// ## Errors
+///
+/// If we can't read the local database or install one of the mods.
+///
+pub async fn install_required_mods(
+    config: &Config,
+    remote_db: &RemoteDatabase,
+) -> Result<Vec<String>> {
+    let local_db = LocalDatabase::fetch(config.get_owml_path())?;
+    let mut missing = remote_db
+        .required()
+        .filter(|m| local_db.get_mod(&m.unique_name).is_none())
+        .map(|m| m.unique_name.clone())
+        .collect::<Vec<_>>();
+    missing.sort();
+    for unique_name in missing.iter() {
+        info!("Installing required mod {}", unique_name);
+        // Refetch so dependencies a previous required mod installed aren't installed again
+        let local_db = LocalDatabase::fetch(config.get_owml_path())?;
+        if local_db.get_mod(unique_name).is_none() {
+            install_mod_from_db(unique_name, config, remote_db, &local_db, true, false).await?;
+        }
+    }
+    Ok(missing)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
+        config::Game,
         file::serialize_to_json,
         mods::local::UnsafeLocalMod,
-        test_utils::{get_test_file, make_test_dir},
+        test_utils::{get_test_file, make_test_dir, MockFailure, MockServer},
+    };
+    use std::{
+        fs::read_to_string,
+        time::{Duration, Instant},
     };
-    use std::fs::read_to_string;
-
-    const TEST_URL: &str =
-        "https://github.com/Bwc9876/OW-TimeSaver/releases/download/1.1.1/Bwc9876.TimeSaver.zip";
 
     #[test]
-    fn test_download_zip() {
+    fn test_download_file() {
+        let server = MockServer::start();
         tokio_test::block_on(async {
             let dir = make_test_dir();
             let path = dir.path().join("test.zip");
-            download_zip(TEST_URL, &path).await.unwrap();
+            download_file(&server.url("Bwc9876.TimeSaver.zip"), &path)
+                .await
+                .unwrap();
             assert!(path.is_file());
             dir.close().unwrap();
         });
     }
 
+    #[test]
+    fn test_download_file_failures() {
+        let server = MockServer::start();
+        tokio_test::block_on(async {
+            let dir = make_test_dir();
+            let path = dir.path().join("test.zip");
+            let url = server.url("Bwc9876.TimeSaver.zip");
+            server.fail("Bwc9876.TimeSaver.zip", MockFailure::Status(500));
+            assert!(download_file(&url, &path).await.is_err());
+            server.fail("Bwc9876.TimeSaver.zip", MockFailure::Truncated);
+            assert!(download_file(&url, &path).await.is_err());
+            assert!(download_file(&server.url("Missing.zip"), &path)
+                .await
+                .is_err());
+            dir.close().unwrap();
+        });
+    }
+
+    #[test]
+    fn test_download_and_install_owml() {
+        let server = MockServer::start();
+        tokio_test::block_on(async {
+            let dir = make_test_dir();
+            let owml_path = dir.path().join("OWML");
+            let mut config = server.config(dir.path());
+            config.owml_path = owml_path.to_str().unwrap().to_string();
+            assert!(!config.check_owml());
+            let remote_db = RemoteDatabase::fetch(&config.database_url).await.unwrap();
+            download_and_install_owml(&config, remote_db.get_owml().unwrap())
+                .await
+                .unwrap();
+            assert!(config.check_owml());
+            let owml = LocalDatabase::get_owml(&config.owml_path).unwrap();
+            assert_eq!(owml.manifest.version, "2.9.0");
+            dir.close().unwrap();
+        });
+    }
+
+    #[test]
+    fn test_download_and_install_owml_alpha() {
+        let server = MockServer::start();
+        tokio_test::block_on(async {
+            let dir = make_test_dir();
+            let mut config = server.config(dir.path());
+            config.game = Game::Alpha;
+            config.alpha.owml_path = dir.path().join("OWML_Alpha").to_str().unwrap().to_string();
+            config.alpha.game_path = "/games/Outer Wilds Alpha".to_string();
+            let remote_db = RemoteDatabase::fetch(&config.database_url).await.unwrap();
+            download_and_install_owml(&config, remote_db.get_owml().unwrap())
+                .await
+                .unwrap();
+            assert!(config.check_owml());
+            assert!(dir
+                .path()
+                .join("OWML_Alpha")
+                .join("OWML.Config.json")
+                .is_file());
+            assert_eq!(
+                OWMLConfig::get(&config).unwrap().game_path,
+                "/games/Outer Wilds Alpha"
+            );
+            dir.close().unwrap();
+        });
+    }
+
     #[test]
     fn test_get_manifest_path() {
         let path = get_test_file("Bwc9876.NestedManifest.zip");
-        let (_, manifest_path) = get_manifest_path_from_zip(&path).unwrap();
+        let manifest_path = ModZip::open(&path).unwrap().manifest_path;
         assert_eq!(
             manifest_path,
             PathBuf::from("Bwc9876.NestedManifest/Folder1/Folder2/manifest.json")
         );
     }
 
+    #[test]
+    fn test_get_manifest_path_shallowest() {
+        let dir = make_test_dir();
+        let zip_path = dir.path().join("nested.zip");
+        make_zip(
+            &zip_path,
+            &[
+                (
+                    "Mod/Extras/manifest.json",
+                    br#"{"uniqueName": "Test.Extras", "name": "Extras", "author": "Test", "version": "1.0.0"}"#,
+                ),
+                ("Mod/manifest.json", TEST_MANIFEST),
+            ],
+        );
+        let zip = ModZip::open(&zip_path).unwrap();
+        assert_eq!(zip.manifest_path, PathBuf::from("Mod/manifest.json"));
+        assert_eq!(zip.manifest.unique_name, "Test.Mod");
+        dir.close().unwrap();
+    }
+
     #[test]
     fn test_get_unique_name() {
         let path = get_test_file("Bwc9876.TimeSaver.zip");
-        let name = get_unique_name_from_zip(&path).unwrap();
+        let name = ModZip::open(&path).unwrap().manifest.unique_name;
         assert_eq!(name, "Bwc9876.TimeSaver");
     }
 
@@ -483,6 +1401,453 @@ mod tests {
         dir.close().unwrap();
     }
 
+    const TEST_MANIFEST: &[u8] =
+        br#"{"uniqueName": "Test.Mod", "name": "Test", "author": "Test", "version": "1.0.0"}"#;
+
+    fn extract_mod_zip(
+        zip_path: &Path,
+        target_path: &Path,
+        exclude_paths: Vec<PathBuf>,
+    ) -> Result<LocalMod> {
+        ModZip::open(zip_path)?.extract(target_path, exclude_paths)
+    }
+
+    fn make_zip(path: &Path, files: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, contents) in files {
+            zip.start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_extract_zip_unsafe_paths() {
+        let dir = make_test_dir();
+        let target_path = dir.path().join("Target");
+        for name in ["../evil.txt", "/evil.txt", "Folder/../../evil.txt"] {
+            let zip_path = dir.path().join("evil.zip");
+            make_zip(
+                &zip_path,
+                &[("manifest.json", TEST_MANIFEST), (name, b"evil")],
+            );
+            let err = extract_zip(&zip_path, &target_path, "Evil").err().unwrap();
+            assert!(err.to_string().contains("unsafe path"));
+            let err = extract_mod_zip(&zip_path, &target_path, vec![])
+                .err()
+                .unwrap();
+            assert!(err.to_string().contains("unsafe path"));
+        }
+        assert!(!dir.path().join("evil.txt").exists());
+        assert!(!target_path.join("manifest.json").exists());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_extract_zip_symlink() {
+        let dir = make_test_dir();
+        let zip_path = dir.path().join("link.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+        zip.add_symlink("link", "/etc/passwd", zip::write::FileOptions::default())
+            .unwrap();
+        zip.finish().unwrap();
+        let err = extract_zip(&zip_path, &dir.path().join("Target"), "Link")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("symlink"));
+        assert!(!dir.path().join("Target").join("link").exists());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_extract_zip_bomb() {
+        let dir = make_test_dir();
+        let zip_path = dir.path().join("bomb.zip");
+        let zeros = vec![0; 4 * 1024 * 1024];
+        make_zip(
+            &zip_path,
+            &[("manifest.json", TEST_MANIFEST), ("zeros.bin", &zeros)],
+        );
+        let err = extract_mod_zip(&zip_path, &dir.path().join("Target"), vec![])
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("zip bomb"));
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_check_zip_entries_limits() {
+        let dir = make_test_dir();
+        let zip_path = dir.path().join("big.zip");
+        make_zip(
+            &zip_path,
+            &[("a.txt", b"aaaa"), ("b.txt", b"bbbb"), ("c.txt", b"cccc")],
+        );
+        let mut archive = ZipArchive::new(File::open(&zip_path).unwrap()).unwrap();
+        let select = |p: &Path| Some(p.to_path_buf());
+        let limits = ExtractLimits {
+            max_entries: 2,
+            ..ExtractLimits::default()
+        };
+        let err = check_zip_entries(&mut archive, "big.zip", &limits, select)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("too many files"));
+        let limits = ExtractLimits {
+            max_size: 10,
+            ..ExtractLimits::default()
+        };
+        let err = check_zip_entries(&mut archive, "big.zip", &limits, select)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("too big"));
+        let (entries, total) =
+            check_zip_entries(&mut archive, "big.zip", &ExtractLimits::default(), select).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(total, 12);
+        dir.close().unwrap();
+    }
+
+    /// How extraction used to work, opening the zip three times and copying one byte at a time
+    fn extract_bytewise(zip_path: &Path, target_path: &Path) {
+        let manifest_name = {
+            let mut archive = ZipArchive::new(File::open(zip_path).unwrap()).unwrap();
+            let name = (0..archive.len())
+                .map(|i| archive.by_index(i).unwrap().name().to_string())
+                .find(|n| n.ends_with("manifest.json"))
+                .unwrap();
+            name
+        };
+        let mut archive = ZipArchive::new(File::open(zip_path).unwrap()).unwrap();
+        let mut buf = String::new();
+        archive
+            .by_name(&manifest_name)
+            .unwrap()
+            .read_to_string(&mut buf)
+            .unwrap();
+        let mut archive = ZipArchive::new(File::open(zip_path).unwrap()).unwrap();
+        for idx in 0..archive.len() {
+            let zip_file = archive.by_index(idx).unwrap();
+            let output_path = target_path.join(zip_file.enclosed_name().unwrap());
+            create_all_parents(&output_path).unwrap();
+            let mut writer = BufWriter::new(File::create(&output_path).unwrap());
+            for byte in BufReader::new(zip_file).bytes() {
+                writer.write_all(&[byte.unwrap()]).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with --ignored --nocapture to see the timings"]
+    fn test_extract_bench() {
+        const FILE_COUNT: usize = 40;
+        const FILE_SIZE: usize = 512 * 1024;
+        let dir = make_test_dir();
+        // Stand-in for a big story mod's assets, semi-random so it compresses like real assets
+        let mut seed: u32 = 9876;
+        let asset: Vec<u8> = (0..FILE_SIZE)
+            .map(|i| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                if i % 4 == 0 {
+                    (seed >> 16) as u8
+                } else {
+                    b'a' + (i % 26) as u8
+                }
+            })
+            .collect();
+        // Compressing is slow in debug builds, so compress the asset once and copy it in
+        let asset_zip_path = dir.path().join("asset.zip");
+        make_zip(&asset_zip_path, &[("asset.bin", &asset)]);
+        let mut asset_zip = ZipArchive::new(File::open(&asset_zip_path).unwrap()).unwrap();
+        let zip_path = dir.path().join("Bench.BigMod.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+        zip.start_file("manifest.json", zip::write::FileOptions::default())
+            .unwrap();
+        zip.write_all(TEST_MANIFEST).unwrap();
+        for i in 0..FILE_COUNT {
+            zip.raw_copy_file_rename(
+                asset_zip.by_index(0).unwrap(),
+                format!("assets/bundle{i}.bin"),
+            )
+            .unwrap();
+        }
+        zip.finish().unwrap();
+
+        let old_path = dir.path().join("Old");
+        let start = Instant::now();
+        extract_bytewise(&zip_path, &old_path);
+        let old = start.elapsed();
+
+        let new_path = dir.path().join("New");
+        let start = Instant::now();
+        let new_mod = extract_mod_zip(&zip_path, &new_path, vec![]).unwrap();
+        let new = start.elapsed();
+
+        assert_eq!(new_mod.manifest.unique_name, "Test.Mod");
+        for i in 0..FILE_COUNT {
+            let path = new_path.join("assets").join(format!("bundle{i}.bin"));
+            assert_eq!(std::fs::read(path).unwrap(), asset);
+        }
+        println!(
+            "Extracted {} MB in {new:?} buffered and parallel, {old:?} byte by byte",
+            FILE_COUNT * FILE_SIZE / 1024 / 1024
+        );
+        dir.close().unwrap();
+    }
+
+    fn make_tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        for (name, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, name, *contents).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    const TEST_TAR_FILES: &[(&str, &[u8])] = &[
+        ("Test.Mod/manifest.json", TEST_MANIFEST),
+        ("Test.Mod/Test.Mod.dll", b"not really a dll"),
+        ("Test.Mod/assets/bundle", b"assets"),
+    ];
+
+    fn check_test_mod_installed(dir: &Path, new_mod: &LocalMod) {
+        let target_path = dir.join("Mods").join("Test.Mod");
+        assert_eq!(new_mod.manifest.unique_name, "Test.Mod");
+        assert_eq!(new_mod.mod_path, target_path.to_str().unwrap());
+        assert!(target_path.join("Test.Mod.dll").is_file());
+        assert!(target_path.join("assets").join("bundle").is_file());
+        assert!(target_path.join("config.json").is_file());
+    }
+
+    #[test]
+    fn test_archive_format_detect() {
+        let dir = make_test_dir();
+        let zip_path = get_test_file("Bwc9876.TimeSaver.zip");
+        assert_eq!(
+            ArchiveFormat::detect(&zip_path).unwrap(),
+            ArchiveFormat::Zip
+        );
+        // Extensions don't matter, only the contents
+        let cases: [(&[u8], ArchiveFormat); 3] = [
+            (&[0x1f, 0x8b, 0x08, 0x00], ArchiveFormat::TarGz),
+            (&[0x28, 0xb5, 0x2f, 0xfd, 0x00], ArchiveFormat::TarZst),
+            (
+                &[0x37, 0x7a, 0xbc, 0xaf, 0x27, 0x1c, 0x00],
+                ArchiveFormat::SevenZip,
+            ),
+        ];
+        for (magic, format) in cases {
+            let path = dir.path().join("mod.zip");
+            fs::write(&path, magic).unwrap();
+            assert_eq!(ArchiveFormat::detect(&path).unwrap(), format);
+        }
+        let path = dir.path().join("mod.tar.gz");
+        fs::write(&path, "not an archive").unwrap();
+        let err = ArchiveFormat::detect(&path).err().unwrap();
+        assert!(err.to_string().contains("isn't a supported archive"));
+        fs::write(&path, "").unwrap();
+        assert!(ArchiveFormat::detect(&path).is_err());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_install_mod_from_tar_gz() {
+        let dir = make_test_dir();
+        let mut config = Config::default(None).unwrap();
+        config.owml_path = dir.path().to_str().unwrap().to_string();
+        let archive_path = dir.path().join("Test.Mod.tar.gz");
+        let mut encoder =
+            flate2::write::GzEncoder::new(File::create(&archive_path).unwrap(), Default::default());
+        encoder.write_all(&make_tar(TEST_TAR_FILES)).unwrap();
+        encoder.finish().unwrap();
+        let new_mod =
+            install_mod_from_file(&archive_path, &config, &LocalDatabase::default()).unwrap();
+        check_test_mod_installed(dir.path(), &new_mod);
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_install_mod_from_tar_zst() {
+        let dir = make_test_dir();
+        let mut config = Config::default(None).unwrap();
+        config.owml_path = dir.path().to_str().unwrap().to_string();
+        // Wrong extension on purpose
+        let archive_path = dir.path().join("Test.Mod.zip");
+        let compressed = zstd::encode_all(&make_tar(TEST_TAR_FILES)[..], 0).unwrap();
+        fs::write(&archive_path, compressed).unwrap();
+        let new_mod =
+            install_mod_from_file(&archive_path, &config, &LocalDatabase::default()).unwrap();
+        check_test_mod_installed(dir.path(), &new_mod);
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_install_mod_from_dir() {
+        let dir = make_test_dir();
+        let mut config = Config::default(None).unwrap();
+        config.owml_path = dir.path().to_str().unwrap().to_string();
+        let source_path = dir.path().join("Downloads");
+        for (name, contents) in TEST_TAR_FILES {
+            let path = source_path.join("Nested").join(name);
+            create_all_parents(&path).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        let new_mod =
+            install_mod_from_file(&source_path, &config, &LocalDatabase::default()).unwrap();
+        check_test_mod_installed(dir.path(), &new_mod);
+        assert!(!dir
+            .path()
+            .join("Mods")
+            .join("Test.Mod")
+            .join("Nested")
+            .exists());
+        // The source is left alone
+        assert!(source_path
+            .join("Nested")
+            .join("Test.Mod")
+            .join("manifest.json")
+            .is_file());
+
+        let local_db = LocalDatabase::fetch(dir.path().to_str().unwrap()).unwrap();
+        let installed_path = dir.path().join("Mods").join("Test.Mod");
+        let err = install_mod_from_file(&installed_path, &config, &local_db)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("already installed"));
+        assert!(installed_path.join("Test.Mod.dll").is_file());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_install_mod_from_dir_no_manifest() {
+        let dir = make_test_dir();
+        let mut config = Config::default(None).unwrap();
+        config.owml_path = dir.path().to_str().unwrap().to_string();
+        let source_path = dir.path().join("Empty");
+        create_dir_all(source_path.join("Folder")).unwrap();
+        let err = install_mod_from_file(&source_path, &config, &LocalDatabase::default())
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Manifest not found"));
+        dir.close().unwrap();
+    }
+
+    /// Make a tar with an entry whose path isn't checked by the tar crate when writing
+    fn make_raw_tar(name: &str, entry_type: tar::EntryType, link: Option<&str>) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        let mut header = tar::Header::new_gnu();
+        let raw_name = &mut header.as_gnu_mut().unwrap().name;
+        raw_name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_entry_type(entry_type);
+        header.set_mode(0o644);
+        if let Some(link) = link {
+            header.set_link_name(link).unwrap();
+        }
+        header.set_size(4);
+        header.set_cksum();
+        builder.append(&header, &b"evil"[..]).unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_unpack_tar_unsafe() {
+        let dir = make_test_dir();
+        let target_path = dir.path().join("Target");
+        let limits = ExtractLimits::default();
+        for name in ["../evil.txt", "/evil.txt", "Folder/../../evil.txt"] {
+            let tar = make_raw_tar(name, tar::EntryType::Regular, None);
+            let err = unpack_tar(&tar[..], "evil.tar", 1024, &target_path, &limits)
+                .err()
+                .unwrap();
+            assert!(err.to_string().contains("unsafe path"));
+        }
+        let tar = make_raw_tar("link", tar::EntryType::Symlink, Some("/etc/passwd"));
+        let err = unpack_tar(&tar[..], "link.tar", 1024, &target_path, &limits)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("link"));
+        let tar = make_raw_tar("hard", tar::EntryType::Link, Some("/etc/passwd"));
+        assert!(unpack_tar(&tar[..], "link.tar", 1024, &target_path, &limits).is_err());
+        assert!(!dir.path().join("evil.txt").exists());
+        assert!(!target_path.join("link").exists());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_unpack_tar_limits() {
+        let dir = make_test_dir();
+        let target_path = dir.path().join("Target");
+        let tar = make_tar(&[("a.txt", b"aaaa"), ("b.txt", b"bbbb"), ("c.txt", b"cccc")]);
+        let limits = ExtractLimits {
+            max_entries: 2,
+            ..ExtractLimits::default()
+        };
+        let err = unpack_tar(&tar[..], "big.tar", 1024, &target_path, &limits)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("too many files"));
+        let limits = ExtractLimits {
+            max_size: 10,
+            ..ExtractLimits::default()
+        };
+        let err = unpack_tar(&tar[..], "big.tar", 1024, &target_path, &limits)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("too big"));
+        let zeros = vec![0; 4 * 1024 * 1024];
+        let tar = make_tar(&[("zeros.bin", &zeros)]);
+        let compressed = zstd::encode_all(&tar[..], 0).unwrap();
+        let err = unpack_tar(
+            zstd::Decoder::new(&compressed[..]).unwrap(),
+            "bomb.tar.zst",
+            compressed.len() as u64,
+            &target_path,
+            &ExtractLimits::default(),
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("archive bomb"));
+        dir.close().unwrap();
+    }
+
+    #[cfg(feature = "7z")]
+    #[test]
+    fn test_install_mod_from_7z() {
+        let dir = make_test_dir();
+        let mut config = Config::default(None).unwrap();
+        config.owml_path = dir.path().to_str().unwrap().to_string();
+        let source_path = dir.path().join("Source");
+        for (name, contents) in TEST_TAR_FILES {
+            let path = source_path.join(name);
+            create_all_parents(&path).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        let archive_path = dir.path().join("Test.Mod.7z");
+        sevenz_rust::compress_to_path(&source_path, &archive_path).unwrap();
+        let new_mod =
+            install_mod_from_file(&archive_path, &config, &LocalDatabase::default()).unwrap();
+        check_test_mod_installed(dir.path(), &new_mod);
+        dir.close().unwrap();
+    }
+
+    #[cfg(not(feature = "7z"))]
+    #[test]
+    fn test_install_mod_from_7z_unsupported() {
+        let dir = make_test_dir();
+        let mut config = Config::default(None).unwrap();
+        config.owml_path = dir.path().to_str().unwrap().to_string();
+        let archive_path = dir.path().join("Test.Mod.7z");
+        fs::write(&archive_path, [0x37, 0x7a, 0xbc, 0xaf, 0x27, 0x1c, 0x00]).unwrap();
+        let err = install_mod_from_file(&archive_path, &config, &LocalDatabase::default())
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("doesn't support"));
+        dir.close().unwrap();
+    }
+
     #[test]
     fn test_extract_mod_zip_nested() {
         let zip_path = get_test_file("Bwc9876.NestedManifest.zip");
@@ -519,14 +1884,14 @@ mod tests {
     }
 
     #[test]
-    fn test_install_mod_from_zip() {
+    fn test_install_mod_from_file() {
         let zip_path = get_test_file("Bwc9876.TimeSaver.zip");
         let dir = make_test_dir();
         let target_path = dir.path().join("Mods").join("Bwc9876.TimeSaver");
         let mut config = Config::default(None).unwrap();
         config.owml_path = dir.path().to_str().unwrap().to_string();
         let db = LocalDatabase::default();
-        let new_mod = install_mod_from_zip(&zip_path, &config, &db).unwrap();
+        let new_mod = install_mod_from_file(&zip_path, &config, &db).unwrap();
         assert!(target_path.is_dir());
         assert!(target_path.join("config.json").is_file());
         assert!(target_path.join("manifest.json").is_file());
@@ -535,6 +1900,23 @@ mod tests {
         dir.close().unwrap();
     }
 
+    #[test]
+    fn test_install_mod_from_file_keeps_install_reason() {
+        let zip_path = get_test_file("Bwc9876.TimeSaver.zip");
+        let dir = make_test_dir();
+        let target_path = dir.path().join("Mods").join("Bwc9876.TimeSaver");
+        let mut config = Config::default(None).unwrap();
+        config.owml_path = dir.path().to_str().unwrap().to_string();
+        let db = LocalDatabase::default();
+        let new_mod = install_mod_from_file(&zip_path, &config, &db).unwrap();
+        assert_eq!(new_mod.install_reason, InstallReason::Explicit);
+        write_install_reason(&target_path, InstallReason::Dependency).unwrap();
+        let db = LocalDatabase::fetch(dir.path().to_str().unwrap()).unwrap();
+        let new_mod = install_mod_from_file(&zip_path, &config, &db).unwrap();
+        assert_eq!(new_mod.install_reason, InstallReason::Dependency);
+        dir.close().unwrap();
+    }
+
     #[test]
     fn test_install_from_zip_diff_path() {
         let zip_path = get_test_file("Bwc9876.TimeSaver.zip");
@@ -548,7 +1930,7 @@ mod tests {
             "Bwc9876.TimeSaver".to_string(),
             UnsafeLocalMod::Valid(new_mod),
         );
-        let new_mod = install_mod_from_zip(&zip_path, &config, &db).unwrap();
+        let new_mod = install_mod_from_file(&zip_path, &config, &db).unwrap();
         assert!(!dir.path().join("Mods").join("Bwc9876.TimeSaver").is_dir());
         assert!(target_path.is_dir());
         assert!(target_path.join("manifest.json").is_file());
@@ -559,13 +1941,15 @@ mod tests {
 
     #[test]
     fn test_install_mod_from_url() {
+        let server = MockServer::start();
         tokio_test::block_on(async {
             let dir = make_test_dir();
             let target_path = dir.path().join("Mods").join("Bwc9876.TimeSaver");
-            let mut config = Config::default(None).unwrap();
-            config.owml_path = dir.path().to_str().unwrap().to_string();
+            let config = server.config(dir.path());
             let db = LocalDatabase::default();
-            let new_mod = install_mod_from_url(TEST_URL, &config, &db).await.unwrap();
+            let new_mod = install_mod_from_url(&server.url("Bwc9876.TimeSaver.zip"), &config, &db)
+                .await
+                .unwrap();
             assert!(target_path.is_dir());
             assert_eq!(new_mod.mod_path, target_path.to_str().unwrap());
             dir.close().unwrap();
@@ -574,11 +1958,11 @@ mod tests {
 
     #[test]
     fn test_install_mods_parallel() {
+        let server = MockServer::start();
         tokio_test::block_on(async {
             let dir = make_test_dir();
-            let mut config = Config::default(None).unwrap();
+            let config = server.config(dir.path());
             let target_path = dir.path().join("Mods");
-            config.owml_path = dir.path().to_str().unwrap().to_string();
             let remote_db = RemoteDatabase::fetch(&config.database_url).await.unwrap();
             let local_db = LocalDatabase::default();
             let mods: Vec<String> = vec![
@@ -595,13 +1979,68 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_install_mods_parallel_slow() {
+        let server = MockServer::start();
+        server.fail(
+            "Bwc9876.TimeSaver.zip",
+            MockFailure::Slow(Duration::from_millis(500)),
+        );
+        tokio_test::block_on(async {
+            let dir = make_test_dir();
+            let config = server.config(dir.path());
+            let remote_db = RemoteDatabase::fetch(&config.database_url).await.unwrap();
+            let local_db = LocalDatabase::default();
+            let mods = install_mods_parallel(
+                vec![
+                    "Bwc9876.TimeSaver".to_string(),
+                    "Bwc9876.SaveEditor".to_string(),
+                ],
+                &config,
+                &remote_db,
+                &local_db,
+            )
+            .await
+            .unwrap();
+            assert_eq!(mods.len(), 2);
+            assert!(dir.path().join("Mods").join("Bwc9876.TimeSaver").is_dir());
+            assert_eq!(server.request_count("Bwc9876.TimeSaver.zip"), 1);
+            dir.close().unwrap();
+        });
+    }
+
+    #[test]
+    fn test_install_mods_parallel_failure() {
+        let server = MockServer::start();
+        server.fail("Bwc9876.SaveEditor.zip", MockFailure::Status(500));
+        tokio_test::block_on(async {
+            let dir = make_test_dir();
+            let config = server.config(dir.path());
+            let remote_db = RemoteDatabase::fetch(&config.database_url).await.unwrap();
+            let local_db = LocalDatabase::default();
+            let result = install_mods_parallel(
+                vec![
+                    "Bwc9876.TimeSaver".to_string(),
+                    "Bwc9876.SaveEditor".to_string(),
+                ],
+                &config,
+                &remote_db,
+                &local_db,
+            )
+            .await;
+            assert!(result.is_err());
+            assert!(!dir.path().join("Mods").join("Bwc9876.SaveEditor").is_dir());
+            dir.close().unwrap();
+        });
+    }
+
     #[test]
     fn test_install_mod_from_db() {
+        let server = MockServer::start();
         tokio_test::block_on(async {
             let dir = make_test_dir();
-            let mut config = Config::default(None).unwrap();
+            let config = server.config(dir.path());
             let target_path = dir.path().join("Mods").join("Bwc9876.TimeSaver");
-            config.owml_path = dir.path().to_str().unwrap().to_string();
             let remote_db = RemoteDatabase::fetch(&config.database_url).await.unwrap();
             let local_db = LocalDatabase::default();
             install_mod_from_db(
@@ -619,17 +2058,110 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_install_mod_from_db_promotes_dependency() {
+        let server = MockServer::start();
+        tokio_test::block_on(async {
+            let dir = make_test_dir();
+            let config = server.config(dir.path());
+            let target_path = dir.path().join("Mods").join("Bwc9876.TimeSaver");
+            let remote_db = RemoteDatabase::fetch(&config.database_url).await.unwrap();
+            install_mod_from_file(
+                &get_test_file("Bwc9876.TimeSaver.zip"),
+                &config,
+                &LocalDatabase::default(),
+            )
+            .unwrap();
+            write_install_reason(&target_path, InstallReason::Dependency).unwrap();
+            let local_db = LocalDatabase::fetch(config.get_owml_path()).unwrap();
+            install_mod_from_db(
+                &"Bwc9876.TimeSaver".to_string(),
+                &config,
+                &remote_db,
+                &local_db,
+                false,
+                false,
+            )
+            .await
+            .unwrap();
+            let local_db = LocalDatabase::fetch(config.get_owml_path()).unwrap();
+            assert_eq!(
+                local_db
+                    .get_mod("Bwc9876.TimeSaver")
+                    .unwrap()
+                    .install_reason,
+                InstallReason::Explicit
+            );
+            dir.close().unwrap();
+        });
+    }
+
+    #[test]
+    fn test_install_mod_from_db_prerelease() {
+        let server = MockServer::start();
+        tokio_test::block_on(async {
+            let dir = make_test_dir();
+            let config = server.config(dir.path());
+            let unique_name = "Bwc9876.SaveEditor".to_string();
+            let remote_db = RemoteDatabase::fetch(&config.database_url).await.unwrap();
+            let local_db = LocalDatabase::default();
+            install_mod_from_db(&unique_name, &config, &remote_db, &local_db, false, true)
+                .await
+                .unwrap();
+            let local_db = LocalDatabase::fetch(&config.owml_path).unwrap();
+            assert_eq!(
+                local_db.get_mod(&unique_name).unwrap().channel,
+                ReleaseChannel::Prerelease
+            );
+            install_mod_from_db(&unique_name, &config, &remote_db, &local_db, false, false)
+                .await
+                .unwrap();
+            let local_db = LocalDatabase::fetch(&config.owml_path).unwrap();
+            assert_eq!(
+                local_db.get_mod(&unique_name).unwrap().channel,
+                ReleaseChannel::Stable
+            );
+            assert!(install_mod_from_db(
+                &"Bwc9876.TimeSaver".to_string(),
+                &config,
+                &remote_db,
+                &local_db,
+                false,
+                true
+            )
+            .await
+            .is_err());
+            dir.close().unwrap();
+        });
+    }
+
+    #[test]
+    fn test_install_required_mods() {
+        let server = MockServer::start();
+        tokio_test::block_on(async {
+            let dir = make_test_dir();
+            let config = server.config(dir.path());
+            let remote_db = RemoteDatabase::fetch(&config.database_url).await.unwrap();
+            let installed = install_required_mods(&config, &remote_db).await.unwrap();
+            assert_eq!(installed, vec!["Bwc9876.TimeSaver"]);
+            assert!(dir.path().join("Mods").join("Bwc9876.TimeSaver").is_dir());
+            let installed = install_required_mods(&config, &remote_db).await.unwrap();
+            assert!(installed.is_empty());
+            dir.close().unwrap();
+        });
+    }
+
     #[test]
     fn test_install_mod_from_db_recursive() {
+        let server = MockServer::start();
         tokio_test::block_on(async {
             let dir = make_test_dir();
             let zip_path = get_test_file("Bwc9876.TimeSaver.zip");
-            let mut config = Config::default(None).unwrap();
+            let config = server.config(dir.path());
             let target_path = dir.path().join("Mods").join("Bwc9876.TimeSaver");
-            config.owml_path = dir.path().to_str().unwrap().to_string();
             let remote_db = RemoteDatabase::fetch(&config.database_url).await.unwrap();
             let mut local_db = LocalDatabase::default();
-            let mut new_mod = install_mod_from_zip(&zip_path, &config, &local_db).unwrap();
+            let mut new_mod = install_mod_from_file(&zip_path, &config, &local_db).unwrap();
             new_mod.manifest.dependencies = Some(vec!["Bwc9876.SaveEditor".to_string()]);
             new_mod.manifest.paths_to_preserve = Some(vec!["manifest.json".to_string()]);
             serialize_to_json(&new_mod.manifest, &target_path.join("manifest.json"), true).unwrap();
@@ -648,24 +2180,39 @@ mod tests {
             .await
             .unwrap();
             assert!(dir.path().join("Mods").join("Bwc9876.TimeSaver").is_dir());
+            let local_db = LocalDatabase::fetch(dir.path().to_str().unwrap()).unwrap();
+            assert_eq!(
+                local_db
+                    .get_mod("Bwc9876.TimeSaver")
+                    .unwrap()
+                    .install_reason,
+                InstallReason::Explicit
+            );
+            assert_eq!(
+                local_db
+                    .get_mod("Bwc9876.SaveEditor")
+                    .unwrap()
+                    .install_reason,
+                InstallReason::Dependency
+            );
             dir.close().unwrap();
         });
     }
 
     #[test]
     fn test_install_mod_from_db_cyclical_deps() {
+        let server = MockServer::start();
         tokio_test::block_on(async {
             let dir = make_test_dir();
             let zip_path = get_test_file("Bwc9876.TimeSaver.zip");
             let zip_path_2 = get_test_file("Bwc9876.SaveEditor.zip");
-            let mut config = Config::default(None).unwrap();
+            let config = server.config(dir.path());
             let target_path = dir.path().join("Mods").join("Bwc9876.TimeSaver");
             let target_path_2 = dir.path().join("Mods").join("Bwc9876.SaveEditor");
-            config.owml_path = dir.path().to_str().unwrap().to_string();
             let remote_db = RemoteDatabase::fetch(&config.database_url).await.unwrap();
             let mut local_db = LocalDatabase::default();
-            let mut new_mod = install_mod_from_zip(&zip_path, &config, &local_db).unwrap();
-            let mut new_mod_2 = install_mod_from_zip(&zip_path_2, &config, &local_db).unwrap();
+            let mut new_mod = install_mod_from_file(&zip_path, &config, &local_db).unwrap();
+            let mut new_mod_2 = install_mod_from_file(&zip_path_2, &config, &local_db).unwrap();
             new_mod.manifest.dependencies = Some(vec!["Bwc9876.SaveEditor".to_string()]);
             new_mod.manifest.paths_to_preserve = Some(vec!["manifest.json".to_string()]);
             new_mod_2.manifest.dependencies = Some(vec!["Bwc9876.TimeSaver".to_string()]);
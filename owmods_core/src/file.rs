@@ -1,5 +1,5 @@
 use std::{
-    fs::{create_dir_all, read_to_string, File},
+    fs::{create_dir_all, read_dir, read_to_string, File},
     io::{BufReader, BufWriter, Write},
     path::{Path, PathBuf},
 };
@@ -7,6 +7,7 @@ use std::{
 use anyhow::{anyhow, Result};
 use directories::{BaseDirs, ProjectDirs};
 use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
 
 use crate::constants::OLD_MANAGER_FOLDER_NAME;
 
@@ -51,6 +52,37 @@ where
     Ok(())
 }
 
+/// Serialize an object to a JSON file atomically, it's written to a temporary file next to `out_path` that then replaces it.
+/// If writing fails `out_path` is left as it was.
+///
+/// ## Errors
+///
+/// If we can't write the temporary file or move it to `out_path`.
+///
+pub fn serialize_to_json_atomic<T>(obj: &T, out_path: &Path, create_parents: bool) -> Result<()>
+where
+    T: Serialize,
+{
+    if create_parents {
+        create_all_parents(out_path)?;
+    }
+    let parent = out_path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let temp_file = NamedTempFile::new_in(parent)?;
+    {
+        let mut buffer = BufWriter::new(temp_file.as_file());
+        serde_json::to_writer_pretty(&mut buffer, obj)?;
+        buffer.flush()?;
+    }
+    temp_file.as_file().sync_all()?;
+    temp_file
+        .persist(out_path)
+        .map_err(|why| anyhow!("Couldn't write {}: {}", out_path.display(), why.error))?;
+    Ok(())
+}
+
 /// Utility function to get the application directory in the user's files
 /// You should prefer to store settings and such here to keep everything centralized.
 ///
@@ -87,6 +119,16 @@ pub fn get_default_owml_path() -> Result<PathBuf> {
     Ok(appdata_dir.join(OLD_MANAGER_FOLDER_NAME).join("OWML"))
 }
 
+/// Get the default path OWML should be installed to for Outer Wilds Alpha, next to the one for Outer Wilds
+///
+/// ## Errors
+///
+/// If we can't get the user's app data dir (or equivalent on Linux)
+///
+pub fn get_default_alpha_owml_path() -> Result<PathBuf> {
+    Ok(get_default_owml_path()?.with_file_name("OWML_Alpha"))
+}
+
 /// Fix a string of JSON by removing the BOM
 pub fn fix_json(txt: &str) -> String {
     fix_bom(txt).to_string()
@@ -124,6 +166,76 @@ pub fn create_all_parents(file_path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Get the total size of every file in a folder, symlinks inside the folder aren't followed
+///
+/// ## Errors
+///
+/// If we can't read the folder or one of the folders in it
+///
+pub fn get_dir_size(path: &Path) -> Result<u64> {
+    let mut total: u64 = 0;
+    for entry in read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.path().symlink_metadata()?;
+        if metadata.is_dir() {
+            total = total.saturating_add(get_dir_size(&entry.path())?);
+        } else if metadata.is_file() {
+            total = total.saturating_add(metadata.len());
+        }
+    }
+    Ok(total)
+}
+
+/// Get how many bytes are free on the drive a path is on, the path doesn't need to exist yet
+///
+/// ## Errors
+///
+/// If none of the path's parents exist or we can't get the free space of the drive
+///
+pub fn get_available_space(path: &Path) -> Result<u64> {
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .ok_or_else(|| anyhow!("Couldn't find the drive {} is on", path.display()))?;
+    Ok(fs2::available_space(existing)?)
+}
+
+/// Make sure there's enough free space for something before we start writing it
+///
+/// ## Errors
+///
+/// If there isn't `needed` bytes free on the drive `path` is on, or we can't tell how much is free
+///
+pub fn check_free_space(path: &Path, needed: u64, what: &str) -> Result<()> {
+    let available = get_available_space(path)?;
+    if available < needed {
+        Err(anyhow!(
+            "Not enough free space for {}, it needs {} but only {} is free at {}",
+            what,
+            format_size(needed),
+            format_size(available),
+            path.display()
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Format a number of bytes for people to read, ex: `1.5 MB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
 fn fix_bom(str: &str) -> &str {
     str.strip_prefix('\u{FEFF}').unwrap_or(str)
 }
@@ -132,8 +244,9 @@ fn fix_bom(str: &str) -> &str {
 mod tests {
 
     use super::*;
+    use crate::test_utils::make_test_dir;
 
-    #[derive(Deserialize)]
+    #[derive(Deserialize, Serialize)]
     struct TestStruct {
         prop: bool,
     }
@@ -155,4 +268,46 @@ mod tests {
         assert!(check_file_matches_paths(test_path, &[test_parent]));
         assert!(!check_file_matches_paths(test_path, &[unrelated_parent]),);
     }
+
+    #[test]
+    fn test_serialize_to_json_atomic() {
+        let dir = make_test_dir();
+        let path = dir.path().join("Folder").join("test.json");
+        serialize_to_json_atomic(&TestStruct { prop: false }, &path, true).unwrap();
+        serialize_to_json_atomic(&TestStruct { prop: true }, &path, true).unwrap();
+        let obj: TestStruct = deserialize_from_json(&path).unwrap();
+        assert!(obj.prop);
+        assert_eq!(read_dir(path.parent().unwrap()).unwrap().count(), 1);
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_get_dir_size() {
+        let dir = make_test_dir();
+        std::fs::write(dir.path().join("a.txt"), "aaaa").unwrap();
+        create_dir_all(dir.path().join("Folder")).unwrap();
+        std::fs::write(dir.path().join("Folder").join("b.txt"), "bb").unwrap();
+        assert_eq!(get_dir_size(dir.path()).unwrap(), 6);
+        assert!(get_dir_size(&dir.path().join("Nope")).is_err());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_check_free_space() {
+        let dir = make_test_dir();
+        let missing = dir.path().join("Not").join("Made").join("Yet");
+        assert!(get_available_space(&missing).unwrap() > 0);
+        check_free_space(&missing, 1, "Test").unwrap();
+        let err = check_free_space(&missing, u64::MAX, "Test").err().unwrap();
+        assert!(err.to_string().contains("Not enough free space for Test"));
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(12), "12 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
 }
@@ -1,4 +1,7 @@
-use std::{path::PathBuf, process::Stdio};
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+};
 
 use anyhow::{anyhow, Result};
 use log::warn;
@@ -17,7 +20,16 @@ pub async fn launch_game(
 ) -> Result<()> {
     let mut cmd = get_cmd(config, open_in_new_window)?;
 
-    cmd.current_dir(PathBuf::from(&config.owml_path));
+    cmd.current_dir(PathBuf::from(config.get_owml_path()));
+
+    // Make sure OWML launches the game of the selected instance
+    if let Some(game_path) = config.get_game_path() {
+        let mut owml_config = OWMLConfig::get(config)?;
+        if owml_config.game_path != game_path {
+            owml_config.game_path = game_path.to_string();
+            owml_config.save(config)?;
+        }
+    }
 
     if let Some(port) = port {
         cmd.arg("-consolePort")
@@ -62,32 +74,47 @@ pub async fn launch_game(
     Ok(())
 }
 
+/// Get the program to run executables with, see [Config::get_game_runner]
+fn get_runner(config: &Config) -> Option<&str> {
+    config
+        .get_game_runner()
+        .or(if cfg!(windows) { None } else { Some("mono") })
+}
+
+/// Make a command that runs a Windows executable with the runner of the selected instance ([Config::get_game_runner])
+pub(crate) fn get_runner_cmd(config: &Config, exe_path: &Path) -> Command {
+    match get_runner(config) {
+        Some(runner) => {
+            let mut cmd = Command::new(runner);
+            cmd.arg(exe_path);
+            cmd
+        }
+        None => Command::new(exe_path),
+    }
+}
+
 #[cfg(windows)]
 fn get_cmd(config: &Config, open_in_new_window: bool) -> Result<Command> {
-    let owml_path = PathBuf::from(&config.owml_path).join(OWML_EXE_NAME);
+    let owml_path = PathBuf::from(config.get_owml_path()).join(OWML_EXE_NAME);
     let exe_path = owml_path.to_str().unwrap();
     if open_in_new_window {
         let mut cmd = Command::new("cmd");
-        cmd.arg("/c")
-            .arg("start")
-            .arg("cmd")
-            .arg("/c")
-            .arg(exe_path);
+        cmd.arg("/c").arg("start").arg("cmd").arg("/c");
+        if let Some(runner) = get_runner(config) {
+            cmd.arg(runner);
+        }
+        cmd.arg(exe_path);
         Ok(cmd)
     } else {
-        let cmd = Command::new(exe_path);
-        Ok(cmd)
+        Ok(get_runner_cmd(config, &owml_path))
     }
 }
 
 #[cfg(unix)]
 fn get_cmd(config: &Config, _: bool) -> Result<Command> {
-    let owml_path = PathBuf::from(&config.owml_path).join(OWML_EXE_NAME);
-    let exe_path = owml_path.to_str().unwrap();
+    let owml_path = PathBuf::from(config.get_owml_path()).join(OWML_EXE_NAME);
     fix_dlls(config)?;
-    let mut cmd = Command::new("mono");
-    cmd.arg(exe_path);
-    Ok(cmd)
+    Ok(get_runner_cmd(config, &owml_path))
 }
 
 #[cfg(unix)]
@@ -100,7 +127,7 @@ fn fix_dlls(config: &Config) -> Result<()> {
     const OWML_MOD_LOADER_DLL: &[u8] =
         include_bytes!("../linux_replacement_dlls/OWML.ModLoader.dll");
 
-    let owml_dir = PathBuf::from(&config.owml_path);
+    let owml_dir = PathBuf::from(config.get_owml_path());
     let mut file = File::create(owml_dir.join("System.dll"))?;
     file.write_all(SYSTEM_DLL)?;
     let mut file = File::create(owml_dir.join("System.Core.dll"))?;
@@ -0,0 +1,865 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    config::Config,
+    constants::{HISTORY_ARCHIVES_DIR_NAME, HISTORY_ARCHIVES_LIMIT, HISTORY_FILE_NAME},
+    db::LocalDatabase,
+    download::{collect_files, install_mod_from_file},
+    patcher::Patches,
+    remove::remove_mod,
+    toggle::toggle_mod,
+};
+
+/// Something the manager did to the installed mods
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    Install,
+    Uninstall,
+    Enable,
+    Disable,
+    Update,
+    Import,
+    /// Reversing an earlier operation, see [History::undo]
+    Undo,
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// The state of a mod before or after an operation
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModState {
+    pub version: String,
+    pub enabled: bool,
+}
+
+/// A change to a single mod made by an operation
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModChange {
+    pub unique_name: String,
+    pub name: String,
+    /// The state before the operation, `None` if it wasn't installed
+    pub before: Option<ModState>,
+    /// The state after the operation, `None` if it was uninstalled
+    pub after: Option<ModState>,
+    /// The file name of an archive of the mod's files before the operation, used to undo removals and updates
+    pub archive: Option<String>,
+}
+
+impl ModChange {
+    /// Whether undoing this change needs the mod's old files back
+    fn needs_archive(&self) -> bool {
+        match (&self.before, &self.after) {
+            (Some(_), None) => true,
+            (Some(before), Some(after)) => before.version != after.version,
+            _ => false,
+        }
+    }
+}
+
+impl Display for ModChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.before, &self.after) {
+            (None, Some(after)) => write!(f, "Installed {} {}", self.name, after.version),
+            (Some(before), None) => write!(f, "Uninstalled {} {}", self.name, before.version),
+            (Some(before), Some(after)) if before.version != after.version => write!(
+                f,
+                "Changed {} {} -> {}",
+                self.name, before.version, after.version
+            ),
+            (_, Some(after)) => write!(
+                f,
+                "{} {}",
+                if after.enabled { "Enabled" } else { "Disabled" },
+                self.name
+            ),
+            (None, None) => write!(f, "Nothing happened to {}", self.name),
+        }
+    }
+}
+
+/// An operation recorded in the [History]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub id: u32,
+    /// When the operation finished, in seconds since the unix epoch
+    pub timestamp: u64,
+    pub operation: Operation,
+    /// The front-end that performed the operation (`cli`, `gui`, etc.)
+    pub front_end: String,
+    pub changes: Vec<ModChange>,
+    /// If this is an [Operation::Undo], the ID of the entry it reversed
+    #[serde(default)]
+    pub undoes: Option<u32>,
+}
+
+/// An append-only journal of every operation the manager performs on installed mods.
+/// Entries are stored one per line in `history.jsonl` in the app directory, along with archives of mods that were removed or replaced so they can be restored.
+/// Only the newest [HISTORY_ARCHIVES_LIMIT] archives are kept, older entries can't be undone once their archives are gone.
+#[derive(Clone, Debug)]
+pub struct History {
+    dir: PathBuf,
+}
+
+impl History {
+    /// Use the journal in the given directory
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// Use the journal next to the config file, normally the app directory
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(config.path.parent().unwrap_or(Path::new(".")))
+    }
+
+    fn journal_path(&self) -> PathBuf {
+        self.dir.join(HISTORY_FILE_NAME)
+    }
+
+    fn archives_path(&self) -> PathBuf {
+        self.dir.join(HISTORY_ARCHIVES_DIR_NAME)
+    }
+
+    /// Read all the entries in the journal, oldest first.
+    /// Lines that can't be read are skipped with a warning.
+    ///
+    /// ## Errors
+    ///
+    /// If the journal exists but we can't read it.
+    ///
+    pub fn entries(&self) -> Result<Vec<HistoryEntry>> {
+        let path = self.journal_path();
+        if !path.is_file() {
+            return Ok(vec![]);
+        }
+        let reader = BufReader::new(File::open(path)?);
+        let mut entries = vec![];
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(why) => warn!("Skipping unreadable history entry: {:?}", why),
+            }
+        }
+        Ok(entries)
+    }
+
+    fn append(&self, entry: &HistoryEntry) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.journal_path())?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+
+    /// Delete the journal and all archives
+    ///
+    /// ## Errors
+    ///
+    /// If we can't delete the files.
+    ///
+    pub fn clear(&self) -> Result<()> {
+        if self.journal_path().is_file() {
+            fs::remove_file(self.journal_path())?;
+        }
+        if self.archives_path().is_dir() {
+            fs::remove_dir_all(self.archives_path())?;
+        }
+        Ok(())
+    }
+
+    /// Delete the archives of all but the newest `keep` archived mod changes.
+    /// Only archives that are recorded in the journal are deleted, so archives of operations still being recorded are safe.
+    ///
+    /// ## Returns
+    ///
+    /// How many archives were deleted.
+    ///
+    /// ## Errors
+    ///
+    /// If we can't read the journal or delete an archive.
+    ///
+    pub fn prune_archives(&self, keep: usize) -> Result<usize> {
+        let archives_path = self.archives_path();
+        let archives: Vec<String> = self
+            .entries()?
+            .into_iter()
+            .flat_map(|e| e.changes.into_iter().filter_map(|c| c.archive))
+            .filter(|a| archives_path.join(a).is_file())
+            .collect();
+        let excess = archives.len().saturating_sub(keep);
+        for archive in archives.iter().take(excess) {
+            debug!("Deleting old history archive {}", archive);
+            fs::remove_file(archives_path.join(archive))?;
+        }
+        Ok(excess)
+    }
+
+    /// Start recording an operation, this takes a snapshot of the installed mods to compare against in [PendingEntry::finish]
+    pub fn start(
+        &self,
+        operation: Operation,
+        front_end: &str,
+        local_db: &LocalDatabase,
+    ) -> PendingEntry {
+        PendingEntry {
+            history: self.clone(),
+            operation,
+            front_end: front_end.to_string(),
+            before: snapshot(local_db),
+            archives: HashMap::new(),
+            undoes: None,
+        }
+    }
+
+    /// Check if an entry can be undone.
+    /// Undos can't be undone themselves, an entry can only be undone once, and an entry can't be undone if a later one changed the same mods (unless that one was undone too).
+    ///
+    /// ## Errors
+    ///
+    /// Why the entry can't be undone.
+    ///
+    pub fn check_undoable(&self, entry: &HistoryEntry, entries: &[HistoryEntry]) -> Result<()> {
+        self.check_reversible(entry, entries)?;
+        let later = entries.iter().filter(|e| {
+            e.id > entry.id
+                && e.operation != Operation::Undo
+                && !entries.iter().any(|u| u.undoes == Some(e.id))
+        });
+        for later in later {
+            let changed = entry
+                .changes
+                .iter()
+                .find(|c| later.changes.iter().any(|l| l.unique_name == c.unique_name));
+            if let Some(change) = changed {
+                return Err(anyhow!(
+                    "#{} can't be undone, #{} changed {} after it",
+                    entry.id,
+                    later.id,
+                    change.name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // The checks that can't be skipped with `force`
+    fn check_reversible(&self, entry: &HistoryEntry, entries: &[HistoryEntry]) -> Result<()> {
+        if entry.operation == Operation::Undo {
+            return Err(anyhow!("#{} is an undo, it can't be undone", entry.id));
+        }
+        if entries.iter().any(|e| e.undoes == Some(entry.id)) {
+            return Err(anyhow!("#{} was already undone", entry.id));
+        }
+        for change in entry.changes.iter().filter(|c| c.needs_archive()) {
+            let has_archive = change
+                .archive
+                .as_ref()
+                .is_some_and(|a| self.archives_path().join(a).is_file());
+            if !has_archive {
+                return Err(anyhow!(
+                    "#{} can't be undone, the old files of {} weren't kept",
+                    entry.id,
+                    change.name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the entry [History::undo] would reverse if no ID is given, the newest entry that can be undone
+    ///
+    /// ## Errors
+    ///
+    /// If we can't read the journal.
+    ///
+    pub fn last_undoable(&self) -> Result<Option<HistoryEntry>> {
+        let entries = self.entries()?;
+        Ok(entries
+            .iter()
+            .rev()
+            .find(|e| self.check_undoable(e, &entries).is_ok())
+            .cloned())
+    }
+
+    /// Reverse an operation, mods it installed are removed, mods it removed or replaced are restored from archives, and enabled states are put back.
+    /// Mods that are removed have their pre-patches reversed first (see [Patches::unpatch]).
+    /// The undo is recorded as its own entry.
+    ///
+    /// Pass `force` to undo the entry even if its mods were changed since, by a later entry or outside the manager.
+    /// Those changes are lost.
+    ///
+    /// ## Returns
+    ///
+    /// The entry recording the undo.
+    ///
+    /// ## Errors
+    ///
+    /// - If there's no entry with the given ID, or no entry that can be undone when `id` is `None`.
+    /// - If the entry can't be undone (see [History::check_undoable]).
+    /// - If one of the entry's mods isn't in the state the entry left it in, and `force` isn't set.
+    /// - If we can't remove, restore, or toggle one of the mods.
+    ///
+    pub async fn undo(
+        &self,
+        id: Option<u32>,
+        config: &Config,
+        local_db: &LocalDatabase,
+        front_end: &str,
+        force: bool,
+    ) -> Result<HistoryEntry> {
+        let entries = self.entries()?;
+        let entry = match id {
+            Some(id) => entries
+                .iter()
+                .find(|e| e.id == id)
+                .ok_or_else(|| anyhow!("No history entry with ID {}", id))?,
+            None => entries
+                .iter()
+                .rev()
+                .find(|e| self.check_undoable(e, &entries).is_ok())
+                .ok_or_else(|| anyhow!("Nothing to undo"))?,
+        };
+        if force {
+            self.check_reversible(entry, &entries)?;
+        } else {
+            self.check_undoable(entry, &entries)?;
+            for change in entry.changes.iter() {
+                let current = local_db.get_mod(&change.unique_name).map(|m| ModState {
+                    version: m.manifest.version.clone(),
+                    enabled: m.enabled,
+                });
+                if current != change.after {
+                    return Err(anyhow!(
+                        "#{} can't be undone, {} was changed since",
+                        entry.id,
+                        change.name
+                    ));
+                }
+            }
+        }
+
+        let mut pending = self.start(Operation::Undo, front_end, local_db);
+        pending.undoes = Some(entry.id);
+
+        for change in entry.changes.iter().rev() {
+            if change.needs_archive() {
+                // Unwrap is safe, check_undoable made sure the archive is there
+                let archive = self.archives_path().join(change.archive.as_ref().unwrap());
+                debug!(
+                    "Restoring {} from {}",
+                    change.unique_name,
+                    archive.display()
+                );
+                let current_db = LocalDatabase::fetch(config.get_owml_path())?;
+                install_mod_from_file(&archive, config, &current_db)?;
+            } else if change.before.is_none() {
+                let current_db = LocalDatabase::fetch(config.get_owml_path())?;
+                if let Some(local_mod) = current_db.get_mod(&change.unique_name) {
+                    if let Err(why) = Patches::from_config(config)
+                        .unpatch(config, &change.unique_name, Some(&current_db))
+                        .await
+                    {
+                        warn!("Couldn't unpatch {}: {:?}", change.unique_name, why);
+                    }
+                    debug!("Removing {}", change.unique_name);
+                    remove_mod(local_mod, &current_db, false)?;
+                }
+            }
+        }
+
+        let current_db = LocalDatabase::fetch(config.get_owml_path())?;
+        for change in entry.changes.iter() {
+            if let Some(before) = &change.before {
+                let enabled = current_db
+                    .get_mod(&change.unique_name)
+                    .map(|m| m.enabled)
+                    .unwrap_or(before.enabled);
+                if enabled != before.enabled {
+                    toggle_mod(&change.unique_name, &current_db, before.enabled, false)?;
+                }
+            }
+        }
+
+        let current_db = LocalDatabase::fetch(config.get_owml_path())?;
+        pending.write_entry(&current_db)
+    }
+}
+
+fn snapshot(local_db: &LocalDatabase) -> HashMap<String, (String, ModState, PathBuf)> {
+    local_db
+        .valid()
+        .map(|m| {
+            (
+                m.manifest.unique_name.clone(),
+                (
+                    m.manifest.name.clone(),
+                    ModState {
+                        version: m.manifest.version.clone(),
+                        enabled: m.enabled,
+                    },
+                    PathBuf::from(&m.mod_path),
+                ),
+            )
+        })
+        .collect()
+}
+
+/// An operation that's being recorded, created with [History::start]
+pub struct PendingEntry {
+    history: History,
+    operation: Operation,
+    front_end: String,
+    before: HashMap<String, (String, ModState, PathBuf)>,
+    archives: HashMap<String, String>,
+    undoes: Option<u32>,
+}
+
+impl PendingEntry {
+    /// Keep a copy of an installed mod's files before they're removed or replaced so the operation can be undone.
+    /// Archives of mods that end up unchanged are deleted in [PendingEntry::finish].
+    ///
+    /// ## Errors
+    ///
+    /// If we can't write the archive, mods that weren't installed when recording started are ignored.
+    ///
+    pub fn preserve(&mut self, unique_name: &str) -> Result<()> {
+        let Some((_, state, mod_path)) = self.before.get(unique_name) else {
+            return Ok(());
+        };
+        if !mod_path.is_dir() || self.archives.contains_key(unique_name) {
+            return Ok(());
+        }
+        let archives_path = self.history.archives_path();
+        fs::create_dir_all(&archives_path)?;
+        let archive_name = format!(
+            "{}-{}-{}.zip",
+            unique_name,
+            state.version,
+            uuid::Uuid::new_v4().simple()
+        );
+        debug!("Archiving {} to {}", unique_name, archive_name);
+        write_archive(mod_path, &archives_path.join(&archive_name))?;
+        self.archives.insert(unique_name.to_string(), archive_name);
+        Ok(())
+    }
+
+    fn changes(&mut self, local_db: &LocalDatabase) -> Vec<ModChange> {
+        let after = snapshot(local_db);
+        let mut names: Vec<&String> = self.before.keys().chain(after.keys()).collect();
+        names.sort();
+        names.dedup();
+        let mut changes = vec![];
+        for unique_name in names {
+            let before = self.before.get(unique_name);
+            let after = after.get(unique_name);
+            if before.map(|b| &b.1) == after.map(|a| &a.1) {
+                continue;
+            }
+            // Unwrap is safe, a mod has to be in one of the snapshots to be here
+            let name = before.or(after).map(|m| m.0.clone()).unwrap();
+            changes.push(ModChange {
+                unique_name: unique_name.clone(),
+                name,
+                before: before.map(|b| b.1.clone()),
+                after: after.map(|a| a.1.clone()),
+                archive: self.archives.remove(unique_name),
+            });
+        }
+        changes
+    }
+
+    fn cleanup_archives(&self) {
+        let archives_path = self.history.archives_path();
+        for archive in self.archives.values() {
+            fs::remove_file(archives_path.join(archive)).ok();
+        }
+    }
+
+    /// Compare the installed mods to the snapshot taken when recording started and add an entry to the journal.
+    ///
+    /// ## Returns
+    ///
+    /// The new entry, or `None` if nothing changed, in which case nothing is recorded.
+    ///
+    /// ## Errors
+    ///
+    /// If we can't write to the journal.
+    ///
+    pub fn finish(mut self, local_db: &LocalDatabase) -> Result<Option<HistoryEntry>> {
+        let changes = self.changes(local_db);
+        self.cleanup_archives();
+        if changes.is_empty() {
+            return Ok(None);
+        }
+        self.write(changes).map(Some)
+    }
+
+    fn write_entry(mut self, local_db: &LocalDatabase) -> Result<HistoryEntry> {
+        let changes = self.changes(local_db);
+        self.cleanup_archives();
+        self.write(changes)
+    }
+
+    fn write(self, changes: Vec<ModChange>) -> Result<HistoryEntry> {
+        let id = self
+            .history
+            .entries()?
+            .iter()
+            .map(|e| e.id)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let entry = HistoryEntry {
+            id,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            operation: self.operation,
+            front_end: self.front_end,
+            changes,
+            undoes: self.undoes,
+        };
+        self.history.append(&entry)?;
+        if let Err(why) = self.history.prune_archives(HISTORY_ARCHIVES_LIMIT) {
+            warn!("Couldn't delete old history archives: {:?}", why);
+        }
+        Ok(entry)
+    }
+}
+
+fn write_archive(mod_path: &Path, output_path: &Path) -> Result<()> {
+    let mut files = vec![];
+    collect_files(mod_path, mod_path, &mut files)?;
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(output_path)?));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (rel_path, _) in files {
+        let name: Vec<String> = rel_path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        zip.start_file(name.join("/"), options)?;
+        let mut file = File::open(mod_path.join(&rel_path))?;
+        std::io::copy(&mut file, &mut zip)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+/// Format how long ago a timestamp (in seconds since the unix epoch) was, relative to `now`
+///
+/// ## Returns
+///
+/// Something like "just now", "5 minutes ago", or "2 days ago"
+///
+pub fn format_time_ago(timestamp: u64, now: u64) -> String {
+    let secs = now.saturating_sub(timestamp);
+    let (amount, unit) = match secs {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (secs / 60, "minute"),
+        3600..=86399 => (secs / 3600, "hour"),
+        _ => (secs / 86400, "day"),
+    };
+    format!("{amount} {unit}{} ago", if amount == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_utils::{get_test_file, make_test_dir};
+
+    fn setup(dir: &Path) -> (Config, History) {
+        let mut config = Config::default(Some(dir.join("settings.json"))).unwrap();
+        config.owml_path = dir.join("OWML").to_str().unwrap().to_string();
+        let history = History::from_config(&config);
+        (config, history)
+    }
+
+    fn fetch(config: &Config) -> LocalDatabase {
+        LocalDatabase::fetch(&config.owml_path).unwrap()
+    }
+
+    #[test]
+    fn test_record_install() {
+        let dir = make_test_dir();
+        let (config, history) = setup(dir.path());
+        let pending = history.start(Operation::Install, "test", &fetch(&config));
+        install_mod_from_file(
+            &get_test_file("Bwc9876.TimeSaver.zip"),
+            &config,
+            &fetch(&config),
+        )
+        .unwrap();
+        let entry = pending.finish(&fetch(&config)).unwrap().unwrap();
+        assert_eq!(entry.id, 1);
+        assert_eq!(entry.front_end, "test");
+        assert_eq!(entry.changes.len(), 1);
+        assert_eq!(entry.changes[0].unique_name, "Bwc9876.TimeSaver");
+        assert!(entry.changes[0].before.is_none());
+        assert_eq!(entry.changes[0].to_string(), "Installed TimeSaver 1.1.1");
+        assert_eq!(history.entries().unwrap(), vec![entry]);
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_record_nothing() {
+        let dir = make_test_dir();
+        let (config, history) = setup(dir.path());
+        let pending = history.start(Operation::Enable, "test", &fetch(&config));
+        assert!(pending.finish(&fetch(&config)).unwrap().is_none());
+        assert!(history.entries().unwrap().is_empty());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_undo_install() {
+        let dir = make_test_dir();
+        let (config, history) = setup(dir.path());
+        let pending = history.start(Operation::Install, "test", &fetch(&config));
+        install_mod_from_file(
+            &get_test_file("Bwc9876.TimeSaver.zip"),
+            &config,
+            &fetch(&config),
+        )
+        .unwrap();
+        pending.finish(&fetch(&config)).unwrap();
+        let undo =
+            tokio_test::block_on(history.undo(None, &config, &fetch(&config), "test", false))
+                .unwrap();
+        assert_eq!(undo.undoes, Some(1));
+        assert_eq!(undo.operation, Operation::Undo);
+        assert!(fetch(&config).get_mod("Bwc9876.TimeSaver").is_none());
+        assert!(history.last_undoable().unwrap().is_none());
+        assert!(tokio_test::block_on(history.undo(
+            Some(1),
+            &config,
+            &fetch(&config),
+            "test",
+            false
+        ))
+        .is_err());
+        assert!(tokio_test::block_on(history.undo(
+            Some(2),
+            &config,
+            &fetch(&config),
+            "test",
+            false
+        ))
+        .is_err());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_undo_uninstall() {
+        let dir = make_test_dir();
+        let (config, history) = setup(dir.path());
+        install_mod_from_file(
+            &get_test_file("Bwc9876.TimeSaver.zip"),
+            &config,
+            &fetch(&config),
+        )
+        .unwrap();
+        let db = fetch(&config);
+        toggle_mod("Bwc9876.TimeSaver", &db, false, false).unwrap();
+        let db = fetch(&config);
+        let mut pending = history.start(Operation::Uninstall, "test", &db);
+        pending.preserve("Bwc9876.TimeSaver").unwrap();
+        remove_mod(db.get_mod("Bwc9876.TimeSaver").unwrap(), &db, false).unwrap();
+        let entry = pending.finish(&fetch(&config)).unwrap().unwrap();
+        assert_eq!(entry.changes[0].to_string(), "Uninstalled TimeSaver 1.1.1");
+        assert!(entry.changes[0].archive.is_some());
+
+        tokio_test::block_on(history.undo(None, &config, &fetch(&config), "test", false)).unwrap();
+        let restored = fetch(&config);
+        let restored = restored.get_mod("Bwc9876.TimeSaver").unwrap();
+        assert!(!restored.enabled);
+        assert_eq!(restored.manifest.version, "1.1.1");
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_undo_toggle() {
+        let dir = make_test_dir();
+        let (config, history) = setup(dir.path());
+        install_mod_from_file(
+            &get_test_file("Bwc9876.TimeSaver.zip"),
+            &config,
+            &fetch(&config),
+        )
+        .unwrap();
+        let db = fetch(&config);
+        let pending = history.start(Operation::Disable, "test", &db);
+        toggle_mod("Bwc9876.TimeSaver", &db, false, false).unwrap();
+        let entry = pending.finish(&fetch(&config)).unwrap().unwrap();
+        assert_eq!(entry.changes[0].to_string(), "Disabled TimeSaver");
+        tokio_test::block_on(history.undo(Some(entry.id), &config, &fetch(&config), "test", false))
+            .unwrap();
+        assert!(fetch(&config).get_mod("Bwc9876.TimeSaver").unwrap().enabled);
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_undo_changed_since() {
+        let dir = make_test_dir();
+        let (config, history) = setup(dir.path());
+        let pending = history.start(Operation::Install, "test", &fetch(&config));
+        install_mod_from_file(
+            &get_test_file("Bwc9876.TimeSaver.zip"),
+            &config,
+            &fetch(&config),
+        )
+        .unwrap();
+        pending.finish(&fetch(&config)).unwrap();
+        let db = fetch(&config);
+        let pending = history.start(Operation::Disable, "test", &db);
+        toggle_mod("Bwc9876.TimeSaver", &db, false, false).unwrap();
+        pending.finish(&fetch(&config)).unwrap();
+
+        let err =
+            tokio_test::block_on(history.undo(Some(1), &config, &fetch(&config), "test", false))
+                .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "#1 can't be undone, #2 changed TimeSaver after it"
+        );
+        assert_eq!(history.last_undoable().unwrap().unwrap().id, 2);
+
+        // Undoing the later entry makes the earlier one undoable again
+        tokio_test::block_on(history.undo(Some(2), &config, &fetch(&config), "test", false))
+            .unwrap();
+        assert!(history
+            .check_undoable(&history.entries().unwrap()[0], &history.entries().unwrap())
+            .is_ok());
+
+        // Changes made outside the manager are caught too
+        toggle_mod("Bwc9876.TimeSaver", &fetch(&config), false, false).unwrap();
+        let err =
+            tokio_test::block_on(history.undo(Some(1), &config, &fetch(&config), "test", false))
+                .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "#1 can't be undone, TimeSaver was changed since"
+        );
+        tokio_test::block_on(history.undo(Some(1), &config, &fetch(&config), "test", true))
+            .unwrap();
+        assert!(fetch(&config).get_mod("Bwc9876.TimeSaver").is_none());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_undo_without_archive() {
+        let dir = make_test_dir();
+        let (config, history) = setup(dir.path());
+        install_mod_from_file(
+            &get_test_file("Bwc9876.TimeSaver.zip"),
+            &config,
+            &fetch(&config),
+        )
+        .unwrap();
+        let db = fetch(&config);
+        let pending = history.start(Operation::Uninstall, "test", &db);
+        remove_mod(db.get_mod("Bwc9876.TimeSaver").unwrap(), &db, false).unwrap();
+        pending.finish(&fetch(&config)).unwrap();
+        let err = tokio_test::block_on(history.undo(None, &config, &fetch(&config), "test", false))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Nothing to undo");
+        let err =
+            tokio_test::block_on(history.undo(Some(1), &config, &fetch(&config), "test", false))
+                .unwrap_err();
+        assert!(err.to_string().contains("weren't kept"));
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_unused_archives_cleaned_up() {
+        let dir = make_test_dir();
+        let (config, history) = setup(dir.path());
+        install_mod_from_file(
+            &get_test_file("Bwc9876.TimeSaver.zip"),
+            &config,
+            &fetch(&config),
+        )
+        .unwrap();
+        let mut pending = history.start(Operation::Update, "test", &fetch(&config));
+        pending.preserve("Bwc9876.TimeSaver").unwrap();
+        assert!(pending.finish(&fetch(&config)).unwrap().is_none());
+        let archives = history.archives_path().read_dir().unwrap().count();
+        assert_eq!(archives, 0);
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_prune_archives() {
+        let dir = make_test_dir();
+        let (config, history) = setup(dir.path());
+        let mut archives = vec![];
+        for _ in 0..3 {
+            install_mod_from_file(
+                &get_test_file("Bwc9876.TimeSaver.zip"),
+                &config,
+                &fetch(&config),
+            )
+            .unwrap();
+            let db = fetch(&config);
+            let mut pending = history.start(Operation::Uninstall, "test", &db);
+            pending.preserve("Bwc9876.TimeSaver").unwrap();
+            remove_mod(db.get_mod("Bwc9876.TimeSaver").unwrap(), &db, false).unwrap();
+            let entry = pending.finish(&fetch(&config)).unwrap().unwrap();
+            archives.push(entry.changes[0].archive.clone().unwrap());
+        }
+        assert_eq!(history.prune_archives(1).unwrap(), 2);
+        assert!(!history.archives_path().join(&archives[0]).exists());
+        assert!(!history.archives_path().join(&archives[1]).exists());
+        assert!(history.archives_path().join(&archives[2]).is_file());
+        assert_eq!(history.prune_archives(1).unwrap(), 0);
+        assert!(history.last_undoable().unwrap().is_some());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_skip_bad_lines() {
+        let dir = make_test_dir();
+        let (_, history) = setup(dir.path());
+        fs::write(history.journal_path(), "not json\n\n").unwrap();
+        assert!(history.entries().unwrap().is_empty());
+        history.clear().unwrap();
+        assert!(!history.journal_path().exists());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_format_time_ago() {
+        assert_eq!(format_time_ago(100, 130), "just now");
+        assert_eq!(format_time_ago(0, 60), "1 minute ago");
+        assert_eq!(format_time_ago(0, 7200), "2 hours ago");
+        assert_eq!(format_time_ago(0, 3 * 86400), "3 days ago");
+        assert_eq!(format_time_ago(100, 50), "just now");
+    }
+}
@@ -75,8 +75,8 @@ mod tests {
     use std::{fs::File, io::Write};
 
     use crate::{
-        download::install_mod_from_zip,
-        test_utils::{get_test_file, make_test_dir},
+        download::install_mod_from_file,
+        test_utils::{get_test_file, make_test_dir, MockServer},
     };
 
     use super::*;
@@ -92,10 +92,10 @@ mod tests {
 
     #[test]
     fn test_import_mods() {
+        let server = MockServer::start();
         tokio_test::block_on(async {
             let dir = make_test_dir();
-            let mut config = Config::default(Some(dir.path().join("settings.json"))).unwrap();
-            config.owml_path = dir.path().to_str().unwrap().to_string();
+            let config = server.config(dir.path());
             let remote_db = RemoteDatabase::fetch(&config.database_url).await.unwrap();
             let list_path = dir.path().join("list.json");
             let mut file = File::create(&list_path).unwrap();
@@ -112,14 +112,14 @@ mod tests {
 
     #[test]
     fn test_import_mods_with_disabled() {
+        let server = MockServer::start();
         tokio_test::block_on(async {
             let dir = make_test_dir();
             let zip_path = get_test_file("Bwc9876.TimeSaver.zip");
-            let mut config = Config::default(Some(dir.path().join("settings.json"))).unwrap();
-            config.owml_path = dir.path().to_str().unwrap().to_string();
+            let config = server.config(dir.path());
             let remote_db = RemoteDatabase::fetch(&config.database_url).await.unwrap();
             let local_db = LocalDatabase::default();
-            install_mod_from_zip(&zip_path, &config, &local_db).unwrap();
+            install_mod_from_file(&zip_path, &config, &local_db).unwrap();
             let local_db = LocalDatabase::fetch(&config.owml_path).unwrap();
             toggle_mod("Bwc9876.TimeSaver", &local_db, false, false).unwrap();
             let list_path = dir.path().join("list.json");
@@ -144,14 +144,14 @@ mod tests {
 
     #[test]
     fn test_import_mods_disable_missing() {
+        let server = MockServer::start();
         tokio_test::block_on(async {
             let dir = make_test_dir();
             let zip_path = get_test_file("Bwc9876.TimeSaver.zip");
-            let mut config = Config::default(Some(dir.path().join("settings.json"))).unwrap();
-            config.owml_path = dir.path().to_str().unwrap().to_string();
+            let config = server.config(dir.path());
             let remote_db = RemoteDatabase::fetch(&config.database_url).await.unwrap();
             let local_db = LocalDatabase::default();
-            install_mod_from_zip(&zip_path, &config, &local_db).unwrap();
+            install_mod_from_file(&zip_path, &config, &local_db).unwrap();
             let list_path = dir.path().join("list.json");
             let mut file = File::create(&list_path).unwrap();
             write!(file, "[]").unwrap();
@@ -9,6 +9,9 @@ pub mod alerts;
 /// Send analytics events.
 pub mod analytics;
 
+/// Get release notes for mod updates.
+pub mod changelog;
+
 /// Work with the configuration of the app.
 pub mod config;
 
@@ -18,6 +21,9 @@ pub mod constants;
 /// Work with both remote and local databases.
 pub mod db;
 
+/// Tools for mod authors, such as validating and packing mods.
+pub mod dev;
+
 /// Download and install mods and OWML.
 pub mod download;
 
@@ -27,9 +33,15 @@ pub mod file;
 /// Run the game and setup prerequisites on Linux.
 pub mod game;
 
+/// Record operations on mods and undo them.
+pub mod history;
+
 /// Import and export mods from JSON arrays.
 pub mod io;
 
+/// Import settings from the old mod manager.
+pub mod migrate;
+
 /// Work with local and remote mods.
 pub mod mods;
 
@@ -39,12 +51,18 @@ pub mod owml;
 /// Open shortcuts and mod readmes.
 pub mod open;
 
+/// Run mod pre-patchers and reverse their changes.
+pub mod patcher;
+
 /// Types for consuming progress payloads.
 pub mod progress;
 
 /// Uninstall mods
 pub mod remove;
 
+/// Search and filter mods.
+pub mod search;
+
 /// Listen to logs from the game.
 pub mod socket;
 
@@ -57,21 +75,14 @@ pub mod updates;
 /// Validate the local database for common issues
 pub mod validate;
 
-mod search;
-
-#[cfg(test)]
-mod test_utils {
-    use std::path::{Path, PathBuf};
+/// Parse and compare mod versions.
+pub mod version;
 
-    use tempfile::TempDir;
+/// Load and save versioned settings files, with migrations, backups and recovery.
+pub mod versioned;
 
-    pub fn make_test_dir() -> TempDir {
-        TempDir::new().unwrap()
-    }
+/// Watch the mods folder for changes.
+pub mod watcher;
 
-    pub fn get_test_file(path: &str) -> PathBuf {
-        Path::new(env!("CARGO_MANIFEST_DIR"))
-            .join("test_files")
-            .join(path)
-    }
-}
+#[cfg(test)]
+mod test_utils;
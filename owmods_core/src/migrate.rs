@@ -0,0 +1,338 @@
+use std::{
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use log::debug;
+use serde::Deserialize;
+
+use crate::{
+    config::Config,
+    constants::DEFAULT_DB_URL,
+    db::LocalDatabase,
+    file::{fix_json, get_default_owml_path},
+    history::{History, Operation},
+    toggle::toggle_mod,
+};
+
+/// The database the old manager used by default, it isn't updated anymore so it's never migrated
+const OLD_DEFAULT_DB_URL: &str =
+    "https://raw.githubusercontent.com/Raicuparta/ow-mod-db/master/database.json";
+
+/// The settings of the old manager, only the ones we migrate
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct OldSettings {
+    owml_path: Option<String>,
+    #[serde(alias = "modDatabaseUrl")]
+    database_url: Option<String>,
+    #[serde(default, alias = "viewedModWarnings")]
+    viewed_warnings: Vec<String>,
+}
+
+/// What [migrate_old_manager] imported
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Descriptions of everything that was imported
+    pub migrated: Vec<String>,
+    /// Descriptions of everything that couldn't be imported, and why
+    pub skipped: Vec<String>,
+}
+
+impl MigrationReport {
+    fn migrated(&mut self, msg: String) {
+        debug!("Migrated {}", msg);
+        self.migrated.push(msg);
+    }
+
+    fn skipped(&mut self, msg: String) {
+        debug!("Skipped {}", msg);
+        self.skipped.push(msg);
+    }
+}
+
+/// Get the folder the old manager keeps its settings and OWML install in
+///
+/// ## Errors
+///
+/// If we can't get the user's app data dir (or equivalent on Linux)
+///
+pub fn get_old_manager_path() -> Result<PathBuf> {
+    let owml_path = get_default_owml_path()?;
+    // Unwrap is safe, the default OWML path is always inside the old manager's folder
+    Ok(owml_path.parent().unwrap().to_path_buf())
+}
+
+/// Import the settings of the old manager into `config`.
+/// The old manager's files are only read, and settings that were already migrated are skipped, so this can be run as many times as needed.
+///
+/// - The database URL is imported if it isn't the old manager's default and `config` still uses the default database
+/// - The OWML path is imported if it's a valid OWML install and `config` doesn't have one yet
+/// - Viewed mod warnings are added to [Config::viewed_alerts]
+/// - If the old manager's OWML install isn't the one `config` uses, mods installed in both get the enabled state they have in the old install.
+///   This is only done once for each OWML install (see [Config::migrated_owml_paths]) and is recorded in the history as an [Operation::Import] from `front_end`
+///
+/// `config` isn't saved, call [Config::save] after this.
+///
+/// ## Returns
+///
+/// A report of what was migrated and what couldn't be.
+///
+/// ## Errors
+///
+/// - If `old_path` doesn't have the old manager's settings in it, or we can't read them
+/// - If we can't change the enabled state of a mod
+///
+pub fn migrate_old_manager(
+    config: &mut Config,
+    old_path: &Path,
+    front_end: &str,
+) -> Result<MigrationReport> {
+    let settings_path = old_path.join("settings.json");
+    if !settings_path.is_file() {
+        return Err(anyhow!(
+            "Couldn't find the old manager's settings in {}",
+            old_path.display()
+        ));
+    }
+    let settings: OldSettings =
+        serde_json::from_str(&fix_json(&read_to_string(&settings_path)?))
+            .map_err(|why| anyhow!("Couldn't read the old manager's settings: {why}"))?;
+    debug!("Migrating {:?} from {}", settings, old_path.display());
+
+    let mut report = MigrationReport::default();
+
+    match settings.database_url.filter(|u| !u.trim().is_empty()) {
+        Some(url) if url == config.database_url => {}
+        Some(url) if url == OLD_DEFAULT_DB_URL => {
+            report.skipped(format!(
+                "Database URL {url}, it's the old manager's default database which isn't updated anymore"
+            ));
+        }
+        Some(url) if config.database_url != DEFAULT_DB_URL => {
+            report.skipped(format!(
+                "Database URL {url}, a different database ({}) is already set",
+                config.database_url
+            ));
+        }
+        Some(url) => {
+            report.migrated(format!("Database URL {url}"));
+            config.database_url = url;
+        }
+        None => {}
+    }
+
+    let old_owml_path = settings
+        .owml_path
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| old_path.join("OWML").to_str().unwrap().to_string());
+    if Path::new(&old_owml_path) != Path::new(config.get_owml_path()) {
+        let mut old_config = config.clone();
+        old_config.set_owml_path(&old_owml_path);
+        if !old_config.check_owml() {
+            report.skipped(format!(
+                "OWML path {old_owml_path}, there isn't a valid OWML install there"
+            ));
+        } else if config.check_owml() {
+            report.skipped(format!(
+                "OWML path {old_owml_path}, OWML is already set up in {}",
+                config.get_owml_path()
+            ));
+            let owml_path = config.get_owml_path().to_string();
+            if !config.migrated_owml_paths.contains(&owml_path) {
+                migrate_enabled_states(config, &old_owml_path, front_end, &mut report)?;
+                config.migrated_owml_paths.push(owml_path);
+            }
+        } else {
+            report.migrated(format!("OWML path {old_owml_path}"));
+            config.set_owml_path(&old_owml_path);
+        }
+    }
+
+    let new_warnings = settings
+        .viewed_warnings
+        .into_iter()
+        .filter(|w| !config.viewed_alerts.contains(w))
+        .collect::<Vec<_>>();
+    if !new_warnings.is_empty() {
+        report.migrated(format!("Viewed warnings for {}", new_warnings.join(", ")));
+        for warning in new_warnings {
+            config.set_warning_shown(&warning);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Give mods installed in both OWML installs the enabled state they have in the old one
+fn migrate_enabled_states(
+    config: &Config,
+    old_owml_path: &str,
+    front_end: &str,
+    report: &mut MigrationReport,
+) -> Result<()> {
+    let old_db = LocalDatabase::fetch(old_owml_path)?;
+    let db = LocalDatabase::fetch(config.get_owml_path())?;
+    let record = History::from_config(config).start(Operation::Import, front_end, &db);
+    let mut missing = vec![];
+    for old_mod in old_db.valid() {
+        let unique_name = &old_mod.manifest.unique_name;
+        match db.get_mod(unique_name) {
+            Some(local_mod) if local_mod.enabled == old_mod.enabled => {}
+            Some(local_mod) => {
+                toggle_mod(unique_name, &db, old_mod.enabled, false)?;
+                report.migrated(format!(
+                    "{} is now {}",
+                    local_mod.manifest.name,
+                    if old_mod.enabled {
+                        "enabled"
+                    } else {
+                        "disabled"
+                    }
+                ));
+            }
+            None => missing.push(old_mod.manifest.name.as_str()),
+        }
+    }
+    if !missing.is_empty() {
+        report.skipped(format!(
+            "Enabled state of {}, they aren't installed",
+            missing.join(", ")
+        ));
+    }
+    record.finish(&LocalDatabase::fetch(config.get_owml_path())?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+
+    use super::*;
+    use crate::{
+        constants::{OWML_DEFAULT_CONFIG_NAME, OWML_EXE_NAME, OWML_MANIFEST_NAME},
+        download::install_mod_from_file,
+        test_utils::{get_test_file, make_test_dir},
+    };
+
+    fn make_owml(path: &Path) {
+        fs::create_dir_all(path).unwrap();
+        fs::copy(
+            get_test_file("OWML.Manifest.json"),
+            path.join(OWML_MANIFEST_NAME),
+        )
+        .unwrap();
+        fs::copy(
+            get_test_file("OWML.Config.json"),
+            path.join(OWML_DEFAULT_CONFIG_NAME),
+        )
+        .unwrap();
+        fs::write(path.join(OWML_EXE_NAME), "").unwrap();
+    }
+
+    fn setup(dir: &Path, settings: &str) -> (Config, PathBuf) {
+        let mut config = Config::default(Some(dir.join("settings.json"))).unwrap();
+        config.owml_path = dir.join("OWML").to_str().unwrap().to_string();
+        let old_path = dir.join("OuterWildsModManager");
+        fs::create_dir_all(&old_path).unwrap();
+        fs::write(
+            old_path.join("settings.json"),
+            settings.replace("$old$", &old_path.to_str().unwrap().replace('\\', "/")),
+        )
+        .unwrap();
+        (config, old_path)
+    }
+
+    #[test]
+    fn test_migrate() {
+        let dir = make_test_dir();
+        let (mut config, old_path) = setup(
+            dir.path(),
+            r#"{
+                "owmlPath": "$old$/OWML",
+                "modDatabaseUrl": "https://example.com/database.json",
+                "viewedWarnings": ["Bwc9876.TimeSaver"]
+            }"#,
+        );
+        make_owml(&old_path.join("OWML"));
+        let report = migrate_old_manager(&mut config, &old_path, "test").unwrap();
+        assert_eq!(report.migrated.len(), 3);
+        assert!(report.skipped.is_empty());
+        assert_eq!(config.database_url, "https://example.com/database.json");
+        assert_eq!(
+            Path::new(config.get_owml_path()),
+            old_path.join("OWML").as_path()
+        );
+        assert_eq!(config.viewed_alerts, vec!["Bwc9876.TimeSaver"]);
+
+        // Running again doesn't change anything
+        let report = migrate_old_manager(&mut config, &old_path, "test").unwrap();
+        assert_eq!(report, MigrationReport::default());
+        assert_eq!(config.viewed_alerts.len(), 1);
+        assert!(old_path.join("settings.json").is_file());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_migrate_skipped() {
+        let dir = make_test_dir();
+        let (mut config, old_path) = setup(
+            dir.path(),
+            r#"{
+                "owmlPath": "$old$/Nothing",
+                "modDatabaseUrl": "https://raw.githubusercontent.com/Raicuparta/ow-mod-db/master/database.json"
+            }"#,
+        );
+        let report = migrate_old_manager(&mut config, &old_path, "test").unwrap();
+        assert!(report.migrated.is_empty());
+        assert_eq!(report.skipped.len(), 2);
+        assert_eq!(config.database_url, DEFAULT_DB_URL);
+        assert_eq!(
+            Path::new(config.get_owml_path()),
+            dir.path().join("OWML").as_path()
+        );
+        assert!(migrate_old_manager(&mut config, &dir.path().join("Nothing"), "test").is_err());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_migrate_enabled_states() {
+        let dir = make_test_dir();
+        let (mut config, old_path) = setup(dir.path(), r#"{ "owmlPath": "$old$/OWML" }"#);
+        make_owml(&old_path.join("OWML"));
+        make_owml(&dir.path().join("OWML"));
+        let mut old_config = config.clone();
+        old_config.owml_path = old_path.join("OWML").to_str().unwrap().to_string();
+        let db = LocalDatabase::default();
+        install_mod_from_file(&get_test_file("Bwc9876.TimeSaver.zip"), &config, &db).unwrap();
+        install_mod_from_file(&get_test_file("Bwc9876.TimeSaver.zip"), &old_config, &db).unwrap();
+        install_mod_from_file(&get_test_file("Bwc9876.SaveEditor.zip"), &old_config, &db).unwrap();
+        let old_db = LocalDatabase::fetch(old_config.get_owml_path()).unwrap();
+        toggle_mod("Bwc9876.TimeSaver", &old_db, false, false).unwrap();
+
+        let report = migrate_old_manager(&mut config, &old_path, "test").unwrap();
+        assert_eq!(report.migrated, vec!["TimeSaver is now disabled"]);
+        assert_eq!(report.skipped.len(), 2);
+        let db = LocalDatabase::fetch(config.get_owml_path()).unwrap();
+        assert!(!db.get_mod("Bwc9876.TimeSaver").unwrap().enabled);
+        let old_db = LocalDatabase::fetch(old_config.get_owml_path()).unwrap();
+        assert!(old_db.get_mod("Bwc9876.SaveEditor").unwrap().enabled);
+        let entries = History::from_config(&config).entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].operation, Operation::Import);
+        assert_eq!(entries[0].front_end, "test");
+        assert_eq!(entries[0].changes[0].unique_name, "Bwc9876.TimeSaver");
+
+        // Toggles made since then aren't undone by migrating again
+        let db = LocalDatabase::fetch(config.get_owml_path()).unwrap();
+        toggle_mod("Bwc9876.TimeSaver", &db, true, false).unwrap();
+        let report = migrate_old_manager(&mut config, &old_path, "test").unwrap();
+        assert!(report.migrated.is_empty());
+        let db = LocalDatabase::fetch(config.get_owml_path()).unwrap();
+        assert!(db.get_mod("Bwc9876.TimeSaver").unwrap().enabled);
+        assert_eq!(History::from_config(&config).entries().unwrap().len(), 1);
+        dir.close().unwrap();
+    }
+}
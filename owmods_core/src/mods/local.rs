@@ -1,10 +1,19 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use typeshare::typeshare;
 
-use crate::{search::Searchable, validate::ModValidationError};
+use crate::{
+    constants::MOD_INSTALL_INFO_NAME,
+    file::{deserialize_from_json, get_dir_size, serialize_to_json},
+    search::Searchable,
+    validate::ModValidationError,
+};
 
 /// Represents an installed (and valid) mod
 #[typeshare]
@@ -15,12 +24,103 @@ pub struct LocalMod {
     pub errors: Vec<ModValidationError>,
     pub mod_path: String,
     pub manifest: ModManifest,
+    pub linked: bool,
+    pub install_reason: InstallReason,
+    pub channel: ReleaseChannel,
+}
+
+/// Why a mod was installed, used to find dependencies that nothing needs anymore
+#[typeshare]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InstallReason {
+    /// The user asked for this mod, mods installed before we tracked this are treated as explicit so they're never removed automatically
+    #[default]
+    Explicit,
+    /// The mod was only installed because another mod depends on it, or it's the parent of an addon
+    Dependency,
+}
+
+/// Which releases of a mod to install and update to
+#[typeshare]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReleaseChannel {
+    /// Only use the release in the database
+    #[default]
+    Stable,
+    /// Use the mod's prerelease when it has one that's newer than the release
+    Prerelease,
+}
+
+/// Info about how a mod was installed, stored in [MOD_INSTALL_INFO_NAME] in the mod's folder
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct ModInstallInfo {
+    #[serde(default)]
+    install_reason: InstallReason,
+    #[serde(default)]
+    channel: ReleaseChannel,
+}
+
+fn read_install_info(mod_path: &Path) -> ModInstallInfo {
+    deserialize_from_json(&mod_path.join(MOD_INSTALL_INFO_NAME)).unwrap_or_default()
+}
+
+fn write_install_info(mod_path: &Path, info: &ModInstallInfo) -> Result<()> {
+    serialize_to_json(info, &mod_path.join(MOD_INSTALL_INFO_NAME), false)
+}
+
+/// Read why the mod in a folder was installed, if we don't know it's assumed to be [InstallReason::Explicit]
+pub fn read_install_reason(mod_path: &Path) -> InstallReason {
+    read_install_info(mod_path).install_reason
+}
+
+/// Record why the mod in a folder was installed, this is kept when the mod is updated
+///
+/// ## Errors
+///
+/// If we can't write the install info file
+///
+pub fn write_install_reason(mod_path: &Path, install_reason: InstallReason) -> Result<()> {
+    let mut info = read_install_info(mod_path);
+    info.install_reason = install_reason;
+    write_install_info(mod_path, &info)
+}
+
+/// Read which release channel the mod in a folder follows, if we don't know it's assumed to be [ReleaseChannel::Stable]
+pub fn read_channel(mod_path: &Path) -> ReleaseChannel {
+    read_install_info(mod_path).channel
+}
+
+/// Record which release channel the mod in a folder follows, this is kept when the mod is updated
+///
+/// ## Errors
+///
+/// If we can't write the install info file
+///
+pub fn write_channel(mod_path: &Path, channel: ReleaseChannel) -> Result<()> {
+    let mut info = read_install_info(mod_path);
+    info.channel = channel;
+    write_install_info(mod_path, &info)
 }
 
 impl LocalMod {
     pub fn uses_pre_patcher(&self) -> bool {
         self.manifest.patcher.is_some()
     }
+
+    /// Get how much space the mod takes up on disk, this walks the mod's folder so it isn't free
+    ///
+    /// ## Returns
+    ///
+    /// The total size of every file in the mod's folder, in bytes
+    ///
+    /// ## Errors
+    ///
+    /// If we can't read the mod's folder
+    ///
+    pub fn get_install_size(&self) -> Result<u64> {
+        get_dir_size(Path::new(&self.mod_path))
+    }
 }
 
 /// Represents a mod that completely failed to load
@@ -129,6 +229,9 @@ impl LocalMod {
             mod_path: "".to_string(),
             enabled: true,
             errors: vec![],
+            linked: false,
+            install_reason: InstallReason::Explicit,
+            channel: ReleaseChannel::Stable,
         }
     }
 }
@@ -136,8 +239,11 @@ impl LocalMod {
 /// Get the paths to preserve for a mod, if [None] is passed the list will contain only `config.json`.
 pub fn get_paths_to_preserve(local_mod: Option<&LocalMod>) -> Vec<PathBuf> {
     if let Some(local_mod) = local_mod {
-        let mut paths: Vec<PathBuf> =
-            vec![PathBuf::from("config.json"), PathBuf::from("save.json")];
+        let mut paths: Vec<PathBuf> = vec![
+            PathBuf::from("config.json"),
+            PathBuf::from("save.json"),
+            PathBuf::from(MOD_INSTALL_INFO_NAME),
+        ];
         if let Some(raw_paths) = local_mod.manifest.paths_to_preserve.to_owned() {
             for path in raw_paths.iter() {
                 paths.push(PathBuf::from(path));
@@ -1,7 +1,11 @@
+use anyhow::Result;
+use reqwest::header::CONTENT_LENGTH;
 use serde::{Deserialize, Serialize};
 use typeshare::typeshare;
 
-use crate::search::Searchable;
+use crate::{search::Searchable, version::is_newer};
+
+use super::local::{ModManifest, ReleaseChannel};
 
 /// Represents a mod in the remote database
 #[typeshare]
@@ -24,6 +28,9 @@ pub struct RemoteMod {
     pub prerelease: Option<ModPrerelease>,
     alpha: Option<bool>,
     pub tags: Option<Vec<String>>,
+    /// The name of the [crate::config::DatabaseSource] this mod came from, `None` if it came from the default database
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
 }
 
 impl RemoteMod {
@@ -32,6 +39,81 @@ impl RemoteMod {
         self.author_display.as_ref().unwrap_or(&self.author)
     }
 
+    /// Whether the database says this mod should always be installed
+    pub fn is_required(&self) -> bool {
+        self.required.unwrap_or(false)
+    }
+
+    /// Whether this mod is for Outer Wilds Alpha
+    pub fn is_alpha(&self) -> bool {
+        self.alpha.unwrap_or(false)
+    }
+
+    /// Get the version and download URL a mod following `channel` should be on.
+    /// Mods on [ReleaseChannel::Prerelease] get the prerelease if it's newer than the release, otherwise they get the release like everyone else.
+    ///
+    /// ## Returns
+    ///
+    /// The version and download URL, in that order
+    ///
+    pub fn get_channel_release(&self, channel: ReleaseChannel) -> (&String, &String) {
+        match (channel, &self.prerelease) {
+            (ReleaseChannel::Prerelease, Some(prerelease))
+                if is_newer(&prerelease.version, &self.version) =>
+            {
+                (&prerelease.version, &prerelease.download_url)
+            }
+            _ => (&self.version, &self.download_url),
+        }
+    }
+
+    /// Ask the server hosting the mod how big its download is, this makes a `HEAD` request to the download URL
+    ///
+    /// ## Returns
+    ///
+    /// The size of the download in bytes, or `None` if the server doesn't send a `Content-Length`
+    ///
+    /// ## Errors
+    ///
+    /// If the request fails or the server responds with an error
+    ///
+    pub async fn get_download_size(&self) -> Result<Option<u64>> {
+        let response = reqwest::Client::new()
+            .head(&self.download_url)
+            .send()
+            .await?
+            .error_for_status()?;
+        // Not content_length(), that's the size of the (empty) body of the HEAD response
+        Ok(response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok()))
+    }
+
+    /// Make a database entry for a mod from its manifest, for databases we build ourselves
+    pub(crate) fn from_manifest(manifest: &ModManifest, download_url: String) -> Self {
+        Self {
+            download_url,
+            download_count: 0,
+            version: manifest.version.clone(),
+            name: manifest.name.clone(),
+            unique_name: manifest.unique_name.clone(),
+            description: String::new(),
+            readme: None,
+            slug: manifest.unique_name.to_ascii_lowercase(),
+            required: None,
+            repo: String::new(),
+            author: manifest.author.clone(),
+            author_display: None,
+            parent: None,
+            prerelease: None,
+            alpha: None,
+            tags: None,
+            source: None,
+        }
+    }
+
     #[cfg(test)]
     pub fn get_test(num: u8) -> Self {
         serde_json::from_str(
@@ -70,3 +152,58 @@ pub struct ModReadMe {
     pub html_url: String,
     pub download_url: String,
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{
+        db::RemoteDatabase,
+        test_utils::{get_test_file, MockFailure, MockServer},
+    };
+
+    #[test]
+    fn test_get_channel_release() {
+        let mut remote_mod = RemoteMod::get_test(1);
+        remote_mod.version = "1.0.0".to_string();
+        remote_mod.download_url = "stable.zip".to_string();
+        assert_eq!(
+            remote_mod.get_channel_release(ReleaseChannel::Prerelease),
+            (&"1.0.0".to_string(), &"stable.zip".to_string())
+        );
+        remote_mod.prerelease = Some(ModPrerelease {
+            download_url: "pre.zip".to_string(),
+            version: "1.1.0-beta".to_string(),
+        });
+        assert_eq!(
+            remote_mod.get_channel_release(ReleaseChannel::Stable),
+            (&"1.0.0".to_string(), &"stable.zip".to_string())
+        );
+        assert_eq!(
+            remote_mod.get_channel_release(ReleaseChannel::Prerelease),
+            (&"1.1.0-beta".to_string(), &"pre.zip".to_string())
+        );
+        // The prerelease is older than the release now, so the release wins
+        remote_mod.version = "1.1.0".to_string();
+        assert_eq!(
+            remote_mod.get_channel_release(ReleaseChannel::Prerelease),
+            (&"1.1.0".to_string(), &"stable.zip".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_download_size() {
+        let server = MockServer::start();
+        tokio_test::block_on(async {
+            let db = RemoteDatabase::fetch(&server.database_url()).await.unwrap();
+            let remote_mod = db.get_mod("Bwc9876.TimeSaver").unwrap();
+            let size = remote_mod.get_download_size().await.unwrap();
+            let expected = std::fs::metadata(get_test_file("Bwc9876.TimeSaver.zip"))
+                .unwrap()
+                .len();
+            assert_eq!(size, Some(expected));
+            server.fail("Bwc9876.TimeSaver.zip", MockFailure::Status(404));
+            assert!(remote_mod.get_download_size().await.is_err());
+        });
+    }
+}
@@ -20,7 +20,7 @@ pub fn open_shortcut(identifier: &str, conf: &Config, local_db: &LocalDatabase)
         "db" => DB_REPO_URL,
         "website" => WEBSITE_URL,
         "owml_docs" => OWML_DOCS_URL,
-        "owml" => &conf.owml_path,
+        "owml" => conf.get_owml_path(),
         _ => "",
     };
 
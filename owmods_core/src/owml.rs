@@ -34,7 +34,7 @@ pub struct OWMLConfig {
 
 impl OWMLConfig {
     fn path(config: &Config) -> PathBuf {
-        Path::new(&config.owml_path).join("OWML.Config.json")
+        Path::new(config.get_owml_path()).join("OWML.Config.json")
     }
 
     fn read(config: &Config) -> Result<OWMLConfig> {
@@ -72,7 +72,7 @@ impl OWMLConfig {
 
         const LINUX_GAME_PATH: &str = ".steam/steam/steamapps/common/Outer Wilds/";
 
-        let path = Path::new(&config.owml_path).join(OWML_DEFAULT_CONFIG_NAME);
+        let path = Path::new(config.get_owml_path()).join(OWML_DEFAULT_CONFIG_NAME);
         let mut conf: OWMLConfig = deserialize_from_json(&path)?;
         let dirs = UserDirs::new().ok_or_else(|| anyhow!("Can't get user data dir"))?;
         conf.game_path = dirs
@@ -86,7 +86,7 @@ impl OWMLConfig {
 
     #[cfg(windows)]
     pub fn default(config: &Config) -> Result<OWMLConfig> {
-        deserialize_from_json(&Path::new(&config.owml_path).join(OWML_DEFAULT_CONFIG_NAME))
+        deserialize_from_json(&Path::new(config.get_owml_path()).join(OWML_DEFAULT_CONFIG_NAME))
     }
 
     fn write(owml_config: &OWMLConfig, config: &Config) -> Result<()> {
@@ -0,0 +1,752 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use glob::glob;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    constants::{DEFAULT_INSTANCE_NAME, PATCHES_FILE_NAME, PATCH_BACKUPS_DIR_NAME},
+    db::LocalDatabase,
+    download::collect_files,
+    file::{create_all_parents, deserialize_from_json, serialize_to_json},
+    game::get_runner_cmd,
+    mods::local::LocalMod,
+    owml::OWMLConfig,
+};
+
+/// The argument passed to a pre-patcher after the game path to ask it to undo its changes
+pub const UNPATCH_ARG: &str = "--unpatch";
+
+/// A mod whose pre-patcher the manager has run on the game
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchRecord {
+    /// The instance whose game was patched, see [Config::get_instance_name]
+    #[serde(default = "default_instance")]
+    pub instance: String,
+    pub unique_name: String,
+    pub name: String,
+    /// The version of the mod when it patched the game
+    pub version: String,
+    /// The pre-patcher that was run, relative to the mod's folder
+    pub patcher: String,
+    /// The game install that was patched
+    pub game_path: String,
+    /// Game files the pre-patcher changed or deleted, relative to `game_path`. A copy of each from before patching is kept so they can be restored
+    pub changed_files: Vec<String>,
+    /// Game files the pre-patcher created, relative to `game_path`. These are deleted when restoring
+    pub created_files: Vec<String>,
+}
+
+fn default_instance() -> String {
+    DEFAULT_INSTANCE_NAME.to_string()
+}
+
+impl PatchRecord {
+    fn touches(&self, path: &str) -> bool {
+        self.changed_files
+            .iter()
+            .chain(self.created_files.iter())
+            .any(|f| f == path)
+    }
+}
+
+/// Runs the pre-patchers of mods (see [crate::mods::local::ModManifest::patcher]) and keeps track of which mods have patched the game so the changes can be reversed.
+///
+/// Pre-patchers are run with [Config::get_game_runner] from the mod's folder with the game path as their only argument.
+/// Before running one, the game's managed assemblies (`*_Data/Managed`) are backed up so they can be restored when the mod is disabled or uninstalled.
+/// If a mod that patched later changed the same files, the pre-patcher is run again with [UNPATCH_ARG] instead so the other mod's changes are kept.
+///
+/// The patched mods are stored in `patches.json` in the app directory, with the backups in `patch_backups/<instance>`.
+/// Each instance's game is patched separately, a [Patches] only sees and changes the patches of one instance.
+#[derive(Clone, Debug)]
+pub struct Patches {
+    dir: PathBuf,
+    instance: String,
+}
+
+impl Patches {
+    /// Track patches of the given instance in the given directory
+    pub fn new(dir: &Path, instance: &str) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            instance: instance.to_string(),
+        }
+    }
+
+    /// Track patches of the selected instance next to the config file, normally the app directory
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(
+            config.path.parent().unwrap_or(Path::new(".")),
+            config.get_instance_name(),
+        )
+    }
+
+    fn records_path(&self) -> PathBuf {
+        self.dir.join(PATCHES_FILE_NAME)
+    }
+
+    fn backups_path(&self, unique_name: &str) -> PathBuf {
+        self.dir
+            .join(PATCH_BACKUPS_DIR_NAME)
+            .join(&self.instance)
+            .join(unique_name)
+    }
+
+    fn all_records(&self) -> Result<Vec<PatchRecord>> {
+        let path = self.records_path();
+        if path.is_file() {
+            deserialize_from_json(&path)
+        } else {
+            Ok(vec![])
+        }
+    }
+
+    /// Get every mod that has patched the instance's game, in the order they patched it
+    ///
+    /// ## Errors
+    ///
+    /// If the patches file exists but we can't read it.
+    ///
+    pub fn records(&self) -> Result<Vec<PatchRecord>> {
+        Ok(self
+            .all_records()?
+            .into_iter()
+            .filter(|r| r.instance == self.instance)
+            .collect())
+    }
+
+    /// Replace the records of this instance, the ones of other instances are kept
+    fn save_records(&self, records: &[PatchRecord]) -> Result<()> {
+        let mut all_records = self.all_records()?;
+        all_records.retain(|r| r.instance != self.instance);
+        all_records.extend(records.iter().cloned());
+        serialize_to_json(&all_records, &self.records_path(), true)
+    }
+
+    /// Get the patch made by a mod, if the mod has patched the game
+    ///
+    /// ## Errors
+    ///
+    /// If we can't read the patches file.
+    ///
+    pub fn get(&self, unique_name: &str) -> Result<Option<PatchRecord>> {
+        Ok(self
+            .records()?
+            .into_iter()
+            .find(|r| r.unique_name == unique_name))
+    }
+
+    /// Run a mod's pre-patcher on the game of the selected instance ([Config::get_game_path]), or the game set in OWML's config.
+    /// If the mod already patched the game with the same version and patcher nothing is done, if it patched with a different one that patch is reversed first.
+    ///
+    /// ## Returns
+    ///
+    /// The record of the patch.
+    ///
+    /// ## Errors
+    ///
+    /// - If the mod doesn't have a pre-patcher
+    /// - If the game path in OWML's config isn't a folder
+    /// - If we can't back up the game's files
+    /// - If the pre-patcher can't be run or fails, the game's files are restored in this case
+    ///
+    pub async fn patch(&self, config: &Config, local_mod: &LocalMod) -> Result<PatchRecord> {
+        let unique_name = &local_mod.manifest.unique_name;
+        let patcher = local_mod
+            .manifest
+            .patcher
+            .as_ref()
+            .ok_or_else(|| anyhow!("{} doesn't have a pre-patcher", local_mod.manifest.name))?;
+        if let Some(record) = self.get(unique_name)? {
+            if &record.patcher == patcher && record.version == local_mod.manifest.version {
+                return Ok(record);
+            }
+            // The mod is still installed, so if a later patch changed the same files it can unpatch itself
+            self.unpatch_mod(config, unique_name, Some(local_mod))
+                .await?;
+        }
+
+        let game_path = match config.get_game_path() {
+            Some(game_path) => PathBuf::from(game_path),
+            None => PathBuf::from(OWMLConfig::get(config)?.game_path),
+        };
+        if !game_path.is_dir() {
+            return Err(anyhow!(
+                "Game path {} isn't a folder, set it in OWML's settings",
+                game_path.display()
+            ));
+        }
+
+        info!("Running {}'s pre-patcher", local_mod.manifest.name);
+        let backups_path = self.backups_path(unique_name);
+        if backups_path.is_dir() {
+            fs::remove_dir_all(&backups_path)?;
+        }
+        let before = get_patchable_files(&game_path)?;
+        for file in before.iter() {
+            let backup_path = backups_path.join(file);
+            create_all_parents(&backup_path)?;
+            fs::copy(game_path.join(file), backup_path)?;
+        }
+
+        let mut record = PatchRecord {
+            instance: self.instance.clone(),
+            unique_name: unique_name.clone(),
+            name: local_mod.manifest.name.clone(),
+            version: local_mod.manifest.version.clone(),
+            patcher: patcher.clone(),
+            game_path: game_path.to_str().unwrap().to_string(),
+            changed_files: vec![],
+            created_files: vec![],
+        };
+
+        let after = run_patcher(config, local_mod, patcher, &game_path, false)
+            .await
+            .and_then(|_| get_patchable_files(&game_path));
+        let after = match after {
+            Ok(after) => after,
+            Err(why) => {
+                // Put back anything the patcher changed before it failed
+                record.changed_files = before.iter().map(|f| path_to_string(f)).collect();
+                record.created_files = get_patchable_files(&game_path)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter(|f| !before.contains(f))
+                    .map(|f| path_to_string(f))
+                    .collect();
+                self.restore(&record).ok();
+                fs::remove_dir_all(&backups_path).ok();
+                return Err(why);
+            }
+        };
+
+        for file in before.iter() {
+            let backup_path = backups_path.join(file);
+            let game_file = game_path.join(file);
+            let unchanged = game_file.is_file() && fs::read(&game_file)? == fs::read(&backup_path)?;
+            if unchanged {
+                fs::remove_file(backup_path)?;
+            } else {
+                record.changed_files.push(path_to_string(file));
+            }
+        }
+        record.created_files = after
+            .iter()
+            .filter(|f| !before.contains(f))
+            .map(|f| path_to_string(f))
+            .collect();
+        debug!(
+            "{} changed {:?} and created {:?}",
+            patcher, record.changed_files, record.created_files
+        );
+
+        let mut records = self.records()?;
+        records.push(record.clone());
+        self.save_records(&records)?;
+        Ok(record)
+    }
+
+    /// Reverse the patch a mod made to the game.
+    /// The game files backed up when patching are restored, unless a mod that patched after this one changed the same files.
+    /// In that case the mod's pre-patcher is run with [UNPATCH_ARG], which needs the mod to still be installed.
+    ///
+    /// Pass the local database to use the mod's pre-patcher, call this before uninstalling the mod.
+    ///
+    /// ## Returns
+    ///
+    /// `true` if the patch was reversed, `false` if the mod hadn't patched the game.
+    ///
+    /// ## Errors
+    ///
+    /// - If we can't restore the game files
+    /// - If the pre-patcher is needed but the mod isn't installed or its pre-patcher fails
+    ///
+    pub async fn unpatch(
+        &self,
+        config: &Config,
+        unique_name: &str,
+        local_db: Option<&LocalDatabase>,
+    ) -> Result<bool> {
+        let local_mod = local_db.and_then(|db| db.get_mod(unique_name));
+        self.unpatch_mod(config, unique_name, local_mod).await
+    }
+
+    async fn unpatch_mod(
+        &self,
+        config: &Config,
+        unique_name: &str,
+        local_mod: Option<&LocalMod>,
+    ) -> Result<bool> {
+        let mut records = self.records()?;
+        let Some(index) = records.iter().position(|r| r.unique_name == unique_name) else {
+            return Ok(false);
+        };
+        let record = &records[index];
+        let overlaps = records[index + 1..].iter().any(|later| {
+            record
+                .changed_files
+                .iter()
+                .chain(record.created_files.iter())
+                .any(|f| later.touches(f))
+        });
+        if overlaps {
+            let local_mod = local_mod.ok_or_else(|| {
+                anyhow!(
+                    "Can't unpatch {}, another mod patched the same files and it isn't installed",
+                    record.name
+                )
+            })?;
+            info!("Running {}'s pre-patcher to unpatch", record.name);
+            run_patcher(
+                config,
+                local_mod,
+                &record.patcher,
+                Path::new(&record.game_path),
+                true,
+            )
+            .await?;
+        } else {
+            info!("Restoring game files patched by {}", record.name);
+            self.restore(record)?;
+        }
+        let backups_path = self.backups_path(unique_name);
+        if backups_path.is_dir() {
+            fs::remove_dir_all(backups_path)?;
+        }
+        records.remove(index);
+        self.save_records(&records)?;
+        Ok(true)
+    }
+
+    /// Reverse the patches of mods that are now disabled or uninstalled in the instance.
+    /// `local_db` has to be the database of the instance, other instances' patches are never touched.
+    /// Mods that can't be unpatched are logged and skipped.
+    ///
+    /// ## Returns
+    ///
+    /// The patches that were reversed.
+    ///
+    /// ## Errors
+    ///
+    /// If we can't read the patches file.
+    ///
+    pub async fn unpatch_inactive(
+        &self,
+        config: &Config,
+        local_db: &LocalDatabase,
+    ) -> Result<Vec<PatchRecord>> {
+        let mut unpatched = vec![];
+        // Go newest first so restoring backups never has to go through a pre-patcher
+        for record in self.records()?.into_iter().rev() {
+            let active = local_db
+                .get_mod(&record.unique_name)
+                .is_some_and(|m| m.enabled);
+            if active {
+                continue;
+            }
+            match self
+                .unpatch(config, &record.unique_name, Some(local_db))
+                .await
+            {
+                Ok(_) => unpatched.push(record),
+                Err(why) => warn!("Couldn't unpatch {}: {:?}", record.name, why),
+            }
+        }
+        Ok(unpatched)
+    }
+
+    /// Bring the game in line with the installed mods, this reverses the patches of disabled or uninstalled mods ([Patches::unpatch_inactive])
+    /// and runs the pre-patcher of every enabled mod that hasn't patched the game with its current version ([Patches::patch]).
+    ///
+    /// ## Returns
+    ///
+    /// The names of the mods that patched the game.
+    ///
+    /// ## Errors
+    ///
+    /// If a pre-patcher fails.
+    ///
+    pub async fn sync(&self, config: &Config, local_db: &LocalDatabase) -> Result<Vec<String>> {
+        self.unpatch_inactive(config, local_db).await?;
+        let records = self.records()?;
+        let mut patched = vec![];
+        let mut mods = local_db
+            .valid()
+            .filter(|m| m.enabled && m.uses_pre_patcher())
+            .collect::<Vec<_>>();
+        mods.sort_by(|a, b| a.manifest.unique_name.cmp(&b.manifest.unique_name));
+        for local_mod in mods {
+            let up_to_date = records.iter().any(|r| {
+                r.unique_name == local_mod.manifest.unique_name
+                    && r.version == local_mod.manifest.version
+                    && Some(&r.patcher) == local_mod.manifest.patcher.as_ref()
+            });
+            if !up_to_date {
+                self.patch(config, local_mod).await?;
+                patched.push(local_mod.manifest.name.clone());
+            }
+        }
+        Ok(patched)
+    }
+
+    fn restore(&self, record: &PatchRecord) -> Result<()> {
+        let game_path = Path::new(&record.game_path);
+        let backups_path = self.backups_path(&record.unique_name);
+        for file in record.created_files.iter() {
+            let path = game_path.join(file);
+            if path.is_file() {
+                fs::remove_file(path)?;
+            }
+        }
+        for file in record.changed_files.iter() {
+            let path = game_path.join(file);
+            create_all_parents(&path)?;
+            fs::copy(backups_path.join(file), path)?;
+        }
+        Ok(())
+    }
+}
+
+fn path_to_string(path: &Path) -> String {
+    path.to_str().unwrap().to_string()
+}
+
+/// Get the files pre-patchers are expected to change, the game's managed assemblies, relative to the game path
+fn get_patchable_files(game_path: &Path) -> Result<Vec<PathBuf>> {
+    let pattern = game_path.join("*_Data").join("Managed");
+    let mut files = vec![];
+    for dir in glob(pattern.to_str().unwrap())?.flatten() {
+        if dir.is_dir() {
+            let mut dir_files = vec![];
+            collect_files(game_path, &dir, &mut dir_files)?;
+            files.extend(dir_files.into_iter().map(|(path, _)| path));
+        }
+    }
+    Ok(files)
+}
+
+async fn run_patcher(
+    config: &Config,
+    local_mod: &LocalMod,
+    patcher: &str,
+    game_path: &Path,
+    unpatch: bool,
+) -> Result<()> {
+    let mod_path = Path::new(&local_mod.mod_path);
+    let exe_path = mod_path.join(patcher);
+    if !exe_path.is_file() {
+        return Err(anyhow!(
+            "{}'s pre-patcher ({}) wasn't found",
+            local_mod.manifest.name,
+            patcher
+        ));
+    }
+    let mut cmd = get_runner_cmd(config, &exe_path);
+    cmd.arg(game_path).current_dir(mod_path);
+    if unpatch {
+        cmd.arg(UNPATCH_ARG);
+    }
+    let output = cmd.output().await.map_err(|why| {
+        anyhow!(
+            "Failed to run {}'s pre-patcher: {why:?}",
+            local_mod.manifest.name
+        )
+    })?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{}'s pre-patcher failed ({}): {}",
+            local_mod.manifest.name,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+
+    use super::*;
+    use crate::{
+        config::{Game, Instance},
+        history::{History, Operation},
+        mods::local::UnsafeLocalMod,
+        test_utils::make_test_dir,
+        toggle::toggle_mod,
+    };
+
+    const PATCHER: &str = r#"
+managed="$1/Game_Data/Managed"
+if [ "$2" = "--unpatch" ]; then
+    printf original > "$managed/Assembly-CSharp.dll"
+    rm -f "$managed/$PATCHER_NAME.dll"
+    exit 0
+fi
+if [ -f fail ]; then
+    printf broken > "$managed/Assembly-CSharp.dll"
+    echo "Patch failed" >&2
+    exit 1
+fi
+printf "patched by $PATCHER_NAME" > "$managed/Assembly-CSharp.dll"
+printf new > "$managed/$PATCHER_NAME.dll"
+"#;
+
+    fn setup(dir: &Path) -> (Config, Patches, PathBuf) {
+        let mut config = Config::default(Some(dir.join("settings.json"))).unwrap();
+        let owml_path = dir.join("OWML");
+        config.owml_path = owml_path.to_str().unwrap().to_string();
+        config.game_runner = Some("sh".to_string());
+        let game_path = dir.join("Game");
+        let managed = game_path.join("Game_Data").join("Managed");
+        fs::create_dir_all(&managed).unwrap();
+        fs::write(managed.join("Assembly-CSharp.dll"), "original").unwrap();
+        fs::write(managed.join("UnityEngine.dll"), "engine").unwrap();
+        fs::create_dir_all(&owml_path).unwrap();
+        fs::write(
+            owml_path.join("OWML.Config.json"),
+            format!(
+                r#"{{"gamePath": "{}", "debugMode": false, "forceExe": false, "incrementalGC": false, "socketPort": 0}}"#,
+                game_path.to_str().unwrap()
+            ),
+        )
+        .unwrap();
+        (config, Patches::new(dir, DEFAULT_INSTANCE_NAME), game_path)
+    }
+
+    fn make_mod(config: &Config, name: &str) {
+        let mod_path = Path::new(config.get_owml_path()).join("Mods").join(name);
+        fs::create_dir_all(&mod_path).unwrap();
+        fs::write(
+            mod_path.join("manifest.json"),
+            format!(
+                r#"{{"name": "{name}", "uniqueName": "Example.{name}", "author": "Example", "version": "1.0.0", "patcher": "patcher.sh"}}"#
+            ),
+        )
+        .unwrap();
+        fs::write(mod_path.join("config.json"), r#"{"enabled": true}"#).unwrap();
+        fs::write(
+            mod_path.join("patcher.sh"),
+            PATCHER.replace("$PATCHER_NAME", name),
+        )
+        .unwrap();
+    }
+
+    fn read_assembly(game_path: &Path) -> String {
+        fs::read_to_string(game_path.join("Game_Data/Managed/Assembly-CSharp.dll")).unwrap()
+    }
+
+    fn fetch(config: &Config) -> LocalDatabase {
+        LocalDatabase::fetch(config.get_owml_path()).unwrap()
+    }
+
+    #[test]
+    fn test_patch_and_unpatch() {
+        let dir = make_test_dir();
+        let (config, patches, game_path) = setup(dir.path());
+        make_mod(&config, "PatchMod");
+        let db = fetch(&config);
+        let local_mod = db.get_mod("Example.PatchMod").unwrap();
+        tokio_test::block_on(async {
+            let record = patches.patch(&config, local_mod).await.unwrap();
+            assert_eq!(record.version, "1.0.0");
+            assert_eq!(
+                record.changed_files,
+                vec!["Game_Data/Managed/Assembly-CSharp.dll"]
+            );
+            assert_eq!(record.created_files, vec!["Game_Data/Managed/PatchMod.dll"]);
+            assert_eq!(read_assembly(&game_path), "patched by PatchMod");
+            assert_eq!(patches.records().unwrap(), vec![record]);
+
+            assert!(patches
+                .unpatch(&config, "Example.PatchMod", None)
+                .await
+                .unwrap());
+            assert_eq!(read_assembly(&game_path), "original");
+            assert!(!game_path.join("Game_Data/Managed/PatchMod.dll").exists());
+            assert!(patches.records().unwrap().is_empty());
+            assert!(!patches
+                .unpatch(&config, "Example.PatchMod", None)
+                .await
+                .unwrap());
+        });
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_patch_failed() {
+        let dir = make_test_dir();
+        let (config, patches, game_path) = setup(dir.path());
+        make_mod(&config, "PatchMod");
+        let db = fetch(&config);
+        let local_mod = db.get_mod("Example.PatchMod").unwrap();
+        fs::write(Path::new(&local_mod.mod_path).join("fail"), "").unwrap();
+        tokio_test::block_on(async {
+            let err = patches.patch(&config, local_mod).await.unwrap_err();
+            assert!(err.to_string().contains("Patch failed"));
+        });
+        assert_eq!(read_assembly(&game_path), "original");
+        assert!(patches.records().unwrap().is_empty());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_unpatch_overlapping() {
+        let dir = make_test_dir();
+        let (config, patches, game_path) = setup(dir.path());
+        make_mod(&config, "First");
+        make_mod(&config, "Second");
+        let db = fetch(&config);
+        tokio_test::block_on(async {
+            let patched = patches.sync(&config, &db).await.unwrap();
+            assert_eq!(patched, vec!["First", "Second"]);
+            assert_eq!(read_assembly(&game_path), "patched by Second");
+
+            // Second patched the same file after First, so First has to unpatch itself
+            assert!(patches
+                .unpatch(&config, "Example.First", None)
+                .await
+                .is_err());
+            patches
+                .unpatch(&config, "Example.First", Some(&db))
+                .await
+                .unwrap();
+            assert!(!game_path.join("Game_Data/Managed/First.dll").exists());
+            assert!(game_path.join("Game_Data/Managed/Second.dll").exists());
+            assert_eq!(patches.records().unwrap().len(), 1);
+        });
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_repatch_overlapping() {
+        let dir = make_test_dir();
+        let (config, patches, game_path) = setup(dir.path());
+        make_mod(&config, "First");
+        make_mod(&config, "Second");
+        tokio_test::block_on(async {
+            patches.sync(&config, &fetch(&config)).await.unwrap();
+
+            // Updating First means unpatching the old version, which has to go through its pre-patcher because of Second
+            let manifest_path = Path::new(config.get_owml_path()).join("Mods/First/manifest.json");
+            let manifest = fs::read_to_string(&manifest_path).unwrap();
+            fs::write(&manifest_path, manifest.replace("1.0.0", "1.10.0")).unwrap();
+            let patched = patches.sync(&config, &fetch(&config)).await.unwrap();
+            assert_eq!(patched, vec!["First"]);
+            assert_eq!(read_assembly(&game_path), "patched by First");
+            let records = patches.records().unwrap();
+            assert_eq!(records.len(), 2);
+            assert_eq!(records[1].unique_name, "Example.First");
+            assert_eq!(records[1].version, "1.10.0");
+        });
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_undo_install_unpatches() {
+        let dir = make_test_dir();
+        let (config, patches, game_path) = setup(dir.path());
+        let history = History::from_config(&config);
+        let pending = history.start(Operation::Install, "test", &fetch(&config));
+        make_mod(&config, "PatchMod");
+        pending.finish(&fetch(&config)).unwrap();
+        tokio_test::block_on(async {
+            patches.sync(&config, &fetch(&config)).await.unwrap();
+            history
+                .undo(None, &config, &fetch(&config), "test", false)
+                .await
+                .unwrap();
+        });
+        assert!(fetch(&config).get_mod("Example.PatchMod").is_none());
+        assert_eq!(read_assembly(&game_path), "original");
+        assert!(patches.records().unwrap().is_empty());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_unpatch_inactive() {
+        let dir = make_test_dir();
+        let (config, patches, game_path) = setup(dir.path());
+        make_mod(&config, "First");
+        make_mod(&config, "Second");
+        let db = fetch(&config);
+        tokio_test::block_on(async {
+            patches.sync(&config, &db).await.unwrap();
+            toggle_mod("Example.Second", &db, false, false).unwrap();
+            let db = fetch(&config);
+            assert!(matches!(
+                db.mods.get("Example.Second"),
+                Some(UnsafeLocalMod::Valid(m)) if !m.enabled
+            ));
+            let unpatched = patches.unpatch_inactive(&config, &db).await.unwrap();
+            assert_eq!(unpatched.len(), 1);
+            assert_eq!(unpatched[0].unique_name, "Example.Second");
+            assert_eq!(read_assembly(&game_path), "patched by First");
+            assert!(patches.sync(&config, &db).await.unwrap().is_empty());
+        });
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_instances_patch_separately() {
+        let dir = make_test_dir();
+        let (config, _, game_path) = setup(dir.path());
+        let other_game_path = dir.path().join("OtherGame");
+        let managed = other_game_path.join("Game_Data").join("Managed");
+        fs::create_dir_all(&managed).unwrap();
+        fs::write(managed.join("Assembly-CSharp.dll"), "original").unwrap();
+        let mut other_config = config.clone();
+        other_config
+            .add_instance(Instance {
+                name: "Other".to_string(),
+                owml_path: dir.path().join("OtherOWML").to_str().unwrap().to_string(),
+                game_path: other_game_path.to_str().unwrap().to_string(),
+                game_runner: None,
+                game: Game::OuterWilds,
+            })
+            .unwrap();
+        other_config.use_instance("Other").unwrap();
+        make_mod(&config, "PatchMod");
+        let patches = Patches::from_config(&config);
+        let other_patches = Patches::from_config(&other_config);
+        tokio_test::block_on(async {
+            patches.sync(&config, &fetch(&config)).await.unwrap();
+
+            // The other instance doesn't have the mod, that doesn't make it inactive in this one
+            fs::create_dir_all(dir.path().join("OtherOWML")).unwrap();
+            let other_db = fetch(&other_config);
+            let unpatched = other_patches
+                .unpatch_inactive(&other_config, &other_db)
+                .await
+                .unwrap();
+            assert!(unpatched.is_empty());
+            assert_eq!(read_assembly(&game_path), "patched by PatchMod");
+
+            // The same version patched in this instance doesn't stop the other one's game from being patched
+            make_mod(&other_config, "PatchMod");
+            let patched = other_patches
+                .sync(&other_config, &fetch(&other_config))
+                .await
+                .unwrap();
+            assert_eq!(patched, vec!["PatchMod"]);
+            assert_eq!(read_assembly(&other_game_path), "patched by PatchMod");
+
+            assert!(patches
+                .unpatch(&config, "Example.PatchMod", None)
+                .await
+                .unwrap());
+            assert_eq!(read_assembly(&game_path), "original");
+            assert_eq!(read_assembly(&other_game_path), "patched by PatchMod");
+            assert_eq!(other_patches.records().unwrap().len(), 1);
+            assert!(patches.records().unwrap().is_empty());
+        });
+        dir.close().unwrap();
+    }
+}
@@ -3,16 +3,17 @@ use std::{
     path::PathBuf,
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use glob::glob;
 
 use crate::{
-    db::LocalDatabase,
+    db::{LocalDatabase, RemoteDatabase},
     file::check_file_matches_paths,
-    mods::local::{get_paths_to_preserve, FailedMod, LocalMod},
+    mods::local::{get_paths_to_preserve, FailedMod, InstallReason, LocalMod},
 };
 
-/// Uninstall a mod
+/// Uninstall a mod.
+/// When `recursive` is set its dependencies are removed as well, unless a mod that's staying installed still needs them.
 ///
 /// ## Returns
 ///
@@ -20,7 +21,8 @@ use crate::{
 ///
 /// ## Errors
 ///
-/// If we can't delete the mod's folder.
+/// - If we can't delete the mod's folder.
+/// - If the mod is linked with [crate::dev::link_mod], linked dependencies are skipped instead.
 ///
 pub fn remove_mod(
     local_mod: &LocalMod,
@@ -29,6 +31,13 @@ pub fn remove_mod(
 ) -> Result<Vec<String>> {
     let mut show_warnings_for: Vec<String> = vec![];
 
+    if local_mod.linked {
+        return Err(anyhow!(
+            "{} is linked to a development folder, unlink it instead",
+            local_mod.manifest.name
+        ));
+    }
+
     if PathBuf::from(&local_mod.mod_path).is_dir() {
         // In case weird circular dep stuff happens, just don't delete it if it doesn't exist
         remove_dir_all(&local_mod.mod_path)?;
@@ -38,19 +47,101 @@ pub fn remove_mod(
     }
 
     if recursive {
-        let empty: &Vec<String> = &vec![];
-        let deps = local_mod.manifest.dependencies.as_ref().unwrap_or(empty);
-        for dep in deps.iter() {
-            let dep = db.get_mod(dep);
-            if let Some(dep) = dep {
-                show_warnings_for.extend(remove_mod(dep, db, true)?);
-            }
+        for dep in get_unneeded_deps(local_mod, db) {
+            show_warnings_for.extend(remove_mod(dep, db, false)?);
         }
     }
 
     Ok(show_warnings_for)
 }
 
+/// Check if an installed mod that isn't in `ignore` needs `unique_name`, either as a dependency or as the parent of an addon
+fn is_needed(
+    unique_name: &str,
+    db: &LocalDatabase,
+    remote_db: Option<&RemoteDatabase>,
+    ignore: &[&str],
+) -> bool {
+    db.valid()
+        .filter(|m| {
+            m.manifest.unique_name != unique_name
+                && !ignore.contains(&m.manifest.unique_name.as_str())
+        })
+        .any(|m| {
+            let is_dep = m
+                .manifest
+                .dependencies
+                .as_ref()
+                .is_some_and(|deps| deps.iter().any(|d| d == unique_name));
+            let is_parent = remote_db
+                .and_then(|db| db.get_mod(&m.manifest.unique_name))
+                .and_then(|m| m.parent.as_deref())
+                == Some(unique_name);
+            is_dep || is_parent
+        })
+}
+
+/// Get the dependencies of a mod, and their dependencies, that nothing else needs and can be removed with it.
+/// Only mods that were installed as dependencies ([InstallReason::Dependency]) are included, ones the user installed themselves are kept.
+/// These are the mods [remove_mod] removes when `recursive` is set.
+pub fn get_unneeded_deps<'a>(local_mod: &'a LocalMod, db: &'a LocalDatabase) -> Vec<&'a LocalMod> {
+    let mut removing: Vec<&LocalMod> = vec![local_mod];
+    loop {
+        let removing_names: Vec<&str> = removing
+            .iter()
+            .map(|m| m.manifest.unique_name.as_str())
+            .collect();
+        let next = removing
+            .iter()
+            .flat_map(|m| m.manifest.dependencies.iter().flatten())
+            .filter_map(|dep| db.get_mod(dep))
+            .find(|dep| {
+                dep.install_reason == InstallReason::Dependency
+                    && !dep.linked
+                    && !removing_names.contains(&dep.manifest.unique_name.as_str())
+                    && !is_needed(&dep.manifest.unique_name, db, None, &removing_names)
+            });
+        match next {
+            Some(dep) => removing.push(dep),
+            None => break,
+        }
+    }
+    removing.split_off(1)
+}
+
+/// Find mods that were only installed as dependencies ([InstallReason::Dependency]) and that no installed mod needs anymore.
+/// Removing an orphan can leave its own dependencies orphaned, those are included as well.
+/// Pass the remote database so parents of installed addons are kept, without it only dependencies are checked.
+///
+/// ## Returns
+///
+/// The orphaned mods sorted by unique name, linked mods are never included.
+///
+pub fn get_orphaned_mods<'a>(
+    db: &'a LocalDatabase,
+    remote_db: Option<&RemoteDatabase>,
+) -> Vec<&'a LocalMod> {
+    let mut orphans: Vec<&LocalMod> = vec![];
+    loop {
+        let orphan_names: Vec<&str> = orphans
+            .iter()
+            .map(|m| m.manifest.unique_name.as_str())
+            .collect();
+        let next = db.valid().find(|m| {
+            m.install_reason == InstallReason::Dependency
+                && !m.linked
+                && !orphan_names.contains(&m.manifest.unique_name.as_str())
+                && !is_needed(&m.manifest.unique_name, db, remote_db, &orphan_names)
+        });
+        match next {
+            Some(orphan) => orphans.push(orphan),
+            None => break,
+        }
+    }
+    orphans.sort_by(|a, b| a.manifest.unique_name.cmp(&b.manifest.unique_name));
+    orphans
+}
+
 /// Removes a [FailedMod]
 ///
 /// ## Errors
@@ -89,14 +180,23 @@ pub fn remove_old_mod_files(local_mod: &LocalMod) -> Result<()> {
 #[cfg(test)]
 mod tests {
 
-    use std::{fs::File, io::Write};
+    use std::{
+        collections::HashMap,
+        fs::{create_dir_all, File},
+        io::Write,
+        path::Path,
+    };
 
     use crate::{
         config::Config,
-        download::install_mod_from_zip,
+        download::install_mod_from_file,
         file::create_all_parents,
-        mods::local::UnsafeLocalMod,
+        mods::{
+            local::{write_install_reason, UnsafeLocalMod},
+            remote::RemoteMod,
+        },
         test_utils::{get_test_file, make_test_dir},
+        toggle::generate_config,
     };
 
     use super::*;
@@ -108,7 +208,7 @@ mod tests {
         let mut config = Config::default(None).unwrap();
         config.owml_path = dir.path().join("").to_str().unwrap().to_string();
         let db = LocalDatabase::default();
-        install_mod_from_zip(&test_path, &config, &db).unwrap();
+        install_mod_from_file(&test_path, &config, &db).unwrap();
         let db = LocalDatabase::fetch(&config.owml_path).unwrap();
         let new_mod = db.get_mod("Bwc9876.TimeSaver").unwrap();
         remove_mod(new_mod, &db, false).unwrap();
@@ -124,8 +224,14 @@ mod tests {
         let mut config = Config::default(None).unwrap();
         config.owml_path = dir.path().join("").to_str().unwrap().to_string();
         let db = LocalDatabase::default();
-        install_mod_from_zip(&test_path, &config, &db).unwrap();
-        install_mod_from_zip(&test_path_2, &config, &db).unwrap();
+        install_mod_from_file(&test_path, &config, &db).unwrap();
+        install_mod_from_file(&test_path_2, &config, &db).unwrap();
+        let mods_path = dir.path().join("Mods");
+        write_install_reason(
+            &mods_path.join("Bwc9876.SaveEditor"),
+            InstallReason::Dependency,
+        )
+        .unwrap();
         let db = LocalDatabase::fetch(&config.owml_path).unwrap();
         let mut new_mod = db.get_mod("Bwc9876.TimeSaver").unwrap().clone();
         new_mod.manifest.dependencies = Some(vec!["Bwc9876.SaveEditor".to_string()]);
@@ -135,6 +241,147 @@ mod tests {
         dir.close().unwrap();
     }
 
+    fn make_mod(owml_path: &Path, unique_name: &str, deps: &[&str], reason: InstallReason) {
+        let mod_path = owml_path.join("Mods").join(unique_name);
+        create_dir_all(&mod_path).unwrap();
+        let manifest = serde_json::json!({
+            "uniqueName": unique_name,
+            "name": unique_name,
+            "author": "Test",
+            "version": "1.0.0",
+            "dependencies": deps,
+        });
+        std::fs::write(mod_path.join("manifest.json"), manifest.to_string()).unwrap();
+        generate_config(&mod_path.join("config.json")).unwrap();
+        write_install_reason(&mod_path, reason).unwrap();
+    }
+
+    #[test]
+    fn test_remove_mod_recursive_keeps_needed() {
+        let dir = make_test_dir();
+        make_mod(
+            dir.path(),
+            "Test.A",
+            &["Test.Shared", "Test.OnlyA"],
+            InstallReason::Explicit,
+        );
+        make_mod(
+            dir.path(),
+            "Test.B",
+            &["Test.Shared"],
+            InstallReason::Explicit,
+        );
+        make_mod(dir.path(), "Test.Shared", &[], InstallReason::Dependency);
+        make_mod(
+            dir.path(),
+            "Test.OnlyA",
+            &["Test.Nested"],
+            InstallReason::Dependency,
+        );
+        make_mod(dir.path(), "Test.Nested", &[], InstallReason::Dependency);
+        let db = LocalDatabase::fetch(dir.path().to_str().unwrap()).unwrap();
+        remove_mod(db.get_mod("Test.A").unwrap(), &db, true).unwrap();
+        let mods_path = dir.path().join("Mods");
+        assert!(!mods_path.join("Test.A").is_dir());
+        assert!(!mods_path.join("Test.OnlyA").is_dir());
+        assert!(!mods_path.join("Test.Nested").is_dir());
+        assert!(mods_path.join("Test.Shared").is_dir());
+        assert!(mods_path.join("Test.B").is_dir());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_remove_mod_recursive_keeps_explicit() {
+        let dir = make_test_dir();
+        make_mod(
+            dir.path(),
+            "Test.A",
+            &["Test.Explicit", "Test.Dep"],
+            InstallReason::Explicit,
+        );
+        make_mod(dir.path(), "Test.Explicit", &[], InstallReason::Explicit);
+        make_mod(dir.path(), "Test.Dep", &[], InstallReason::Dependency);
+        let db = LocalDatabase::fetch(dir.path().to_str().unwrap()).unwrap();
+        let unneeded = get_unneeded_deps(db.get_mod("Test.A").unwrap(), &db)
+            .into_iter()
+            .map(|m| m.manifest.unique_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(unneeded, vec!["Test.Dep"]);
+        remove_mod(db.get_mod("Test.A").unwrap(), &db, true).unwrap();
+        let mods_path = dir.path().join("Mods");
+        assert!(!mods_path.join("Test.Dep").is_dir());
+        assert!(mods_path.join("Test.Explicit").is_dir());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_remove_mod_recursive_shared_between_removed() {
+        let dir = make_test_dir();
+        // Test.Shared is needed by Test.A and Test.B, but both are going
+        make_mod(
+            dir.path(),
+            "Test.A",
+            &["Test.Shared", "Test.B"],
+            InstallReason::Explicit,
+        );
+        make_mod(
+            dir.path(),
+            "Test.B",
+            &["Test.Shared"],
+            InstallReason::Dependency,
+        );
+        make_mod(dir.path(), "Test.Shared", &[], InstallReason::Dependency);
+        let db = LocalDatabase::fetch(dir.path().to_str().unwrap()).unwrap();
+        remove_mod(db.get_mod("Test.A").unwrap(), &db, true).unwrap();
+        let db = LocalDatabase::fetch(dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(db.valid().count(), 0);
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_get_orphaned_mods() {
+        let dir = make_test_dir();
+        make_mod(
+            dir.path(),
+            "Test.App",
+            &["Test.Needed"],
+            InstallReason::Explicit,
+        );
+        make_mod(dir.path(), "Test.Needed", &[], InstallReason::Dependency);
+        make_mod(
+            dir.path(),
+            "Test.Orphan",
+            &["Test.OrphanDep"],
+            InstallReason::Dependency,
+        );
+        make_mod(dir.path(), "Test.OrphanDep", &[], InstallReason::Dependency);
+        make_mod(dir.path(), "Test.Unused", &[], InstallReason::Explicit);
+        let db = LocalDatabase::fetch(dir.path().to_str().unwrap()).unwrap();
+        let orphans = get_orphaned_mods(&db, None)
+            .into_iter()
+            .map(|m| m.manifest.unique_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(orphans, vec!["Test.Orphan", "Test.OrphanDep"]);
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_get_orphaned_mods_keeps_parents() {
+        let dir = make_test_dir();
+        make_mod(dir.path(), "Test.Addon", &[], InstallReason::Explicit);
+        make_mod(dir.path(), "Test.Parent", &[], InstallReason::Dependency);
+        let db = LocalDatabase::fetch(dir.path().to_str().unwrap()).unwrap();
+        let mut addon = RemoteMod::get_test(1);
+        addon.unique_name = "Test.Addon".to_string();
+        addon.parent = Some("Test.Parent".to_string());
+        let remote_db = RemoteDatabase {
+            mods: HashMap::from([(addon.unique_name.clone(), addon)]),
+        };
+        assert!(get_orphaned_mods(&db, Some(&remote_db)).is_empty());
+        assert_eq!(get_orphaned_mods(&db, None).len(), 1);
+        dir.close().unwrap();
+    }
+
     #[test]
     fn test_remove_old_mod_files() {
         let dir = make_test_dir();
@@ -143,7 +390,7 @@ mod tests {
         let mut config = Config::default(None).unwrap();
         config.owml_path = dir.path().to_str().unwrap().to_string();
         let db = LocalDatabase::default();
-        let new_mod = install_mod_from_zip(&test_path, &config, &db).unwrap();
+        let new_mod = install_mod_from_file(&test_path, &config, &db).unwrap();
         remove_old_mod_files(&new_mod).unwrap();
         assert!(target_path.is_dir());
         assert!(!target_path.join("TimeSaver.dll").is_file());
@@ -162,7 +409,7 @@ mod tests {
         let mut config = Config::default(None).unwrap();
         config.owml_path = dir.path().to_str().unwrap().to_string();
         let db = LocalDatabase::default();
-        let new_mod = install_mod_from_zip(&test_path, &config, &db).unwrap();
+        let new_mod = install_mod_from_file(&test_path, &config, &db).unwrap();
         create_all_parents(&unimportant_path).unwrap();
         let mut file = File::create(&unimportant_path).unwrap();
         write!(file, "{{}}").unwrap();
@@ -183,7 +430,7 @@ mod tests {
         let mut config = Config::default(None).unwrap();
         config.owml_path = dir.path().join("").to_str().unwrap().to_string();
         let db = LocalDatabase::default();
-        let mut new_mod = install_mod_from_zip(&test_path, &config, &db).unwrap();
+        let mut new_mod = install_mod_from_file(&test_path, &config, &db).unwrap();
         new_mod.manifest.paths_to_preserve = Some(vec!["important.json".to_string()]);
         let mut file = File::create(&important_path).unwrap();
         write!(file, "{{}}").unwrap();
@@ -205,7 +452,7 @@ mod tests {
         let mut config = Config::default(None).unwrap();
         config.owml_path = dir.path().join("").to_str().unwrap().to_string();
         let db = LocalDatabase::default();
-        let mut new_mod = install_mod_from_zip(&test_path, &config, &db).unwrap();
+        let mut new_mod = install_mod_from_file(&test_path, &config, &db).unwrap();
         new_mod.manifest.paths_to_preserve = Some(vec!["ImportantFolder".to_string()]);
         create_all_parents(&important_path).unwrap();
         let mut file = File::create(&important_path).unwrap();
@@ -227,8 +474,14 @@ mod tests {
         let mut config = Config::default(None).unwrap();
         config.owml_path = dir.path().join("").to_str().unwrap().to_string();
         let db = LocalDatabase::default();
-        install_mod_from_zip(&test_path, &config, &db).unwrap();
-        install_mod_from_zip(&test_path_2, &config, &db).unwrap();
+        install_mod_from_file(&test_path, &config, &db).unwrap();
+        install_mod_from_file(&test_path_2, &config, &db).unwrap();
+        let mods_path = dir.path().join("Mods");
+        write_install_reason(
+            &mods_path.join("Bwc9876.SaveEditor"),
+            InstallReason::Dependency,
+        )
+        .unwrap();
         let mut db = LocalDatabase::fetch(&config.owml_path).unwrap();
         let mut new_mod = db.get_mod("Bwc9876.TimeSaver").unwrap().clone();
         new_mod.manifest.dependencies = Some(vec!["Bwc9876.SaveEditor".to_string()]);
@@ -1,12 +1,34 @@
+use std::cmp::Reverse;
+
 use rust_fuzzy_search::fuzzy_compare;
 
 const SEARCH_THRESHOLD: f32 = 0.08;
 
+/// Something that can be fuzzy searched
 pub trait Searchable {
+    /// Get the values to score against the query, in order of importance. These should be lowercase.
     fn get_values(&self) -> Vec<String>;
 }
 
-pub fn search_list<'a, T>(source_list: Vec<&'a T>, filter: &str) -> Vec<&'a T>
+fn score_values(values: Vec<String>, filter: &str) -> f32 {
+    values
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let weight = 1.0 - (index as f32 / 10.0 * 2.0);
+            let mut score = fuzzy_compare(field, filter);
+            if field == filter {
+                score += 10.0;
+            } else if field.contains(filter) {
+                score += 1.0;
+            }
+            score *= weight;
+            score
+        })
+        .sum()
+}
+
+fn score_list<'a, T>(source_list: Vec<&'a T>, filter: &str) -> Vec<(&'a T, f32)>
 where
     T: Searchable,
 {
@@ -14,22 +36,7 @@ where
     let mut scores: Vec<(&T, f32)> = source_list
         .into_iter()
         .filter_map(|m| {
-            let final_score = m
-                .get_values()
-                .iter()
-                .enumerate()
-                .map(|(index, field)| {
-                    let weight = 1.0 - (index as f32 / 10.0 * 2.0);
-                    let mut score = fuzzy_compare(field, &filter);
-                    if field == &filter {
-                        score += 10.0;
-                    } else if field.contains(&filter) {
-                        score += 1.0;
-                    }
-                    score *= weight;
-                    score
-                })
-                .sum();
+            let final_score = score_values(m.get_values(), &filter);
             if final_score >= SEARCH_THRESHOLD {
                 Some((m, final_score))
             } else {
@@ -38,5 +45,277 @@ where
         })
         .collect();
     scores.sort_by(|(_, a), (_, b)| a.total_cmp(b).reverse());
-    scores.iter().map(|(m, _)| *m).collect()
+    scores
+}
+
+/// Fuzzy search a list of items, items that don't match closely enough are removed.
+///
+/// ## Returns
+///
+/// The items that match, best match first.
+///
+pub fn search_list<'a, T>(source_list: Vec<&'a T>, filter: &str) -> Vec<&'a T>
+where
+    T: Searchable,
+{
+    score_list(source_list, filter)
+        .into_iter()
+        .map(|(m, _)| m)
+        .collect()
+}
+
+/// How to sort the results of a [SearchQuery]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchSort {
+    /// Best match to the text of the query first
+    Relevance,
+    /// Most downloaded first
+    Downloads,
+    /// Alphabetically by name
+    Name,
+    /// Alphabetically by author
+    Author,
+}
+
+impl SearchSort {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "relevance" => Some(Self::Relevance),
+            "downloads" => Some(Self::Downloads),
+            "name" => Some(Self::Name),
+            "author" => Some(Self::Author),
+            _ => None,
+        }
+    }
+}
+
+/// Something a mod can have, used with `has:`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchFlag {
+    /// The mod has a prerelease available
+    Prerelease,
+    /// The mod is an addon of another mod
+    Parent,
+    /// The mod has an update available
+    Update,
+}
+
+impl SearchFlag {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "prerelease" => Some(Self::Prerelease),
+            "parent" => Some(Self::Parent),
+            "update" => Some(Self::Update),
+            _ => None,
+        }
+    }
+}
+
+/// A search query parsed from a string like `tag:story author:xen sort:downloads has:prerelease -installed`.
+///
+/// Supported terms (any term can be negated by prefixing it with `-`, except `sort:`):
+/// - `tag:<tag>`: The mod has the given tag
+/// - `author:<name>`: The mod's author contains the given name
+/// - `has:prerelease|parent|update`: The mod has a prerelease, is an addon, or has an update available
+/// - `installed`: The mod is installed
+/// - `enabled`: The mod is installed and enabled
+/// - `sort:relevance|downloads|name|author`: How to sort the results
+///
+/// Anything else is used as text to fuzzy search for.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    /// Text to fuzzy search for
+    pub text: String,
+    /// Tags the mod needs to have (`true`) or not have (`false`)
+    pub tags: Vec<(String, bool)>,
+    /// Names the mod's author needs to contain (`true`) or not contain (`false`)
+    pub authors: Vec<(String, bool)>,
+    /// Flags the mod needs to have (`true`) or not have (`false`)
+    pub flags: Vec<(SearchFlag, bool)>,
+    /// Whether the mod needs to be installed or not installed
+    pub installed: Option<bool>,
+    /// Whether the mod needs to be enabled or disabled
+    pub enabled: Option<bool>,
+    /// How to sort the results, if not given results are sorted by relevance if there's text
+    pub sort: Option<SearchSort>,
+}
+
+impl SearchQuery {
+    /// Parse a query string, unknown terms are treated as text so this never fails
+    ///
+    /// ## Returns
+    ///
+    /// The parsed query
+    ///
+    pub fn parse(query: &str) -> Self {
+        let mut parsed = Self::default();
+        let mut text: Vec<&str> = vec![];
+        for term in query.split_whitespace() {
+            let (negated, stripped) = match term.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, term),
+            };
+            let lower = stripped.to_ascii_lowercase();
+            let known = match lower.split_once(':') {
+                Some(("tag", value)) if !value.is_empty() => {
+                    parsed.tags.push((value.to_string(), !negated));
+                    true
+                }
+                Some(("author", value)) if !value.is_empty() => {
+                    parsed.authors.push((value.to_string(), !negated));
+                    true
+                }
+                Some(("has", value)) => SearchFlag::parse(value)
+                    .map(|flag| parsed.flags.push((flag, !negated)))
+                    .is_some(),
+                Some(("sort", value)) if !negated => SearchSort::parse(value)
+                    .map(|sort| parsed.sort = Some(sort))
+                    .is_some(),
+                None if lower == "installed" => {
+                    parsed.installed = Some(!negated);
+                    true
+                }
+                None if lower == "enabled" => {
+                    parsed.enabled = Some(!negated);
+                    true
+                }
+                _ => false,
+            };
+            if !known {
+                text.push(term);
+            }
+        }
+        parsed.text = text.join(" ");
+        parsed
+    }
+
+    /// Whether this query doesn't filter or sort anything
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn matches(&self, info: &QueryInfo) -> bool {
+        let tags_match = self
+            .tags
+            .iter()
+            .all(|(tag, wanted)| info.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) == *wanted);
+        let authors_match = self.authors.iter().all(|(author, wanted)| {
+            info.authors
+                .iter()
+                .any(|a| a.to_ascii_lowercase().contains(author))
+                == *wanted
+        });
+        let flags_match = self.flags.iter().all(|(flag, wanted)| {
+            let has = match flag {
+                SearchFlag::Prerelease => info.has_prerelease,
+                SearchFlag::Parent => info.has_parent,
+                SearchFlag::Update => info.has_update,
+            };
+            has == *wanted
+        });
+        let installed_matches = self.installed.iter().all(|i| info.installed == *i);
+        let enabled_matches = self
+            .enabled
+            .iter()
+            .all(|e| info.installed && info.enabled == *e);
+        tags_match && authors_match && flags_match && installed_matches && enabled_matches
+    }
+}
+
+/// Info about a mod that a [SearchQuery] can filter and sort by.
+/// This is gathered from whichever databases are available, so a local mod can be filtered by tags from the remote database and vice versa.
+#[derive(Default)]
+pub struct QueryInfo<'a> {
+    /// The display name of the mod
+    pub name: &'a str,
+    /// The author(s) of the mod
+    pub authors: Vec<&'a str>,
+    /// The tags of the mod
+    pub tags: &'a [String],
+    /// How many times the mod has been downloaded
+    pub download_count: u32,
+    /// Whether the mod has a prerelease
+    pub has_prerelease: bool,
+    /// Whether the mod is an addon of another mod
+    pub has_parent: bool,
+    /// Whether the mod has an update available
+    pub has_update: bool,
+    /// Whether the mod is installed
+    pub installed: bool,
+    /// Whether the mod is enabled
+    pub enabled: bool,
+}
+
+/// Filter and sort a list of items with a [SearchQuery].
+/// If the query has no text or sort the items stay in the order they were given in.
+///
+/// ## Returns
+///
+/// The items that match the query, in the order specified by the query.
+///
+pub fn query_list<'a, T, F>(source_list: Vec<&'a T>, query: &SearchQuery, get_info: F) -> Vec<&'a T>
+where
+    T: Searchable,
+    F: Fn(&'a T) -> QueryInfo<'a>,
+{
+    let scored: Vec<(&T, f32)> = if query.text.is_empty() {
+        source_list.into_iter().map(|m| (m, 0.0)).collect()
+    } else {
+        score_list(source_list, &query.text)
+    };
+    let mut results: Vec<(&T, QueryInfo)> = scored
+        .into_iter()
+        .map(|(m, _)| (m, get_info(m)))
+        .filter(|(_, info)| query.matches(info))
+        .collect();
+    // Results from score_list are already sorted by relevance, and sort_by is stable
+    match query.sort {
+        Some(SearchSort::Downloads) => {
+            results.sort_by_key(|(_, info)| Reverse(info.download_count))
+        }
+        Some(SearchSort::Name) => results.sort_by(|(_, a), (_, b)| {
+            a.name
+                .to_ascii_lowercase()
+                .cmp(&b.name.to_ascii_lowercase())
+        }),
+        Some(SearchSort::Author) => results.sort_by(|(_, a), (_, b)| {
+            let a = a.authors.first().map(|a| a.to_ascii_lowercase());
+            let b = b.authors.first().map(|b| b.to_ascii_lowercase());
+            a.cmp(&b)
+        }),
+        Some(SearchSort::Relevance) | None => {}
+    }
+    results.into_iter().map(|(m, _)| m).collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        let query = SearchQuery::parse(
+            "time tag:Story author:xen sort:downloads has:prerelease -has:parent -installed saver",
+        );
+        assert_eq!(query.text, "time saver");
+        assert_eq!(query.tags, vec![("story".to_string(), true)]);
+        assert_eq!(query.authors, vec![("xen".to_string(), true)]);
+        assert_eq!(
+            query.flags,
+            vec![(SearchFlag::Prerelease, true), (SearchFlag::Parent, false)]
+        );
+        assert_eq!(query.installed, Some(false));
+        assert_eq!(query.sort, Some(SearchSort::Downloads));
+    }
+
+    #[test]
+    fn test_parse_query_unknown_terms() {
+        let query = SearchQuery::parse("sort:bogus tag: - -sort:name");
+        assert_eq!(query.text, "sort:bogus tag: - -sort:name");
+        assert!(query.tags.is_empty());
+        assert!(query.sort.is_none());
+        assert!(SearchQuery::parse("  ").is_empty());
+    }
 }
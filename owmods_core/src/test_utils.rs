@@ -0,0 +1,222 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Cursor, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use tempfile::TempDir;
+use zip::{write::FileOptions, ZipWriter};
+
+use crate::{
+    config::Config,
+    constants::{OWML_DEFAULT_CONFIG_NAME, OWML_EXE_NAME, OWML_MANIFEST_NAME},
+};
+
+pub fn make_test_dir() -> TempDir {
+    TempDir::new().unwrap()
+}
+
+pub fn get_test_file(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("test_files")
+        .join(path)
+}
+
+/// Something to go wrong when a path on a [MockServer] is requested
+#[derive(Clone, Copy, Debug)]
+pub enum MockFailure {
+    /// Respond with this status code and an empty body
+    Status(u16),
+    /// Send the full `Content-Length` but only half of the body, then close the connection
+    Truncated,
+    /// Wait this long before responding normally
+    Slow(Duration),
+}
+
+type Shared<T> = Arc<Mutex<T>>;
+
+/// An HTTP server on localhost that serves the fixtures in `test_files/mock` and the test zips, so tests don't need the network.
+///
+/// - `/database.json`: A database with OWML, TimeSaver and SaveEditor that point back at this server
+/// - `/alert.json`: An enabled alert
+/// - `/repos/Bwc9876/OW-TimeSaver/releases`: GitHub releases for TimeSaver, for use as the GitHub API URL
+/// - `/OWML.zip`: A fake OWML install that passes [Config::check_owml]
+/// - `/Bwc9876.TimeSaver.zip`, `/Bwc9876.SaveEditor.zip`: The test zips
+///
+/// The server runs until the test process exits.
+pub struct MockServer {
+    port: u16,
+    failures: Shared<HashMap<String, MockFailure>>,
+    requests: Shared<Vec<String>>,
+}
+
+impl MockServer {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let base = format!("http://127.0.0.1:{port}");
+        let mut files: HashMap<String, Vec<u8>> = HashMap::new();
+        let database = fs::read_to_string(get_test_file("mock/database.json")).unwrap();
+        files.insert(
+            "database.json".to_string(),
+            database.replace("$base$", &base).into_bytes(),
+        );
+        files.insert(
+            "alert.json".to_string(),
+            fs::read(get_test_file("mock/alert.json")).unwrap(),
+        );
+        files.insert(
+            "repos/Bwc9876/OW-TimeSaver/releases".to_string(),
+            fs::read(get_test_file("mock/releases.json")).unwrap(),
+        );
+        for name in ["Bwc9876.TimeSaver.zip", "Bwc9876.SaveEditor.zip"] {
+            files.insert(name.to_string(), fs::read(get_test_file(name)).unwrap());
+        }
+        files.insert("OWML.zip".to_string(), make_owml_zip());
+
+        let server = Self {
+            port,
+            failures: Arc::new(Mutex::new(HashMap::new())),
+            requests: Arc::new(Mutex::new(vec![])),
+        };
+        let files = Arc::new(files);
+        let failures = server.failures.clone();
+        let requests = server.requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let files = files.clone();
+                let failures = failures.clone();
+                let requests = requests.clone();
+                thread::spawn(move || {
+                    handle_connection(stream, &files, &failures, &requests).ok();
+                });
+            }
+        });
+        server
+    }
+
+    /// Get the full URL of a file on the server
+    pub fn url(&self, path: &str) -> String {
+        format!("http://127.0.0.1:{}/{}", self.port, path)
+    }
+
+    pub fn database_url(&self) -> String {
+        self.url("database.json")
+    }
+
+    pub fn alert_url(&self) -> String {
+        self.url("alert.json")
+    }
+
+    /// Get the URL to use in place of the GitHub API
+    pub fn github_api_url(&self) -> String {
+        format!("http://127.0.0.1:{}", self.port)
+    }
+
+    /// Make a config that uses this server and installs to `dir`
+    pub fn config(&self, dir: &Path) -> Config {
+        let mut config = Config::default(Some(dir.join("settings.json"))).unwrap();
+        config.owml_path = dir.to_str().unwrap().to_string();
+        config.database_url = self.database_url();
+        config.alert_url = self.alert_url();
+        config
+    }
+
+    /// Make requests for `path` fail from now on
+    pub fn fail(&self, path: &str, failure: MockFailure) {
+        self.failures
+            .lock()
+            .unwrap()
+            .insert(path.to_string(), failure);
+    }
+
+    /// How many times `path` has been requested
+    pub fn request_count(&self, path: &str) -> usize {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|p| *p == path)
+            .count()
+    }
+}
+
+fn make_owml_zip() -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(vec![]));
+    let files = [
+        (
+            OWML_MANIFEST_NAME,
+            fs::read(get_test_file("OWML.Manifest.json")).unwrap(),
+        ),
+        (
+            OWML_DEFAULT_CONFIG_NAME,
+            fs::read(get_test_file("OWML.Config.json")).unwrap(),
+        ),
+        (OWML_EXE_NAME, vec![]),
+    ];
+    for (name, contents) in files {
+        zip.start_file(name, FileOptions::default()).unwrap();
+        zip.write_all(&contents).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+fn write_head(stream: &mut TcpStream, status: u16, len: usize) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status} Mock\r\nContent-Length: {len}\r\nConnection: close\r\n\r\n"
+    )
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    files: &HashMap<String, Vec<u8>>,
+    failures: &Shared<HashMap<String, MockFailure>>,
+    requests: &Shared<Vec<String>>,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+    let head_only = request_line.starts_with("HEAD ");
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let path = path
+        .split('?')
+        .next()
+        .unwrap_or(path)
+        .trim_start_matches('/');
+    requests.lock().unwrap().push(path.to_string());
+    let failure = failures.lock().unwrap().get(path).copied();
+
+    let Some(body) = files.get(path) else {
+        return write_head(&mut stream, 404, 0);
+    };
+    match failure {
+        Some(MockFailure::Status(status)) => write_head(&mut stream, status, 0),
+        Some(MockFailure::Truncated) => {
+            write_head(&mut stream, 200, body.len())?;
+            stream.write_all(&body[..body.len() / 2])?;
+            stream.flush()
+        }
+        Some(MockFailure::Slow(delay)) => {
+            thread::sleep(delay);
+            write_head(&mut stream, 200, body.len())?;
+            stream.write_all(body)
+        }
+        None if head_only => write_head(&mut stream, 200, body.len()),
+        None => {
+            write_head(&mut stream, 200, body.len())?;
+            stream.write_all(body)
+        }
+    }
+}
@@ -137,7 +137,7 @@ mod tests {
 
     use crate::{
         config::Config,
-        download::install_mod_from_zip,
+        download::install_mod_from_file,
         mods::local::{LocalMod, UnsafeLocalMod},
         test_utils::{get_test_file, make_test_dir},
     };
@@ -150,7 +150,7 @@ mod tests {
         let db = LocalDatabase::default();
         let mut config = Config::default(None).unwrap();
         config.owml_path = dir.path().to_str().unwrap().to_string();
-        let new_mod = install_mod_from_zip(&test_zip, &config, &db).unwrap();
+        let new_mod = install_mod_from_file(&test_zip, &config, &db).unwrap();
         let db = LocalDatabase::fetch(&config.owml_path).unwrap();
         (dir, db, new_mod)
     }
@@ -177,8 +177,8 @@ mod tests {
         let mut config = Config::default(None).unwrap();
         config.owml_path = dir.path().join("").to_str().unwrap().to_string();
         let db = LocalDatabase::default();
-        install_mod_from_zip(&test_path, &config, &db).unwrap();
-        install_mod_from_zip(&test_path_2, &config, &db).unwrap();
+        install_mod_from_file(&test_path, &config, &db).unwrap();
+        install_mod_from_file(&test_path_2, &config, &db).unwrap();
         let mut db = LocalDatabase::fetch(&config.owml_path).unwrap();
         let mut new_mod = db.get_mod("Bwc9876.TimeSaver").unwrap().clone();
         new_mod.manifest.dependencies = Some(vec!["Bwc9876.SaveEditor".to_string()]);
@@ -200,8 +200,8 @@ mod tests {
         let mut config = Config::default(None).unwrap();
         config.owml_path = dir.path().join("").to_str().unwrap().to_string();
         let db = LocalDatabase::default();
-        install_mod_from_zip(&test_path, &config, &db).unwrap();
-        install_mod_from_zip(&test_path_2, &config, &db).unwrap();
+        install_mod_from_file(&test_path, &config, &db).unwrap();
+        install_mod_from_file(&test_path_2, &config, &db).unwrap();
         let mut db = LocalDatabase::fetch(&config.owml_path).unwrap();
         let mut new_mod = db.get_mod("Bwc9876.TimeSaver").unwrap().clone();
         new_mod.manifest.dependencies = Some(vec!["Bwc9876.SaveEditor".to_string()]);
@@ -229,8 +229,8 @@ mod tests {
         let mut config = Config::default(None).unwrap();
         config.owml_path = dir.path().join("").to_str().unwrap().to_string();
         let db = LocalDatabase::default();
-        install_mod_from_zip(&test_path, &config, &db).unwrap();
-        install_mod_from_zip(&test_path_2, &config, &db).unwrap();
+        install_mod_from_file(&test_path, &config, &db).unwrap();
+        install_mod_from_file(&test_path_2, &config, &db).unwrap();
         let mut db = LocalDatabase::fetch(&config.owml_path).unwrap();
         let mut new_mod = db.get_mod("Bwc9876.TimeSaver").unwrap().clone();
         new_mod.manifest.dependencies = Some(vec!["Bwc9876.SaveEditor".to_string()]);
@@ -1,14 +1,26 @@
-use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use futures::{stream::FuturesUnordered, StreamExt};
 use log::info;
-use version_compare::Cmp;
+use serde::{Deserialize, Serialize};
+use tempfile::TempDir;
+use typeshare::typeshare;
 
 use crate::{
     analytics::{send_analytics_event, AnalyticsEventName},
     config::Config,
     constants::OWML_UNIQUE_NAME,
     db::{LocalDatabase, RemoteDatabase},
-    download::{download_and_install_owml, install_mods_parallel},
-    mods::{local::LocalMod, remote::RemoteMod},
+    download::{
+        download_and_install_owml, download_file, get_end_of_url, get_manifest_from_file,
+        install_mod_from_db, install_mod_from_file,
+    },
+    mods::{
+        local::{write_install_reason, InstallReason, LocalMod, ModManifest},
+        remote::RemoteMod,
+    },
+    version::is_newer,
 };
 
 /// Check a given local mod against the remote database to see if there's an update.
@@ -30,10 +42,13 @@ pub fn check_mod_needs_update<'a>(
         remote_db.get_mod(&local_mod.manifest.unique_name)
     };
     if let Some(remote_mod) = remote_mod {
+        // Updating a linked mod would overwrite the author's working copy
+        if local_mod.linked {
+            return (false, Some(remote_mod));
+        }
+        let (version, _) = remote_mod.get_channel_release(local_mod.channel);
         (
-            version_compare::compare(&remote_mod.version, &local_mod.manifest.version)
-                .unwrap_or(Cmp::Eq)
-                == Cmp::Gt,
+            is_newer(version, &local_mod.manifest.version),
             Some(remote_mod),
         )
     } else {
@@ -41,6 +56,319 @@ pub fn check_mod_needs_update<'a>(
     }
 }
 
+/// How a list in a mod's manifest changed in an update
+#[typeshare]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ListDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl ListDiff {
+    fn new(old: &Option<Vec<String>>, new: &Option<Vec<String>>) -> Self {
+        let old = old.as_deref().unwrap_or_default();
+        let new = new.as_deref().unwrap_or_default();
+        Self {
+            added: new.iter().filter(|i| !old.contains(i)).cloned().collect(),
+            removed: old.iter().filter(|i| !new.contains(i)).cloned().collect(),
+        }
+    }
+
+    /// Whether nothing was added or removed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// How a value in a mod's manifest changed in an update
+#[typeshare]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueDiff {
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl ValueDiff {
+    fn new(old: &Option<String>, new: &Option<String>) -> Option<Self> {
+        (old != new).then(|| Self {
+            old: old.clone(),
+            new: new.clone(),
+        })
+    }
+}
+
+/// What updating a mod will change besides its files, found by comparing the installed manifest to the one in the downloaded update
+#[typeshare]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateImpact {
+    pub unique_name: String,
+    pub name: String,
+    pub old_version: String,
+    pub new_version: String,
+    pub dependencies: ListDiff,
+    pub conflicts: ListDiff,
+    pub paths_to_preserve: ListDiff,
+    pub owml_version: Option<ValueDiff>,
+    pub patcher: Option<ValueDiff>,
+    /// New dependencies that aren't installed, these are installed along with the update
+    pub missing_dependencies: Vec<String>,
+    /// Enabled mods that the update adds a conflict with
+    pub new_conflicts: Vec<String>,
+}
+
+impl UpdateImpact {
+    /// Compare an installed mod to the manifest of its update
+    pub fn new(local_mod: &LocalMod, new_manifest: &ModManifest, local_db: &LocalDatabase) -> Self {
+        let old_manifest = &local_mod.manifest;
+        let dependencies = ListDiff::new(&old_manifest.dependencies, &new_manifest.dependencies);
+        let conflicts = ListDiff::new(&old_manifest.conflicts, &new_manifest.conflicts);
+        let missing_dependencies = dependencies
+            .added
+            .iter()
+            .filter(|d| local_db.get_mod(d).is_none())
+            .cloned()
+            .collect();
+        let new_conflicts = conflicts
+            .added
+            .iter()
+            .filter(|c| local_db.get_mod(c).is_some_and(|m| m.enabled))
+            .cloned()
+            .collect();
+        Self {
+            unique_name: old_manifest.unique_name.clone(),
+            name: new_manifest.name.clone(),
+            old_version: old_manifest.version.clone(),
+            new_version: new_manifest.version.clone(),
+            paths_to_preserve: ListDiff::new(
+                &old_manifest.paths_to_preserve,
+                &new_manifest.paths_to_preserve,
+            ),
+            owml_version: ValueDiff::new(&old_manifest.owml_version, &new_manifest.owml_version),
+            patcher: ValueDiff::new(&old_manifest.patcher, &new_manifest.patcher),
+            dependencies,
+            conflicts,
+            missing_dependencies,
+            new_conflicts,
+        }
+    }
+
+    /// Whether the user should confirm the update, because it installs new mods, conflicts with enabled ones, or changes its pre-patcher
+    pub fn needs_confirmation(&self) -> bool {
+        !self.missing_dependencies.is_empty()
+            || !self.new_conflicts.is_empty()
+            || self.patcher.is_some()
+    }
+
+    /// Describe everything that changes, for showing to the user
+    ///
+    /// ## Returns
+    ///
+    /// One line per change, empty if only the version changed
+    ///
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = vec![];
+        for dep in self.dependencies.added.iter() {
+            if self.missing_dependencies.contains(dep) {
+                lines.push(format!("Adds dependency {dep}, which will be installed"));
+            } else {
+                lines.push(format!("Adds dependency {dep}"));
+            }
+        }
+        for dep in self.dependencies.removed.iter() {
+            lines.push(format!("No longer depends on {dep}"));
+        }
+        for conflict in self.conflicts.added.iter() {
+            if self.new_conflicts.contains(conflict) {
+                lines.push(format!("Now conflicts with {conflict}, which is enabled"));
+            } else {
+                lines.push(format!("Now conflicts with {conflict}"));
+            }
+        }
+        for conflict in self.conflicts.removed.iter() {
+            lines.push(format!("No longer conflicts with {conflict}"));
+        }
+        if let Some(owml_version) = &self.owml_version {
+            lines.push(format!(
+                "Expects OWML {} instead of {}",
+                owml_version.new.as_deref().unwrap_or("(any)"),
+                owml_version.old.as_deref().unwrap_or("(any)")
+            ));
+        }
+        if let Some(patcher) = &self.patcher {
+            lines.push(match (&patcher.old, &patcher.new) {
+                (None, Some(new)) => format!("Starts using a pre-patcher ({new})"),
+                (Some(_), None) => "Stops using a pre-patcher".to_string(),
+                (_, new) => format!(
+                    "Changes its pre-patcher to {}",
+                    new.as_deref().unwrap_or_default()
+                ),
+            });
+        }
+        if !self.paths_to_preserve.added.is_empty() {
+            lines.push(format!(
+                "Keeps {} when updating",
+                self.paths_to_preserve.added.join(", ")
+            ));
+        }
+        if !self.paths_to_preserve.removed.is_empty() {
+            lines.push(format!(
+                "No longer keeps {} when updating",
+                self.paths_to_preserve.removed.join(", ")
+            ));
+        }
+        lines
+    }
+}
+
+/// Updates that have been downloaded but not installed yet, so what they change can be checked before anything is touched.
+/// Dropping the plan without applying it discards the downloads.
+pub struct UpdatePlan {
+    pub impacts: Vec<UpdateImpact>,
+    downloads: Vec<PathBuf>,
+    _temp_dir: TempDir,
+}
+
+impl UpdatePlan {
+    /// Download the updates for the given installed mods and compare their manifests to the installed ones
+    ///
+    /// ## Errors
+    ///
+    /// - If one of the mods isn't installed or isn't in the database
+    /// - If we can't download an update or read the manifest in it
+    ///
+    pub async fn new(
+        unique_names: &[String],
+        local_db: &LocalDatabase,
+        remote_db: &RemoteDatabase,
+    ) -> Result<Self> {
+        let temp_dir = TempDir::new()?;
+        let mut set = FuturesUnordered::new();
+        for unique_name in unique_names {
+            let local_mod = local_db
+                .get_mod(unique_name)
+                .ok_or_else(|| anyhow!("Mod {} is not installed", unique_name))?;
+            let remote_mod = remote_db
+                .get_mod(unique_name)
+                .ok_or_else(|| anyhow!("Mod {} not found in database.", unique_name))?;
+            let download_dir = temp_dir.path().join(unique_name);
+            set.push(Self::download(
+                local_mod,
+                remote_mod,
+                download_dir,
+                local_db,
+            ));
+        }
+        let mut impacts = vec![];
+        let mut downloads = vec![];
+        while let Some(res) = set.next().await {
+            let (impact, path) = res?;
+            impacts.push(impact);
+            downloads.push(path);
+        }
+        impacts.sort_by(|a, b| a.unique_name.cmp(&b.unique_name));
+        Ok(Self {
+            impacts,
+            downloads,
+            _temp_dir: temp_dir,
+        })
+    }
+
+    async fn download(
+        local_mod: &LocalMod,
+        remote_mod: &RemoteMod,
+        download_dir: PathBuf,
+        local_db: &LocalDatabase,
+    ) -> Result<(UpdateImpact, PathBuf)> {
+        std::fs::create_dir_all(&download_dir)?;
+        let (_, download_url) = remote_mod.get_channel_release(local_mod.channel);
+        let file_name = match get_end_of_url(download_url) {
+            "" => "mod",
+            name => name,
+        };
+        let path = download_dir.join(file_name);
+        download_file(download_url, &path).await?;
+        let manifest = get_manifest_from_file(&path)?;
+        Ok((UpdateImpact::new(local_mod, &manifest, local_db), path))
+    }
+
+    /// Whether any of the updates need the user to confirm them, see [UpdateImpact::needs_confirmation]
+    pub fn needs_confirmation(&self) -> bool {
+        self.impacts.iter().any(|i| i.needs_confirmation())
+    }
+
+    /// Install the downloaded updates, then install any new dependencies they need (marked with [InstallReason::Dependency]).
+    /// This will also send analytics events given you set `ANALYTICS_API_KEY`.
+    ///
+    /// ## Returns
+    ///
+    /// The updated mods
+    ///
+    /// ## Errors
+    ///
+    /// If we can't install one of the updates or dependencies
+    ///
+    pub async fn apply(
+        self,
+        config: &Config,
+        local_db: &LocalDatabase,
+        remote_db: &RemoteDatabase,
+    ) -> Result<Vec<LocalMod>> {
+        let mut updated = vec![];
+        for path in self.downloads.iter() {
+            let updated_mod = install_mod_from_file(path, config, local_db)?;
+            send_analytics_event(
+                AnalyticsEventName::ModUpdate,
+                &updated_mod.manifest.unique_name,
+            )
+            .await?;
+            updated.push(updated_mod);
+        }
+        let mut missing: Vec<&String> = self
+            .impacts
+            .iter()
+            .flat_map(|i| i.missing_dependencies.iter())
+            .collect();
+        missing.sort();
+        missing.dedup();
+        for dep in missing {
+            let current_db = LocalDatabase::fetch(config.get_owml_path())?;
+            if current_db.get_mod(dep).is_some() {
+                continue;
+            }
+            install_mod_from_db(dep, config, remote_db, &current_db, true, false).await?;
+            if let Some(dep_mod) = LocalDatabase::fetch(config.get_owml_path())?.get_mod(dep) {
+                write_install_reason(Path::new(&dep_mod.mod_path), InstallReason::Dependency)?;
+            }
+        }
+        Ok(updated)
+    }
+}
+
+/// Check OWML for an update and install it if there is one
+///
+/// ## Returns
+///
+/// Whether OWML was updated
+///
+/// ## Errors
+///
+/// If we can't download or install the update.
+///
+pub async fn update_owml(config: &Config, remote_db: &RemoteDatabase) -> Result<bool> {
+    if let Some(owml) = LocalDatabase::get_owml(config.get_owml_path()) {
+        let (update, remote_owml) = check_mod_needs_update(&owml, remote_db);
+        if let (true, Some(remote_owml)) = (update, remote_owml) {
+            info!("OWML: {} -> {}", owml.manifest.version, remote_owml.version);
+            download_and_install_owml(config, remote_owml).await?;
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
 /// Check all mods *and OWML* for updates and update them if needed.
 ///
 /// ## Returns
@@ -66,28 +394,13 @@ pub async fn update_all(
                 "{}: {} -> {}",
                 local_mod.manifest.name,
                 local_mod.manifest.version,
-                remote_mod.unwrap().version
+                remote_mod.unwrap().get_channel_release(local_mod.channel).0
             );
             needs_update.push(remote_mod.unwrap());
         }
     }
 
-    let owml = LocalDatabase::get_owml(&config.owml_path);
-
-    let mut owml_updated = false;
-
-    if owml.is_some() {
-        let (update, remote_owml) = check_mod_needs_update(owml.as_ref().unwrap(), remote_db);
-        if update {
-            owml_updated = true;
-            info!(
-                "OWML: {} -> {}",
-                owml.as_ref().unwrap().manifest.version,
-                remote_owml.unwrap().version
-            );
-            download_and_install_owml(config, remote_owml.unwrap()).await?;
-        }
-    }
+    let owml_updated = update_owml(config, remote_db).await?;
 
     if needs_update.is_empty() {
         Ok(owml_updated)
@@ -96,15 +409,14 @@ pub async fn update_all(
             let mod_names = needs_update
                 .into_iter()
                 .map(|m| m.unique_name.clone())
-                .collect();
-            let updated = install_mods_parallel(mod_names, config, remote_db, local_db).await?;
-            for updated_mod in updated {
-                send_analytics_event(
-                    AnalyticsEventName::ModUpdate,
-                    &updated_mod.manifest.unique_name,
-                )
-                .await?;
+                .collect::<Vec<_>>();
+            let plan = UpdatePlan::new(&mod_names, local_db, remote_db).await?;
+            for impact in plan.impacts.iter() {
+                for line in impact.describe() {
+                    info!("{}: {}", impact.name, line);
+                }
             }
+            plan.apply(config, local_db, remote_db).await?;
         }
         Ok(true)
     }
@@ -114,6 +426,14 @@ pub async fn update_all(
 mod tests {
 
     use super::*;
+    use crate::{
+        file::serialize_to_json,
+        mods::{
+            local::{ReleaseChannel, UnsafeLocalMod},
+            remote::ModPrerelease,
+        },
+        test_utils::{get_test_file, make_test_dir, MockServer},
+    };
 
     fn setup(local_version: &str, remote_version: &str) -> (LocalMod, RemoteDatabase) {
         let mut new_mod = LocalMod::get_test(0);
@@ -147,4 +467,193 @@ mod tests {
         let (needs_update, _) = check_mod_needs_update(&new_mod, &db);
         assert!(!needs_update);
     }
+
+    #[test]
+    fn test_check_mod_needs_update_prerelease() {
+        let (mut new_mod, mut db) = setup("0.2.0-beta", "0.1.0");
+        db.mods
+            .get_mut(&new_mod.manifest.unique_name)
+            .unwrap()
+            .prerelease = Some(ModPrerelease {
+            download_url: String::new(),
+            version: "0.2.0-beta".to_string(),
+        });
+        // A stable mod shouldn't be "updated" back down to the release
+        let (needs_update, _) = check_mod_needs_update(&new_mod, &db);
+        assert!(!needs_update);
+        new_mod.manifest.version = "0.1.0".to_string();
+        let (needs_update, _) = check_mod_needs_update(&new_mod, &db);
+        assert!(!needs_update);
+        new_mod.channel = ReleaseChannel::Prerelease;
+        let (needs_update, _) = check_mod_needs_update(&new_mod, &db);
+        assert!(needs_update);
+        new_mod.manifest.version = "0.2.0-beta".to_string();
+        let (needs_update, _) = check_mod_needs_update(&new_mod, &db);
+        assert!(!needs_update);
+    }
+
+    #[test]
+    fn test_update_impact() {
+        let mut old_mod = LocalMod::get_test(0);
+        old_mod.manifest.dependencies = Some(vec!["Test.Old".to_string()]);
+        old_mod.manifest.owml_version = Some("2.3.2".to_string());
+        let mut new_manifest = old_mod.manifest.clone();
+        new_manifest.version = "0.2.0".to_string();
+        new_manifest.dependencies = Some(vec![
+            "Test.Installed".to_string(),
+            "Test.Missing".to_string(),
+        ]);
+        new_manifest.conflicts = Some(vec![
+            "Test.Installed".to_string(),
+            "Test.Disabled".to_string(),
+        ]);
+        new_manifest.patcher = Some("Patcher.dll".to_string());
+
+        let mut local_db = LocalDatabase::default();
+        let mut installed = LocalMod::get_test(1);
+        installed.manifest.unique_name = "Test.Installed".to_string();
+        let mut disabled = LocalMod::get_test(2);
+        disabled.manifest.unique_name = "Test.Disabled".to_string();
+        disabled.enabled = false;
+        for m in [installed, disabled] {
+            local_db
+                .mods
+                .insert(m.manifest.unique_name.clone(), UnsafeLocalMod::Valid(m));
+        }
+
+        let impact = UpdateImpact::new(&old_mod, &new_manifest, &local_db);
+        assert_eq!(
+            impact.dependencies.added,
+            vec!["Test.Installed", "Test.Missing"]
+        );
+        assert_eq!(impact.dependencies.removed, vec!["Test.Old"]);
+        assert_eq!(impact.missing_dependencies, vec!["Test.Missing"]);
+        assert_eq!(impact.new_conflicts, vec!["Test.Installed"]);
+        assert!(impact.owml_version.is_none());
+        assert!(impact.paths_to_preserve.is_empty());
+        assert_eq!(
+            impact.patcher,
+            Some(ValueDiff {
+                old: None,
+                new: Some("Patcher.dll".to_string())
+            })
+        );
+        assert!(impact.needs_confirmation());
+        let lines = impact.describe();
+        assert!(
+            lines.contains(&"Adds dependency Test.Missing, which will be installed".to_string())
+        );
+        assert!(lines.contains(&"Now conflicts with Test.Installed, which is enabled".to_string()));
+        assert!(lines.contains(&"Now conflicts with Test.Disabled".to_string()));
+        assert!(lines.contains(&"Starts using a pre-patcher (Patcher.dll)".to_string()));
+
+        let impact = UpdateImpact::new(&old_mod, &old_mod.manifest, &local_db);
+        assert!(!impact.needs_confirmation());
+        assert!(impact.describe().is_empty());
+    }
+
+    #[test]
+    fn test_update_plan() {
+        let server = MockServer::start();
+        tokio_test::block_on(async {
+            let dir = make_test_dir();
+            let config = server.config(dir.path());
+            let remote_db = RemoteDatabase::fetch(&config.database_url).await.unwrap();
+            let zip_path = get_test_file("Bwc9876.TimeSaver.zip");
+            let mut old_mod =
+                install_mod_from_file(&zip_path, &config, &LocalDatabase::default()).unwrap();
+            old_mod.manifest.version = "1.0.0".to_string();
+            old_mod.manifest.conflicts = Some(vec!["Test.Gone".to_string()]);
+            let manifest_path = dir
+                .path()
+                .join("Mods")
+                .join("Bwc9876.TimeSaver")
+                .join("manifest.json");
+            serialize_to_json(&old_mod.manifest, &manifest_path, false).unwrap();
+            let local_db = LocalDatabase::fetch(&config.owml_path).unwrap();
+
+            let mut plan =
+                UpdatePlan::new(&["Bwc9876.TimeSaver".to_string()], &local_db, &remote_db)
+                    .await
+                    .unwrap();
+            assert_eq!(plan.impacts.len(), 1);
+            let impact = &plan.impacts[0];
+            assert_eq!(impact.old_version, "1.0.0");
+            assert_eq!(impact.new_version, "1.1.1");
+            assert_eq!(impact.conflicts.removed, vec!["Test.Gone"]);
+            assert!(!plan.needs_confirmation());
+            // Nothing is installed until the plan is applied
+            let unchanged = LocalDatabase::read_local_mod(&manifest_path).unwrap();
+            assert_eq!(unchanged.manifest.version, "1.0.0");
+
+            plan.impacts[0]
+                .missing_dependencies
+                .push("Bwc9876.SaveEditor".to_string());
+            let updated = plan.apply(&config, &local_db, &remote_db).await.unwrap();
+            assert_eq!(updated[0].manifest.version, "1.1.1");
+            let local_db = LocalDatabase::fetch(&config.owml_path).unwrap();
+            assert_eq!(
+                local_db
+                    .get_mod("Bwc9876.SaveEditor")
+                    .unwrap()
+                    .install_reason,
+                InstallReason::Dependency
+            );
+            dir.close().unwrap();
+        });
+    }
+
+    #[test]
+    fn test_update_plan_not_installed() {
+        let server = MockServer::start();
+        tokio_test::block_on(async {
+            let remote_db = RemoteDatabase::fetch(&server.database_url()).await.unwrap();
+            let res = UpdatePlan::new(
+                &["Bwc9876.TimeSaver".to_string()],
+                &LocalDatabase::default(),
+                &remote_db,
+            )
+            .await;
+            assert!(res.is_err());
+        });
+    }
+
+    #[test]
+    fn test_update_all() {
+        let server = MockServer::start();
+        tokio_test::block_on(async {
+            let dir = make_test_dir();
+            let config = server.config(dir.path());
+            let remote_db = RemoteDatabase::fetch(&config.database_url).await.unwrap();
+            let zip_path = get_test_file("Bwc9876.TimeSaver.zip");
+            let mut old_mod =
+                install_mod_from_file(&zip_path, &config, &LocalDatabase::default()).unwrap();
+            old_mod.manifest.version = "1.0.0".to_string();
+            let manifest_path = dir
+                .path()
+                .join("Mods")
+                .join("Bwc9876.TimeSaver")
+                .join("manifest.json");
+            serialize_to_json(&old_mod.manifest, &manifest_path, false).unwrap();
+            let local_db = LocalDatabase::fetch(&config.owml_path).unwrap();
+
+            assert!(update_all(&config, &local_db, &remote_db, true)
+                .await
+                .unwrap());
+            let unchanged = LocalDatabase::read_local_mod(&manifest_path).unwrap();
+            assert_eq!(unchanged.manifest.version, "1.0.0");
+
+            assert!(update_all(&config, &local_db, &remote_db, false)
+                .await
+                .unwrap());
+            let updated = LocalDatabase::read_local_mod(&manifest_path).unwrap();
+            assert_eq!(updated.manifest.version, "1.1.1");
+
+            let local_db = LocalDatabase::fetch(&config.owml_path).unwrap();
+            assert!(!update_all(&config, &local_db, &remote_db, false)
+                .await
+                .unwrap());
+            dir.close().unwrap();
+        });
+    }
 }
@@ -11,6 +11,7 @@ use crate::{
     download::install_mods_parallel,
     mods::local::LocalMod,
     toggle::toggle_mod,
+    version::Version,
 };
 
 /// Represents an error with a [LocalMod]
@@ -32,6 +33,16 @@ pub enum ModValidationError {
     DuplicateMod(String),
     /// The mod is outdated, contains the newest version
     Outdated(String),
+    /// The mod's version couldn't be parsed so we can't tell if it's outdated, contains the version
+    UnparseableVersion(String),
+}
+
+fn check_mod_version(local_mod: &LocalMod) -> Option<ModValidationError> {
+    let version = &local_mod.manifest.version;
+    version
+        .parse::<Version>()
+        .err()
+        .map(|_| ModValidationError::UnparseableVersion(version.clone()))
 }
 
 fn check_mod_dll(local_mod: &LocalMod) -> Option<ModValidationError> {
@@ -80,6 +91,7 @@ fn check_mod_conflicts(local_mod: &LocalMod, db: &LocalDatabase) -> Vec<ModValid
 /// - Missing/Disabled Dependencies
 /// - Conflicting Mods
 /// - Missing DLL File
+/// - Versions that can't be parsed
 ///
 /// ## Returns
 ///
@@ -92,6 +104,9 @@ pub fn check_mod(local_mod: &LocalMod, db: &LocalDatabase) -> Vec<ModValidationE
     if let Some(dll_error) = check_mod_dll(local_mod) {
         errors.push(dll_error);
     }
+    if let Some(version_error) = check_mod_version(local_mod) {
+        errors.push(version_error);
+    }
     errors
 }
 
@@ -299,4 +314,20 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_check_mod_version() {
+        let mut mod_a = LocalMod::get_test(0);
+        mod_a.manifest.version = "v1.2".to_string();
+        assert!(check_mod_version(&mod_a).is_none());
+        mod_a.manifest.version = "1.2.0 beta".to_string();
+        match check_mod_version(&mod_a) {
+            Some(ModValidationError::UnparseableVersion(version)) => {
+                assert_eq!(version, "1.2.0 beta");
+            }
+            _ => {
+                panic!("Wrong Error Thrown!");
+            }
+        }
+    }
 }
@@ -0,0 +1,192 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use anyhow::{anyhow, Error, Result};
+use log::debug;
+use semver::{BuildMetadata, Prerelease};
+
+/// A mod version, parsed like semver but leniently enough to handle the versions older mods use.
+///
+/// - Leading `v`s are ignored (`v1.0.0` is `1.0.0`)
+/// - Missing parts are zero (`1.2` is `1.2.0`), extra parts are compared as well (`1.2.0.1` is newer than `1.2.0`)
+/// - Prerelease tags are compared like semver (`1.2.0-beta.2` < `1.2.0-beta.10` < `1.2.0`)
+/// - Build metadata (`1.2.0+abc`) is kept but ignored when comparing
+///
+#[derive(Clone, Debug)]
+pub struct Version {
+    numbers: Vec<u64>,
+    pre: Prerelease,
+    build: BuildMetadata,
+}
+
+impl Version {
+    /// Whether this version has a prerelease tag
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+}
+
+impl FromStr for Version {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("\"{}\" isn't a valid version", s);
+        let version = s.trim().trim_start_matches(['v', 'V']);
+        let (version, build) = match version.split_once('+') {
+            Some((version, build)) if !build.is_empty() => {
+                (version, BuildMetadata::new(build).map_err(|_| invalid())?)
+            }
+            Some(_) => return Err(invalid()),
+            None => (version, BuildMetadata::EMPTY),
+        };
+        let (version, pre) = match version.split_once('-') {
+            Some((version, pre)) if !pre.is_empty() => {
+                (version, Prerelease::new(pre).map_err(|_| invalid())?)
+            }
+            Some(_) => return Err(invalid()),
+            None => (version, Prerelease::EMPTY),
+        };
+        let numbers = version
+            .split('.')
+            .map(|n| n.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        Ok(Self {
+            numbers,
+            pre,
+            build,
+        })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let numbers = self
+            .numbers
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{}", numbers.join("."))?;
+        if !self.pre.is_empty() {
+            write!(f, "-{}", self.pre)?;
+        }
+        if !self.build.is_empty() {
+            write!(f, "+{}", self.build)?;
+        }
+        Ok(())
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.numbers.len().max(other.numbers.len());
+        for i in 0..len {
+            let ord = self
+                .numbers
+                .get(i)
+                .unwrap_or(&0)
+                .cmp(other.numbers.get(i).unwrap_or(&0));
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        // An empty prerelease (a release) is greater than any prerelease
+        self.pre.cmp(&other.pre)
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+/// Compare two versions, see [Version] for how they're parsed
+///
+/// ## Errors
+///
+/// If either version can't be parsed
+///
+pub fn compare_versions(a: &str, b: &str) -> Result<Ordering> {
+    Ok(a.parse::<Version>()?.cmp(&b.parse::<Version>()?))
+}
+
+/// Check if `new` is a newer version than `old`.
+/// If either version can't be parsed this is always false, so we never update a mod based on a version we don't understand.
+pub fn is_newer(new: &str, old: &str) -> bool {
+    match compare_versions(new, old) {
+        Ok(ord) => ord == Ordering::Greater,
+        Err(why) => {
+            debug!("Can't compare {} to {}: {:?}", new, old, why);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn parse(version: &str) -> Version {
+        version.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("1.2.3").to_string(), "1.2.3");
+        assert_eq!(parse("v1.2.3").to_string(), "1.2.3");
+        assert_eq!(parse(" 1.2 ").to_string(), "1.2");
+        assert_eq!(parse("1.2.3.4").to_string(), "1.2.3.4");
+        assert_eq!(parse("1.2.0-beta.3+abc").to_string(), "1.2.0-beta.3+abc");
+        assert!(parse("1.2.0-beta.3").is_prerelease());
+        assert!(!parse("1.2.0+abc").is_prerelease());
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        for version in [
+            "",
+            "burger",
+            "1..2",
+            "1.2.",
+            "1.2.0-",
+            "1.2.0+",
+            "1.2.0-be ta",
+            "1.x",
+        ] {
+            assert!(version.parse::<Version>().is_err(), "{version} parsed");
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        assert!(parse("1.2.0") > parse("1.1.9"));
+        assert!(parse("1.10.0") > parse("1.9.0"));
+        assert_eq!(parse("1.2"), parse("1.2.0"));
+        assert_eq!(parse("v1.2.0"), parse("1.2.0"));
+        assert!(parse("1.2.0.1") > parse("1.2.0"));
+        assert!(parse("1.2.0") > parse("1.2.0-beta.3"));
+        assert!(parse("1.2.0-beta.10") > parse("1.2.0-beta.3"));
+        assert!(parse("1.2.0-beta") > parse("1.2.0-alpha"));
+        assert!(parse("1.2.0-beta.3") > parse("1.1.0"));
+        assert_eq!(parse("1.2.0+abc"), parse("1.2.0+def"));
+    }
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("0.2.0", "0.1.0"));
+        assert!(!is_newer("0.1.0", "0.1.0"));
+        assert!(!is_newer("0.1.0", "0.2.0"));
+        assert!(!is_newer("burger", "0.1.0"));
+        assert!(!is_newer("0.2.0", "burger"));
+        assert!(compare_versions("burger", "0.1.0").is_err());
+    }
+}
@@ -0,0 +1,354 @@
+use std::{
+    fmt::Display,
+    fs::{copy, read_to_string, rename},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use log::{debug, warn};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+use crate::file::{fix_json, serialize_to_json_atomic};
+
+/// The key the version of a settings file is stored in
+pub const VERSION_KEY: &str = "version";
+
+/// A step that upgrades a settings file by one version, the migration at index `n` upgrades version `n` to `n + 1`.
+/// Files from before settings were versioned are version `0`.
+pub type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+/// What was done about a settings file that couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recovery {
+    /// The settings file that couldn't be read
+    pub path: PathBuf,
+    /// Where the file was moved to, it's never deleted
+    pub quarantined_path: PathBuf,
+    /// Why the file couldn't be read
+    pub reason: String,
+    /// Whether the last known-good backup was restored, if not the settings were reset to the defaults
+    pub restored_backup: bool,
+}
+
+impl Display for Recovery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} couldn't be read ({}), it was moved to {} and {}",
+            self.path.display(),
+            self.reason,
+            self.quarantined_path.display(),
+            if self.restored_backup {
+                "the last working backup was restored"
+            } else {
+                "the defaults are being used"
+            }
+        )
+    }
+}
+
+/// A settings file that was loaded with [load_versioned]
+pub struct Loaded<T> {
+    pub value: T,
+    /// Set if the file was corrupted and had to be recovered
+    pub recovery: Option<Recovery>,
+}
+
+/// Get where the last known-good copy of a settings file is kept
+pub fn get_backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+/// Get the version of the settings a list of migrations upgrades to
+pub const fn current_version(migrations: &[Migration]) -> u32 {
+    migrations.len() as u32
+}
+
+// The outer error is for problems with reading the file at all, the inner one is for the file being corrupted
+fn read_versioned<T: DeserializeOwned>(
+    path: &Path,
+    migrations: &[Migration],
+) -> Result<Result<(T, u32), String>> {
+    let txt = read_to_string(path)?;
+    let mut obj = match serde_json::from_str::<Value>(&fix_json(&txt)) {
+        Ok(Value::Object(obj)) => obj,
+        Ok(_) => return Ok(Err("it isn't a JSON object".to_string())),
+        Err(why) => return Ok(Err(why.to_string())),
+    };
+    let current = current_version(migrations);
+    let version = match obj.get(VERSION_KEY) {
+        None => 0,
+        Some(Value::Number(n)) if n.as_u64().is_some_and(|v| v <= u32::MAX as u64) => {
+            n.as_u64().unwrap() as u32
+        }
+        Some(v) => return Ok(Err(format!("{v} isn't a valid version"))),
+    };
+    if version > current {
+        return Err(anyhow!(
+            "{} is from a newer version of the manager (settings version {version}, this version supports up to {current}), please update",
+            path.display()
+        ));
+    }
+    for (from, migration) in migrations.iter().enumerate().skip(version as usize) {
+        debug!(
+            "Migrating {} from version {} to {}",
+            path.display(),
+            from,
+            from + 1
+        );
+        if let Err(why) = migration(&mut obj) {
+            return Ok(Err(format!(
+                "couldn't migrate it from version {from}: {why}"
+            )));
+        }
+    }
+    obj.insert(VERSION_KEY.to_string(), current.into());
+    Ok(serde_json::from_value(Value::Object(obj))
+        .map(|value| (value, version))
+        .map_err(|why| why.to_string()))
+}
+
+fn quarantine(path: &Path) -> Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let new_path = match path.extension() {
+        Some(ext) => path.with_file_name(format!(
+            "{stem}.corrupt-{timestamp}.{}",
+            ext.to_string_lossy()
+        )),
+        None => path.with_file_name(format!("{stem}.corrupt-{timestamp}")),
+    };
+    rename(path, &new_path)?;
+    Ok(new_path)
+}
+
+/// Load a versioned settings file, creating it with `default` if it doesn't exist.
+/// Files from older versions are upgraded with `migrations` and saved, the old file is kept as the backup.
+///
+/// If the file is corrupted it's moved next to itself with `.corrupt-<timestamp>` added to its name,
+/// then the last known-good backup is restored, or the defaults are used if the backup can't be read either.
+/// Corrupted files are never deleted or overwritten.
+///
+/// ## Returns
+///
+/// The loaded settings, and what was done to recover them if the file was corrupted
+///
+/// ## Errors
+///
+/// - If we can't read or write the file
+/// - If the file is from a newer version than `migrations` upgrades to, it's left as it is
+///
+pub fn load_versioned<T>(
+    path: &Path,
+    migrations: &[Migration],
+    default: impl FnOnce() -> Result<T>,
+) -> Result<Loaded<T>>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let current = current_version(migrations);
+    if !path.is_file() {
+        let value = default()?;
+        save_versioned(&value, path, current)?;
+        return Ok(Loaded {
+            value,
+            recovery: None,
+        });
+    }
+    let reason = match read_versioned::<T>(path, migrations)? {
+        Ok((value, version)) => {
+            if version != current {
+                save_versioned(&value, path, current)?;
+            }
+            return Ok(Loaded {
+                value,
+                recovery: None,
+            });
+        }
+        Err(reason) => reason,
+    };
+    let quarantined_path = quarantine(path)?;
+    let backup_path = get_backup_path(path);
+    let backup = if backup_path.is_file() {
+        read_versioned::<T>(&backup_path, migrations)
+            .ok()
+            .and_then(|r| r.ok())
+    } else {
+        None
+    };
+    let restored_backup = backup.is_some();
+    let value = match backup {
+        Some((value, _)) => value,
+        None => default()?,
+    };
+    serialize_to_json_atomic(&with_version(&value, current)?, path, true)?;
+    let recovery = Recovery {
+        path: path.to_path_buf(),
+        quarantined_path,
+        reason,
+        restored_backup,
+    };
+    warn!("{}", recovery);
+    Ok(Loaded {
+        value,
+        recovery: Some(recovery),
+    })
+}
+
+fn with_version<T: Serialize>(value: &T, version: u32) -> Result<Value> {
+    let mut value = serde_json::to_value(value)?;
+    if let Value::Object(obj) = &mut value {
+        obj.insert(VERSION_KEY.to_string(), version.into());
+    }
+    Ok(value)
+}
+
+/// Save a versioned settings file atomically.
+/// If the file being replaced is valid JSON it's kept as the backup (see [get_backup_path]), so [load_versioned] can restore it.
+///
+/// ## Errors
+///
+/// - If we can't write the file or its backup
+/// - If the file being replaced is from a newer version than `version`, it's left as it is
+///
+pub fn save_versioned<T: Serialize>(value: &T, path: &Path, version: u32) -> Result<()> {
+    let value = with_version(value, version)?;
+    if path.is_file() {
+        let existing = read_to_string(path)
+            .ok()
+            .and_then(|txt| serde_json::from_str::<Value>(&fix_json(&txt)).ok());
+        if let Some(existing) = existing {
+            let existing_version = existing.get(VERSION_KEY).and_then(|v| v.as_u64());
+            if existing_version.is_some_and(|v| v > version as u64) {
+                return Err(anyhow!(
+                    "Not saving over {}, it's from a newer version of the manager",
+                    path.display()
+                ));
+            }
+            copy(path, get_backup_path(path))?;
+        }
+    }
+    serialize_to_json_atomic(&value, path, true)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs::{read_dir, write};
+
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::test_utils::make_test_dir;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct TestSettings {
+        #[serde(default)]
+        version: u32,
+        name: String,
+    }
+
+    fn rename_title(obj: &mut Map<String, Value>) -> Result<()> {
+        let title = obj.remove("title").ok_or_else(|| anyhow!("No title"))?;
+        obj.insert("name".to_string(), title);
+        Ok(())
+    }
+
+    const MIGRATIONS: &[Migration] = &[rename_title];
+
+    fn default() -> Result<TestSettings> {
+        Ok(TestSettings {
+            version: 1,
+            name: "Default".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_load_versioned_new() {
+        let dir = make_test_dir();
+        let path = dir.path().join("settings.json");
+        let loaded = load_versioned(&path, MIGRATIONS, default).unwrap();
+        assert_eq!(loaded.value, default().unwrap());
+        assert!(loaded.recovery.is_none());
+        assert!(path.is_file());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_load_versioned_migrate() {
+        let dir = make_test_dir();
+        let path = dir.path().join("settings.json");
+        write(&path, r#"{ "title": "Old" }"#).unwrap();
+        let loaded = load_versioned::<TestSettings>(&path, MIGRATIONS, default).unwrap();
+        assert_eq!(loaded.value.name, "Old");
+        assert_eq!(loaded.value.version, 1);
+        let saved = read_to_string(&path).unwrap();
+        assert!(saved.contains("\"version\": 1"));
+        let backup = read_to_string(get_backup_path(&path)).unwrap();
+        assert!(backup.contains("title"));
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_load_versioned_newer() {
+        let dir = make_test_dir();
+        let path = dir.path().join("settings.json");
+        let contents = r#"{ "version": 2, "name": "New" }"#;
+        write(&path, contents).unwrap();
+        assert!(load_versioned::<TestSettings>(&path, MIGRATIONS, default).is_err());
+        // Falling back to the defaults can't replace the newer settings
+        assert!(save_versioned(&default().unwrap(), &path, 1).is_err());
+        assert_eq!(read_to_string(&path).unwrap(), contents);
+        assert!(!get_backup_path(&path).exists());
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_load_versioned_corrupted() {
+        let dir = make_test_dir();
+        let path = dir.path().join("settings.json");
+        let mut settings = default().unwrap();
+        settings.name = "Good".to_string();
+        save_versioned(&settings, &path, 1).unwrap();
+        settings.name = "Newer".to_string();
+        save_versioned(&settings, &path, 1).unwrap();
+        write(&path, "{ \"name\": ").unwrap();
+
+        let loaded = load_versioned::<TestSettings>(&path, MIGRATIONS, default).unwrap();
+        assert_eq!(loaded.value.name, "Good");
+        let recovery = loaded.recovery.unwrap();
+        assert!(recovery.restored_backup);
+        assert_eq!(
+            read_to_string(&recovery.quarantined_path).unwrap(),
+            "{ \"name\": "
+        );
+        assert_eq!(
+            load_versioned::<TestSettings>(&path, MIGRATIONS, default)
+                .unwrap()
+                .value
+                .name,
+            "Good"
+        );
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_load_versioned_corrupted_no_backup() {
+        let dir = make_test_dir();
+        let path = dir.path().join("settings.json");
+        write(&path, r#"{ "version": 1, "name": 5 }"#).unwrap();
+        let loaded = load_versioned::<TestSettings>(&path, MIGRATIONS, default).unwrap();
+        assert_eq!(loaded.value, default().unwrap());
+        assert!(!loaded.recovery.unwrap().restored_backup);
+        // The new settings, and the quarantined file
+        assert_eq!(read_dir(dir.path()).unwrap().count(), 2);
+        dir.close().unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    fs::create_dir_all,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use typeshare::typeshare;
+
+/// Represents a change made to a [crate::db::LocalDatabase] after re-reading mods from the disk
+#[typeshare]
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "changeType", content = "payload")]
+pub enum LocalDatabaseChange {
+    /// A new mod was found, contains the unique name of the mod
+    Added(String),
+    /// An existing mod's manifest or config changed, contains the unique name of the mod
+    Updated(String),
+    /// A mod was deleted, contains the unique name of the mod
+    Removed(String),
+}
+
+/// Watches the `Mods` folder of an OWML install and reports which paths changed.
+/// Changes are collected into batches so a mod being extracted only results in one batch.
+///
+/// Feed the batches into [crate::db::LocalDatabase::apply_changes] to keep the database up to date.
+///
+pub struct ModsWatcher {
+    mods_path: PathBuf,
+    debounce: Duration,
+    rx: Receiver<notify::Result<Event>>,
+    // Dropping the watcher stops it, so we need to keep it around
+    _watcher: RecommendedWatcher,
+}
+
+impl ModsWatcher {
+    /// Start watching the `Mods` folder in the given OWML path, creating it if it doesn't exist.
+    ///
+    /// ## Errors
+    ///
+    /// If we can't create the `Mods` folder or we can't start watching it.
+    ///
+    pub fn new(owml_path: &str, debounce: Duration) -> Result<Self> {
+        let mods_path = PathBuf::from(owml_path).join("Mods");
+        create_dir_all(&mods_path)?;
+        let (tx, rx) = channel();
+        let mut watcher = notify::recommended_watcher(move |res| {
+            tx.send(res).ok();
+        })?;
+        watcher.watch(&mods_path, RecursiveMode::Recursive)?;
+        Ok(Self {
+            mods_path,
+            debounce,
+            rx,
+            _watcher: watcher,
+        })
+    }
+
+    /// Get the path to the `Mods` folder being watched
+    pub fn mods_path(&self) -> &Path {
+        &self.mods_path
+    }
+
+    /// Wait up to `timeout` for something to change, then keep collecting changes until nothing has happened for the debounce duration.
+    ///
+    /// ## Returns
+    ///
+    /// The paths that changed, empty if the timeout elapsed without any changes.
+    ///
+    /// ## Errors
+    ///
+    /// If the watcher reports an error or has stopped.
+    ///
+    pub fn next_batch(&self, timeout: Duration) -> Result<Vec<PathBuf>> {
+        let mut paths: Vec<PathBuf> = vec![];
+        let mut wait_for = timeout;
+        loop {
+            match self.rx.recv_timeout(wait_for) {
+                Ok(event) => {
+                    let event = event?;
+                    if !matches!(event.kind, EventKind::Access(_)) {
+                        paths.extend(event.paths);
+                    }
+                    if !paths.is_empty() {
+                        wait_for = self.debounce;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(anyhow!("Mods watcher stopped"));
+                }
+            }
+        }
+        paths.sort();
+        paths.dedup();
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs::{copy, create_dir_all, rename};
+
+    use crate::{
+        db::LocalDatabase,
+        test_utils::{get_test_file, make_test_dir},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_watcher_keeps_db_live() {
+        let dir = make_test_dir();
+        let owml_path = dir.path().to_str().unwrap();
+        let mut db = LocalDatabase::fetch(owml_path).unwrap();
+        let watcher = ModsWatcher::new(owml_path, Duration::from_millis(200)).unwrap();
+        // Make the mod outside the Mods folder and move it in, so it shows up as a single change
+        let staged_path = dir.path().join("Bwc9876.TimeSaver");
+        create_dir_all(&staged_path).unwrap();
+        copy(
+            get_test_file("Mods/Bwc9876.TimeSaver/manifest.json"),
+            staged_path.join("manifest.json"),
+        )
+        .unwrap();
+        rename(&staged_path, watcher.mods_path().join("Bwc9876.TimeSaver")).unwrap();
+        let paths = watcher.next_batch(Duration::from_secs(5)).unwrap();
+        let changes = db.apply_changes(watcher.mods_path(), &paths);
+        assert_eq!(
+            changes,
+            vec![LocalDatabaseChange::Added("Bwc9876.TimeSaver".to_string())]
+        );
+        dir.close().unwrap();
+    }
+}
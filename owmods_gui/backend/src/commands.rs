@@ -9,21 +9,25 @@ use anyhow::anyhow;
 use log::error;
 use owmods_core::{
     alerts::{fetch_alert, Alert},
+    changelog::{fetch_changelog, ChangelogEntry},
     config::Config,
-    constants::OWML_UNIQUE_NAME,
+    constants::{GITHUB_API_URL, OWML_UNIQUE_NAME},
     db::{LocalDatabase, RemoteDatabase},
     download::{
-        download_and_install_owml, install_mod_from_db, install_mod_from_url, install_mod_from_zip,
-        install_mods_parallel,
+        download_and_install_owml, install_mod_from_db, install_mod_from_file,
+        install_mod_from_url, install_required_mods,
     },
-    file::{create_all_parents, get_app_path},
+    file::{create_all_parents, format_size, get_app_path},
     game::launch_game,
+    history::{History, Operation},
     mods::{local::UnsafeLocalMod, remote::RemoteMod},
     open::{open_readme, open_shortcut},
     owml::OWMLConfig,
+    patcher::Patches,
     remove::{remove_failed_mod, remove_mod},
+    search::SearchQuery,
     socket::{LogServer, SocketMessageType},
-    updates::check_mod_needs_update,
+    updates::{check_mod_needs_update, update_owml, UpdatePlan},
     validate::fix_deps,
 };
 use serde::Serialize;
@@ -40,6 +44,9 @@ use crate::{
 
 type Result<T = ()> = StdResult<T, Error>;
 
+/// How the GUI identifies itself in the operation history
+const FRONT_END: &str = "gui";
+
 pub struct Error(anyhow::Error);
 
 impl From<anyhow::Error> for Error {
@@ -96,7 +103,7 @@ pub async fn refresh_local_db(handle: tauri::AppHandle, state: tauri::State<'_,
     let conf = state.config.read().await;
     {
         let mut db = state.local_db.write().await;
-        let local_db = LocalDatabase::fetch(&conf.owml_path)?;
+        let local_db = LocalDatabase::fetch(conf.get_owml_path())?;
         *db = local_db;
     }
     handle.emit_all("LOCAL-REFRESH", "").ok();
@@ -116,15 +123,15 @@ pub async fn refresh_local_db(handle: tauri::AppHandle, state: tauri::State<'_,
 #[tauri::command]
 pub async fn get_local_mods(filter: &str, state: tauri::State<'_, State>) -> Result<Vec<String>> {
     let db = state.local_db.read().await;
-    let mut mods: Vec<&UnsafeLocalMod> = db.all().collect();
-    if filter.is_empty() {
+    let remote_db = state.remote_db.read().await;
+    let query = SearchQuery::parse(filter);
+    let mut mods: Vec<&UnsafeLocalMod> = db.query(&query, Some(&remote_db));
+    if query.text.is_empty() && query.sort.is_none() {
         mods.sort_by(|a, b| {
             let name_ord = a.get_name().cmp(b.get_name());
             let errors_ord = a.get_errs().len().cmp(&b.get_errs().len()).reverse();
             errors_ord.then(name_ord)
         });
-    } else {
-        mods = db.search(filter);
     }
     Ok(mods
         .into_iter()
@@ -139,8 +146,8 @@ pub async fn get_local_mod(
 ) -> Result<Option<UnsafeLocalMod>> {
     if unique_name == OWML_UNIQUE_NAME {
         let config = state.config.read().await;
-        let owml = LocalDatabase::get_owml(&config.owml_path)
-            .ok_or_else(|| anyhow!("Couldn't Find OWML at path {}", &config.owml_path))?;
+        let owml = LocalDatabase::get_owml(config.get_owml_path())
+            .ok_or_else(|| anyhow!("Couldn't Find OWML at path {}", config.get_owml_path()))?;
         Ok(Some(UnsafeLocalMod::Valid(owml)))
     } else {
         Ok(state
@@ -158,7 +165,7 @@ pub async fn refresh_remote_db(handle: tauri::AppHandle, state: tauri::State<'_,
     let conf = state.config.read().await;
     {
         let mut db = state.remote_db.write().await;
-        let remote_db = RemoteDatabase::fetch(&conf.database_url).await?;
+        let remote_db = RemoteDatabase::fetch_from_config(&conf).await?;
         *db = remote_db;
     }
     handle.emit_all("REMOTE-REFRESH", "").ok();
@@ -168,17 +175,11 @@ pub async fn refresh_remote_db(handle: tauri::AppHandle, state: tauri::State<'_,
 
 #[tauri::command]
 pub async fn get_remote_mods(filter: &str, state: tauri::State<'_, State>) -> Result<Vec<String>> {
+    // Always lock the local db first to match the other commands
+    let local_db = state.local_db.read().await;
     let db = state.remote_db.read().await;
-    let mut mods: Vec<&RemoteMod> = db
-        .mods
-        .values()
-        .filter(|m| m.unique_name != OWML_UNIQUE_NAME)
-        .collect();
-    if filter.is_empty() {
-        mods.sort_by(|a, b| b.download_count.cmp(&a.download_count));
-    } else {
-        mods = db.search(filter);
-    }
+    let query = SearchQuery::parse(filter);
+    let mods: Vec<&RemoteMod> = db.query(&query, Some(&local_db));
     Ok(mods.into_iter().map(|m| m.unique_name.clone()).collect())
 }
 
@@ -211,13 +212,29 @@ pub async fn toggle_mod(
     state: tauri::State<'_, State>,
 ) -> Result<Vec<String>> {
     let db = state.local_db.read().await;
+    let conf = state.config.read().await;
+    let operation = if enabled {
+        Operation::Enable
+    } else {
+        Operation::Disable
+    };
+    let record = History::from_config(&conf).start(operation, FRONT_END, &db);
     let show_warnings_for = owmods_core::toggle::toggle_mod(unique_name, &db, enabled, recursive)?;
-    Ok(show_warnings_for)
+    let new_db = LocalDatabase::fetch(conf.get_owml_path())?;
+    record.finish(&new_db)?;
+    unpatch_disabled(&conf, &new_db, show_warnings_for).await
 }
 
 #[tauri::command]
 pub async fn toggle_all(enabled: bool, state: tauri::State<'_, State>) -> Result<Vec<String>> {
     let local_db = state.local_db.read().await;
+    let conf = state.config.read().await;
+    let operation = if enabled {
+        Operation::Enable
+    } else {
+        Operation::Disable
+    };
+    let record = History::from_config(&conf).start(operation, FRONT_END, &local_db);
     let mut show_warnings_for: Vec<String> = vec![];
     for local_mod in local_db.valid() {
         show_warnings_for.extend(owmods_core::toggle::toggle_mod(
@@ -227,7 +244,24 @@ pub async fn toggle_all(enabled: bool, state: tauri::State<'_, State>) -> Result
             false,
         )?);
     }
-    Ok(show_warnings_for)
+    let new_db = LocalDatabase::fetch(conf.get_owml_path())?;
+    record.finish(&new_db)?;
+    unpatch_disabled(&conf, &new_db, show_warnings_for).await
+}
+
+/// Reverse the pre-patches of mods that were just disabled, returns the mods in `show_warnings_for` that still need a warning
+async fn unpatch_disabled(
+    conf: &Config,
+    local_db: &LocalDatabase,
+    show_warnings_for: Vec<String>,
+) -> Result<Vec<String>> {
+    let unpatched = Patches::from_config(conf)
+        .unpatch_inactive(conf, local_db)
+        .await?;
+    Ok(show_warnings_for
+        .into_iter()
+        .filter(|name| !unpatched.iter().any(|p| &p.name == name))
+        .collect())
 }
 
 #[tauri::command]
@@ -255,6 +289,8 @@ pub async fn install_mod(
             return Ok(());
         }
     }
+    let mut record = History::from_config(&conf).start(Operation::Install, FRONT_END, &local_db);
+    record.preserve(unique_name)?;
     install_mod_from_db(
         &unique_name.to_string(),
         &conf,
@@ -264,6 +300,7 @@ pub async fn install_mod(
         prerelease.unwrap_or(false),
     )
     .await?;
+    record.finish(&LocalDatabase::fetch(conf.get_owml_path())?)?;
     mark_mod_busy(unique_name, false, true, &state, &handle).await;
     Ok(())
 }
@@ -272,29 +309,79 @@ pub async fn install_mod(
 pub async fn install_url(url: &str, state: tauri::State<'_, State>) -> Result {
     let conf = state.config.read().await;
     let db = state.local_db.read().await;
+    let record = History::from_config(&conf).start(Operation::Install, FRONT_END, &db);
     install_mod_from_url(url, &conf, &db).await?;
+    record.finish(&LocalDatabase::fetch(conf.get_owml_path())?)?;
     Ok(())
 }
 
 #[tauri::command]
-pub async fn install_zip(path: &str, state: tauri::State<'_, State>) -> Result {
+pub async fn install_file(path: &str, state: tauri::State<'_, State>) -> Result {
     let conf = state.config.read().await;
     let db = state.local_db.read().await;
     println!("Installing {}", path);
-    install_mod_from_zip(&PathBuf::from(path), &conf, &db)?;
+    let record = History::from_config(&conf).start(Operation::Install, FRONT_END, &db);
+    install_mod_from_file(&PathBuf::from(path), &conf, &db)?;
+    record.finish(&LocalDatabase::fetch(conf.get_owml_path())?)?;
     Ok(())
 }
 
 #[tauri::command]
 pub async fn uninstall_mod(
     unique_name: &str,
+    window: tauri::Window,
     state: tauri::State<'_, State>,
 ) -> Result<Vec<String>> {
+    let (name, addons) = {
+        let db = state.local_db.read().await;
+        let remote_db = state.remote_db.read().await;
+        let local_mod = db
+            .get_mod(unique_name)
+            .ok_or_else(|| anyhow!("Mod {} not found", unique_name))?;
+        let addons: Vec<String> = db
+            .addons(unique_name, &remote_db)
+            .map(|m| m.manifest.name.clone())
+            .collect();
+        (local_mod.manifest.name.clone(), addons)
+    };
+    if !addons.is_empty() {
+        // Don't hold the databases or a runtime thread while waiting on the user
+        let res = async_runtime::spawn_blocking(move || {
+            dialog::blocking::confirm(
+                Some(&window),
+                "Uninstall?",
+                format!(
+                    "The following addons for {} won't work without it: {}. Uninstall it anyway?",
+                    name,
+                    addons.join(", ")
+                ),
+            )
+        })
+        .await
+        .unwrap_or(false);
+        if !res {
+            return Ok(vec![]);
+        }
+    }
     let db = state.local_db.read().await;
     let local_mod = db
         .get_mod(unique_name)
         .ok_or_else(|| anyhow!("Mod {} not found", unique_name))?;
-    let warnings = remove_mod(local_mod, &db, false)?;
+    let conf = state.config.read().await;
+    let mut record = History::from_config(&conf).start(Operation::Uninstall, FRONT_END, &db);
+    record.preserve(unique_name)?;
+    let unpatched = Patches::from_config(&conf)
+        .unpatch(&conf, unique_name, Some(&db))
+        .await
+        .unwrap_or_else(|why| {
+            error!("Couldn't Unpatch {}: {:?}", local_mod.manifest.name, why);
+            false
+        });
+    let mut warnings = remove_mod(local_mod, &db, false)?;
+    if unpatched {
+        warnings.retain(|name| name != &local_mod.manifest.name);
+    }
+    record.finish(&LocalDatabase::fetch(conf.get_owml_path())?)?;
     Ok(warnings)
 }
 
@@ -391,6 +478,10 @@ pub async fn install_owml(state: tauri::State<'_, State>, handle: tauri::AppHand
         .get_owml()
         .ok_or_else(|| anyhow!("Couldn't Find OWML In The Database"))?;
     download_and_install_owml(&config, owml).await?;
+    let local_db = LocalDatabase::fetch(config.get_owml_path())?;
+    let record = History::from_config(&config).start(Operation::Install, FRONT_END, &local_db);
+    install_required_mods(&config, &db).await?;
+    record.finish(&LocalDatabase::fetch(config.get_owml_path())?)?;
     handle.emit_all("OWML_CONFIG_RELOAD", "").ok();
     Ok(())
 }
@@ -404,7 +495,7 @@ pub async fn set_owml(
     let path = Path::new(path);
     if path.is_dir() && path.join("OWML.Manifest.json").is_file() {
         let mut config = state.config.write().await;
-        config.owml_path = path.to_str().unwrap().to_string();
+        config.set_owml_path(path.to_str().unwrap());
         config.save()?;
         handle.emit_all("OWML_CONFIG_RELOAD", "").ok();
         Ok(true)
@@ -425,7 +516,7 @@ pub async fn get_updatable_mods(state: tauri::State<'_, State>) -> Result<Vec<St
             updates.push(local_mod.manifest.unique_name.clone());
         }
     }
-    if let Some(owml) = LocalDatabase::get_owml(&config.owml_path) {
+    if let Some(owml) = LocalDatabase::get_owml(config.get_owml_path()) {
         let (needs_update, _) = check_mod_needs_update(&owml, &remote_db);
         if needs_update {
             updates.push(OWML_UNIQUE_NAME.to_string());
@@ -434,9 +525,34 @@ pub async fn get_updatable_mods(state: tauri::State<'_, State>) -> Result<Vec<St
     Ok(updates)
 }
 
+/// Ask the user to confirm the updates in a plan if any of them add dependencies, conflicts, or pre-patchers
+fn confirm_update_plan(plan: &UpdatePlan, window: &tauri::Window) -> bool {
+    if !plan.needs_confirmation() {
+        return true;
+    }
+    let details = plan
+        .impacts
+        .iter()
+        .filter(|i| i.needs_confirmation())
+        .map(|i| {
+            let lines: Vec<String> = i.describe().iter().map(|l| format!("- {l}")).collect();
+            format!("{}:\n{}", i.name, lines.join("\n"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    dialog::blocking::confirm(
+        Some(window),
+        "Update?",
+        format!(
+            "These updates change more than just the mod's files:\n\n{details}\n\nUpdate anyway?"
+        ),
+    )
+}
+
 #[tauri::command]
 pub async fn update_mod(
     unique_name: &str,
+    window: tauri::Window,
     state: tauri::State<'_, State>,
     handle: tauri::AppHandle,
 ) -> Result {
@@ -454,15 +570,14 @@ pub async fn update_mod(
         )
         .await?;
     } else {
-        install_mod_from_db(
-            &unique_name.to_string(),
-            &config,
-            &remote_db,
-            &local_db,
-            false,
-            false,
-        )
-        .await?;
+        let plan = UpdatePlan::new(&[unique_name.to_string()], &local_db, &remote_db).await?;
+        if confirm_update_plan(&plan, &window) {
+            let mut record =
+                History::from_config(&config).start(Operation::Update, FRONT_END, &local_db);
+            record.preserve(unique_name)?;
+            plan.apply(&config, &local_db, &remote_db).await?;
+            record.finish(&LocalDatabase::fetch(config.get_owml_path())?)?;
+        }
     }
     toggle_fs_watch(&handle, true);
     mark_mod_busy(unique_name, false, true, &state, &handle).await;
@@ -472,6 +587,7 @@ pub async fn update_mod(
 #[tauri::command]
 pub async fn update_all_mods(
     unique_names: Vec<String>,
+    window: tauri::Window,
     state: tauri::State<'_, State>,
     handle: tauri::AppHandle,
 ) -> Result {
@@ -488,7 +604,24 @@ pub async fn update_all_mods(
     busy_mods.extend(unique_names.clone());
     drop(busy_mods);
     handle.emit_all("MOD-BUSY", "").ok();
-    install_mods_parallel(unique_names.clone(), &config, &remote_db, &local_db).await?;
+    if unique_names.iter().any(|m| m == OWML_UNIQUE_NAME) {
+        update_owml(&config, &remote_db).await?;
+    }
+    let mod_names: Vec<String> = unique_names
+        .iter()
+        .filter(|m| *m != OWML_UNIQUE_NAME)
+        .cloned()
+        .collect();
+    let plan = UpdatePlan::new(&mod_names, &local_db, &remote_db).await?;
+    if confirm_update_plan(&plan, &window) {
+        let mut record =
+            History::from_config(&config).start(Operation::Update, FRONT_END, &local_db);
+        for unique_name in mod_names.iter() {
+            record.preserve(unique_name)?;
+        }
+        plan.apply(&config, &local_db, &remote_db).await?;
+        record.finish(&LocalDatabase::fetch(config.get_owml_path())?)?;
+    }
     let mut busy_mods = state.mods_in_progress.write().await;
     busy_mods.retain(|m| !unique_names.contains(m));
     handle.emit_all("MOD-BUSY", "").ok();
@@ -501,6 +634,7 @@ pub async fn start_logs(state: tauri::State<'_, State>, handle: tauri::AppHandle
     let game_logs = state.game_log.read().await;
     let gui_config = state.gui_config.read().await;
     let config = state.config.read().await.clone();
+    patch_game(&config, &state).await?;
     if gui_config.no_log_server {
         drop(gui_config);
         launch_game(&config, true, None).await?;
@@ -519,6 +653,13 @@ pub async fn start_logs(state: tauri::State<'_, State>, handle: tauri::AppHandle
     Ok(())
 }
 
+/// Run the pre-patchers of enabled mods that haven't patched the game yet, and reverse the patches of disabled mods
+async fn patch_game(config: &Config, state: &tauri::State<'_, State>) -> Result {
+    let local_db = state.local_db.read().await;
+    Patches::from_config(config).sync(config, &local_db).await?;
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn active_log(port: LogPort, state: tauri::State<'_, State>) -> Result<bool> {
     Ok(state.game_log.read().await.get(&port).is_some())
@@ -531,6 +672,9 @@ pub async fn run_game(state: tauri::State<'_, State>, window: tauri::Window) ->
         let local_db = state.local_db.read().await;
         let new_config = show_warnings(&window, &local_db, &config)?;
         new_config.save()?;
+        Patches::from_config(&new_config)
+            .sync(&new_config, &local_db)
+            .await?;
         {
             let mut config = state.config.write().await;
             *config = new_config;
@@ -688,7 +832,9 @@ pub async fn import_mods(path: String, state: tauri::State<'_, State>) -> Result
     let remote_db = state.remote_db.read().await;
     let config = state.config.read().await;
     let path = PathBuf::from(path);
+    let record = History::from_config(&config).start(Operation::Import, FRONT_END, &local_db);
     owmods_core::io::import_mods(&config, &local_db, &remote_db, &path, false).await?;
+    record.finish(&LocalDatabase::fetch(config.get_owml_path())?)?;
     Ok(())
 }
 
@@ -721,8 +867,13 @@ pub async fn get_alert(state: tauri::State<'_, State>) -> Result<Alert> {
 #[tauri::command]
 pub async fn get_watcher_paths(state: tauri::State<'_, State>) -> Result<Vec<String>> {
     let config = state.config.read().await;
+    // The Mods folder is watched by the backend, so only watch the configs here
     Ok(vec![
-        config.owml_path.clone(),
+        PathBuf::from(config.get_owml_path())
+            .join("OWML.Config.json")
+            .to_str()
+            .unwrap()
+            .to_string(),
         GuiConfig::path().unwrap().to_str().unwrap().to_string(),
         Config::default_path()
             .unwrap()
@@ -798,3 +949,42 @@ pub async fn has_disabled_deps(unique_name: &str, state: tauri::State<'_, State>
     }
     Ok(flag)
 }
+
+#[tauri::command]
+pub async fn get_mod_size(unique_name: &str, state: tauri::State<'_, State>) -> Result<String> {
+    let db = state.local_db.read().await;
+    let local_mod = db
+        .get_mod(unique_name)
+        .ok_or_else(|| anyhow!("Mod Not Found: {unique_name}"))?;
+    Ok(format_size(local_mod.get_install_size()?))
+}
+
+#[tauri::command]
+pub async fn get_total_mods_size(state: tauri::State<'_, State>) -> Result<String> {
+    let db = state.local_db.read().await;
+    let total: u64 = db.valid().map(|m| m.get_install_size().unwrap_or(0)).sum();
+    Ok(format_size(total))
+}
+
+#[tauri::command]
+pub async fn get_mod_changelog(
+    unique_name: &str,
+    state: tauri::State<'_, State>,
+) -> Result<Vec<ChangelogEntry>> {
+    let local_db = state.local_db.read().await;
+    let remote_db = state.remote_db.read().await;
+    let local_mod = local_db
+        .get_mod(unique_name)
+        .ok_or_else(|| anyhow!("Mod {} not found", unique_name))?;
+    let remote_mod = remote_db
+        .get_mod(unique_name)
+        .ok_or_else(|| anyhow!("Mod {} not found in database", unique_name))?;
+    let entries = fetch_changelog(
+        GITHUB_API_URL,
+        remote_mod,
+        &local_mod.manifest.version,
+        local_mod.channel,
+    )
+    .await?;
+    Ok(entries)
+}
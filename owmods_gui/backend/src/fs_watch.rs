@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use log::{debug, warn};
+use owmods_core::watcher::ModsWatcher;
+use tauri::{async_runtime, AppHandle, Manager};
+
+use crate::State;
+
+const DEBOUNCE: Duration = Duration::from_millis(500);
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Start a thread that watches the Mods folder and keeps the local database live without full refreshes.
+/// The watcher is restarted whenever the OWML path changes.
+pub fn watch_mods_folder(handle: AppHandle) {
+    std::thread::spawn(move || {
+        let state = handle.state::<State>();
+        loop {
+            let owml_path = async_runtime::block_on(state.config.read())
+                .get_owml_path()
+                .clone();
+            let watcher = match ModsWatcher::new(&owml_path, DEBOUNCE) {
+                Ok(watcher) => watcher,
+                Err(why) => {
+                    warn!("Couldn't Watch Mods Folder: {:?}", why);
+                    std::thread::sleep(POLL_INTERVAL * 10);
+                    continue;
+                }
+            };
+            loop {
+                let paths = match watcher.next_batch(POLL_INTERVAL) {
+                    Ok(paths) => paths,
+                    Err(why) => {
+                        warn!("Mods Watcher Failed: {:?}", why);
+                        break;
+                    }
+                };
+                if *async_runtime::block_on(state.config.read()).get_owml_path() != owml_path {
+                    break;
+                }
+                if paths.is_empty() || !async_runtime::block_on(state.gui_config.read()).watch_fs {
+                    continue;
+                }
+                let changes = async_runtime::block_on(async {
+                    let mut local_db = state.local_db.write().await;
+                    let remote_db = state.remote_db.read().await;
+                    let changes = local_db.apply_changes(watcher.mods_path(), &paths);
+                    if !changes.is_empty() {
+                        local_db.validate_updates(&remote_db);
+                    }
+                    changes
+                });
+                if !changes.is_empty() {
+                    debug!("Local Database Changed: {:?}", changes);
+                    handle.emit_all("LOCAL-CHANGE", &changes).ok();
+                    handle.emit_all("LOCAL-REFRESH", "").ok();
+                }
+            }
+        }
+    });
+}
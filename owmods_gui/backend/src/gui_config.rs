@@ -1,7 +1,11 @@
 use std::path::PathBuf;
 
-use owmods_core::file::{deserialize_from_json, get_app_path, serialize_to_json};
+use owmods_core::{
+    file::get_app_path,
+    versioned::{current_version, load_versioned, save_versioned, Loaded, Migration},
+};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use typeshare::typeshare;
 
 #[typeshare]
@@ -36,10 +40,21 @@ const fn _default_false() -> bool {
     false
 }
 
+/// GUI configs from before they were versioned only differ by not having a version
+fn migrate_v0(_: &mut Map<String, Value>) -> Result<(), anyhow::Error> {
+    Ok(())
+}
+
+const MIGRATIONS: &[Migration] = &[migrate_v0];
+
+pub const GUI_CONFIG_VERSION: u32 = current_version(MIGRATIONS);
+
 #[typeshare]
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct GuiConfig {
+    #[serde(default)]
+    version: u32,
     #[serde(default = "Theme::default")]
     theme: Theme,
     #[serde(default = "_default_false")]
@@ -47,7 +62,7 @@ pub struct GuiConfig {
     #[serde(default = "Language::default")]
     language: Language,
     #[serde(default = "_default_true")]
-    watch_fs: bool,
+    pub watch_fs: bool,
     #[serde(default = "_default_false")]
     no_warning: bool,
     #[serde(default = "_default_false")]
@@ -61,6 +76,7 @@ pub struct GuiConfig {
 impl Default for GuiConfig {
     fn default() -> Self {
         Self {
+            version: GUI_CONFIG_VERSION,
             theme: Theme::default(),
             rainbow: false,
             language: Language::default(),
@@ -79,25 +95,15 @@ impl GuiConfig {
         Ok(path)
     }
 
-    fn read() -> Result<Self, anyhow::Error> {
-        deserialize_from_json::<GuiConfig>(&Self::path()?)
-    }
-
-    fn write(config: &Self) -> Result<(), anyhow::Error> {
-        serialize_to_json(config, &Self::path()?, true)
+    pub fn load() -> Result<Loaded<Self>, anyhow::Error> {
+        load_versioned(&Self::path()?, MIGRATIONS, || Ok(Self::default()))
     }
 
     pub fn get() -> Result<Self, anyhow::Error> {
-        if Self::path()?.is_file() {
-            Self::read()
-        } else {
-            let new = Self::default();
-            Self::write(&new)?;
-            Ok(new)
-        }
+        Ok(Self::load()?.value)
     }
 
     pub fn save(&self) -> Result<(), anyhow::Error> {
-        Self::write(self)
+        save_versioned(self, &Self::path()?, GUI_CONFIG_VERSION)
     }
 }
@@ -6,9 +6,10 @@
 use std::{collections::HashMap, error::Error, fs::File, io::BufWriter, sync::Arc};
 
 use commands::*;
+use fs_watch::watch_mods_folder;
 use game::GameMessage;
 use gui_config::GuiConfig;
-use log::{debug, set_boxed_logger, set_max_level, warn};
+use log::{debug, error, set_boxed_logger, set_max_level, warn};
 use logging::Logger;
 use owmods_core::{
     config::Config,
@@ -17,10 +18,14 @@ use owmods_core::{
 
 use progress::ProgressBars;
 use protocol::{ProtocolInstallType, ProtocolPayload};
-use tauri::Manager;
+use tauri::{
+    api::dialog::{MessageDialogBuilder, MessageDialogKind},
+    Manager,
+};
 use tokio::sync::RwLock as TokioLock;
 
 mod commands;
+mod fs_watch;
 mod game;
 mod gui_config;
 mod logging;
@@ -55,9 +60,31 @@ pub struct State {
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let config = Config::get(None).unwrap_or(Config::default(None)?);
-    let gui_config = GuiConfig::get().unwrap_or_default();
-    let local_db = LocalDatabase::fetch(&config.owml_path).unwrap_or_default();
+    // Problems with the settings files can't be logged or shown until the app is set up
+    let mut config_problems = vec![];
+    // If a settings file can't be loaded we can't run on the defaults, saving them would replace the user's settings
+    let mut load_error = None;
+    let config = match Config::load(None) {
+        Ok(loaded) => {
+            config_problems.extend(loaded.recovery.map(|r| r.to_string()));
+            loaded.value
+        }
+        Err(why) => {
+            load_error = Some(format!("Couldn't load settings: {why}"));
+            Config::default(None)?
+        }
+    };
+    let gui_config = match GuiConfig::load() {
+        Ok(loaded) => {
+            config_problems.extend(loaded.recovery.map(|r| r.to_string()));
+            loaded.value
+        }
+        Err(why) => {
+            load_error.get_or_insert(format!("Couldn't load GUI settings: {why}"));
+            GuiConfig::default()
+        }
+    };
+    let local_db = LocalDatabase::fetch(config.get_owml_path()).unwrap_or_default();
     let remote_db = RemoteDatabase::default();
 
     tauri_plugin_deep_link::prepare("com.bwc9876.owmods-gui");
@@ -88,6 +115,22 @@ fn main() -> Result<(), Box<dyn Error>> {
                 .ok();
             set_boxed_logger(Box::new(logger)).map(|_| set_max_level(log::LevelFilter::Debug))?;
 
+            if let Some(why) = load_error {
+                error!("{}", why);
+                if let Some(window) = app.get_window("main") {
+                    window.hide().ok();
+                }
+                let handle = app.handle();
+                MessageDialogBuilder::new("Couldn't Load Settings", why)
+                    .kind(MessageDialogKind::Error)
+                    .show(move |_| handle.exit(1));
+                return Ok(());
+            }
+
+            for problem in config_problems.iter() {
+                warn!("{}", problem);
+            }
+
             let handle = app.handle();
 
             let res = tauri_plugin_deep_link::register("owmods", move |request| {
@@ -108,6 +151,8 @@ fn main() -> Result<(), Box<dyn Error>> {
                 warn!("Failed to register URI handler: {:?}", why);
             }
 
+            watch_mods_folder(app.handle());
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -125,7 +170,7 @@ fn main() -> Result<(), Box<dyn Error>> {
             uninstall_broken_mod,
             install_mod,
             install_url,
-            install_zip,
+            install_file,
             open_mod_readme,
             save_config,
             get_config,
@@ -156,7 +201,10 @@ fn main() -> Result<(), Box<dyn Error>> {
             get_downloads,
             clear_downloads,
             get_mod_busy,
-            has_disabled_deps
+            has_disabled_deps,
+            get_mod_size,
+            get_total_mods_size,
+            get_mod_changelog
         ])
         .plugin(tauri_plugin_window_state::Builder::default().build())
         .plugin(tauri_plugin_fs_watch::init())
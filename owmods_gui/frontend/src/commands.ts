@@ -8,7 +8,8 @@ import {
     GameMessage,
     UnsafeLocalMod,
     Alert,
-    ProgressBars
+    ProgressBars,
+    ChangelogEntry
 } from "@types";
 
 type CommandInfo<P, R> = [P, R];
@@ -48,7 +49,7 @@ const commandInfo = {
     uninstallBrokenMod: $<ActionCommand<{ modPath: string }>>("uninstall_broken_mod"),
     installMod: $<CommandInfo<{ uniqueName: string; prerelease?: boolean }, void>>("install_mod"),
     installUrl: $<ActionCommand<{ url: string }>>("install_url"),
-    installZip: $<ActionCommand<{ path: string }>>("install_zip"),
+    installFile: $<ActionCommand<{ path: string }>>("install_file"),
     installOwml: $<EmptyCommand>("install_owml"),
     setOwml: $<CommandInfo<{ path: string }, boolean>>("set_owml"),
     saveConfig: $<ActionCommand<{ config: Config }>>("save_config"),
@@ -81,7 +82,10 @@ const commandInfo = {
     getDownloads: $<GetCommand<ProgressBars>>("get_downloads"),
     clearDownloads: $<EmptyCommand>("clear_downloads"),
     getModBusy: $<ModCommand<boolean>>("get_mod_busy"),
-    hasDisabledDeps: $<ModCommand<boolean>>("has_disabled_deps")
+    hasDisabledDeps: $<ModCommand<boolean>>("has_disabled_deps"),
+    getModSize: $<ModCommand<string>>("get_mod_size"),
+    getTotalModsSize: $<GetCommand<string>>("get_total_mods_size"),
+    getModChangelog: $<ModCommand<ChangelogEntry[]>>("get_mod_changelog")
 };
 
 type Command = keyof typeof commandInfo;
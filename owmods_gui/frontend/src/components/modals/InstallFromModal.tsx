@@ -63,7 +63,7 @@ const InstallFromModal = forwardRef(function InstallFromModal(_: object, ref) {
                 break;
             case "ZIP":
                 commands
-                    .installZip({ path: target })
+                    .installFile({ path: target })
                     .then(() => commands.refreshLocalDb())
                     .catch(console.error);
                 break;
@@ -136,7 +136,10 @@ const InstallFromModal = forwardRef(function InstallFromModal(_: object, ref) {
                             filters: [
                                 {
                                     name: lblMap[source],
-                                    extensions: [source === "ZIP" ? "zip" : "json"]
+                                    extensions:
+                                        source === "ZIP"
+                                            ? ["zip", "gz", "tgz", "zst", "7z"]
+                                            : ["json"]
                                 }
                             ],
                             directory: false,
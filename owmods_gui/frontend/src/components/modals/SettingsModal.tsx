@@ -9,7 +9,7 @@ import {
     useRef,
     useState
 } from "react";
-import { Config, GuiConfig, Language, OWMLConfig, Theme } from "@types";
+import { Config, Game, GuiConfig, Language, OWMLConfig, Theme } from "@types";
 import Modal, { ModalHandle } from "./Modal";
 import { useGetTranslation } from "@hooks";
 import { commands, hooks } from "@commands";
@@ -21,6 +21,8 @@ import { os } from "@tauri-apps/api";
 
 const ThemeArr = Object.values(Theme);
 const LanguageArr = Object.values(Language);
+const GameArr = Object.values(Game);
+const DefaultInstance = "default";
 
 interface SettingsFormProps {
     initialConfig: Config;
@@ -193,14 +195,21 @@ const SettingsForm = forwardRef(function SettingsForm(props: SettingsFormProps,
                 const task = async () => {
                     await commands.saveConfig({ config });
                     await commands.saveGuiConfig({ guiConfig });
-                    if (config.owmlPath !== props.initialConfig.owmlPath) {
+                    const targetChanged =
+                        config.game !== props.initialConfig.game ||
+                        config.instance !== props.initialConfig.instance;
+                    if (config.databaseUrl !== props.initialConfig.databaseUrl || targetChanged) {
+                        await commands.refreshRemoteDb();
+                    }
+                    if (
+                        config.owmlPath !== props.initialConfig.owmlPath ||
+                        config.alpha.owmlPath !== props.initialConfig.alpha.owmlPath ||
+                        targetChanged
+                    ) {
                         await commands.refreshLocalDb();
                     } else {
                         await commands.saveOwmlConfig({ owmlConfig });
                     }
-                    if (config.databaseUrl !== props.initialConfig.databaseUrl) {
-                        await commands.refreshRemoteDb();
-                    }
                 };
                 task().catch(console.error);
             },
@@ -233,6 +242,15 @@ const SettingsForm = forwardRef(function SettingsForm(props: SettingsFormProps,
         setConfig({ ...config, [e.target.id]: getVal(e.target) });
     };
 
+    const handleInstance = (e: ChangeEvent<HTMLSelectElement>) => {
+        const instance = e.target.value === DefaultInstance ? undefined : e.target.value;
+        setConfig({ ...config, instance });
+    };
+
+    const handleAlpha = (e: ChangeEvent<HTMLInputElement | HTMLSelectElement>) => {
+        setConfig({ ...config, alpha: { ...config.alpha, [e.target.id]: getVal(e.target) } });
+    };
+
     const handleOwml = (e: ChangeEvent<HTMLInputElement | HTMLSelectElement>) => {
         setOwmlConfig({ ...owmlConfig, [e.target.id]: getVal(e.target) });
     };
@@ -362,6 +380,16 @@ const SettingsForm = forwardRef(function SettingsForm(props: SettingsFormProps,
                 id="alertUrl"
                 tooltip={getTranslation("TOOLTIP_ALERT_URL")}
             />
+            <SettingsSelect
+                onChange={handleInstance}
+                value={config.instance ?? DefaultInstance}
+                translate={false}
+                label={getTranslation("INSTANCE")}
+                options={[DefaultInstance, ...config.instances.map((i) => i.name)]}
+                id="instance"
+                tooltip={getTranslation("TOOLTIP_INSTANCE")}
+                tooltipPlacement="top"
+            />
             <SettingsFolder
                 onChange={handleConf}
                 value={config.owmlPath}
@@ -370,6 +398,44 @@ const SettingsForm = forwardRef(function SettingsForm(props: SettingsFormProps,
                 tooltip={getTranslation("TOOLTIP_OWML_PATH")}
                 tooltipPlacement="top"
             />
+            <SettingsText
+                onChange={handleConf}
+                value={config.gameRunner ?? ""}
+                label={getTranslation("GAME_RUNNER")}
+                id="gameRunner"
+                tooltip={getTranslation("TOOLTIP_GAME_RUNNER")}
+                tooltipPlacement="top"
+            />
+            <SettingsSelect
+                onChange={handleConf}
+                value={config.game}
+                translate
+                label={getTranslation("GAME")}
+                options={GameArr}
+                id="game"
+                tooltip={getTranslation("TOOLTIP_GAME")}
+                tooltipPlacement="top"
+            />
+            {config.game === Game.Alpha && (
+                <>
+                    <SettingsFolder
+                        onChange={handleAlpha}
+                        value={config.alpha.owmlPath}
+                        label={getTranslation("ALPHA_OWML_PATH")}
+                        id="owmlPath"
+                        tooltip={getTranslation("TOOLTIP_ALPHA_OWML_PATH")}
+                        tooltipPlacement="top"
+                    />
+                    <SettingsFolder
+                        onChange={handleAlpha}
+                        value={config.alpha.gamePath}
+                        label={getTranslation("ALPHA_GAME_PATH")}
+                        id="gamePath"
+                        tooltip={getTranslation("TOOLTIP_ALPHA_GAME_PATH")}
+                        tooltipPlacement="top"
+                    />
+                </>
+            )}
         </form>
     );
 });
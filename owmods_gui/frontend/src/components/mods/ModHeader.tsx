@@ -4,6 +4,7 @@ export interface ModHeaderProps {
     children: ReactNode;
     name: string;
     subtitle: string;
+    badge?: string;
 }
 
 const ModHeader = (props: ModHeaderProps) => {
@@ -11,6 +12,7 @@ const ModHeader = (props: ModHeaderProps) => {
         <summary className="mod-header">
             <div className="mod-heading">
                 <span className="mod-name">{props.name}</span>
+                {props.badge && <span className="mod-badge">{props.badge}</span>}
                 <small className="mod-authors">{props.subtitle}</small>
             </div>
             <div className="mod-actions">{props.children}</div>
@@ -16,6 +16,7 @@ interface LocalModRowProps {
     description?: string;
     readme?: boolean;
     subtitle?: string;
+    badge?: string;
     enabled?: boolean;
     onValidationClick?: (p: ModValidationError[]) => void;
     onToggle?: (newState: boolean) => void;
@@ -32,7 +33,7 @@ const LocalModRow = memo(function LocalModRow(props: LocalModRowProps) {
 
     return (
         <div className="mod-row local">
-            <ModHeader name={props.name} subtitle={props.subtitle ?? ""}>
+            <ModHeader name={props.name} subtitle={props.subtitle ?? ""} badge={props.badge}>
                 {props.showValidation && (
                     <LocalModValidationIcon
                         onClickProp={props.onValidationClick}
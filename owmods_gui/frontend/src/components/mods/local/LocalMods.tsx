@@ -15,6 +15,7 @@ const LocalMods = memo(function LocalMods() {
     const [tempFilter, setTempFilter] = useState("");
     const activeTimeout = useRef<number | undefined>(undefined);
     const [status, mods, err] = hooks.getLocalMods("LOCAL-REFRESH", { filter });
+    const totalSize = hooks.getTotalModsSize("LOCAL-REFRESH")[1];
     const getTranslation = useGetTranslation();
 
     useEffect(() => {
@@ -71,6 +72,11 @@ const LocalMods = memo(function LocalMods() {
                             onChange={(e) => onSearch(e.target.value)}
                         />
                         <div className="gap" />
+                        {totalSize && (
+                            <small className="local-mods-size muted">
+                                {getTranslation("TOTAL_SIZE", { size: totalSize })}
+                            </small>
+                        )}
                         <div className="local-mods-buttons">
                             <button onClick={() => onToggleAll(false)} className="secondary">
                                 {getTranslation("DISABLE_ALL")}
@@ -2,7 +2,7 @@ import { commands, hooks } from "@commands";
 import { OpenModValidationModalPayload } from "@components/modals/ModValidationModal";
 import { useGetTranslation } from "@hooks";
 import { confirm } from "@tauri-apps/api/dialog";
-import { LocalMod, ModValidationError } from "@types";
+import { LocalMod, ModValidationError, ReleaseChannel } from "@types";
 import { memo, useCallback } from "react";
 import LocalModRow from "./LocalModRow";
 import { dialog } from "@tauri-apps/api";
@@ -25,11 +25,15 @@ const ValidModRow = memo(function ValidModRow({ mod, onValidationClick }: LocalM
         name: mod.manifest.name
     });
 
-    const subtitle = getTranslation("BY", {
+    const size = hooks.getModSize("LOCAL-REFRESH", { uniqueName: mod.manifest.uniqueName })[1];
+
+    const byLine = getTranslation("BY", {
         author: mod.manifest.author,
         version: mod.manifest.version
     });
 
+    const subtitle = size ? `${byLine} (${size})` : byLine;
+
     const onValidationClicked = useCallback(
         (errs: ModValidationError[]) => {
             onValidationClick?.({
@@ -106,6 +110,11 @@ const ValidModRow = memo(function ValidModRow({ mod, onValidationClick }: LocalM
             readme
             errors={mod.errors}
             subtitle={subtitle}
+            badge={
+                mod.channel === ReleaseChannel.Prerelease
+                    ? getTranslation("PRERELEASE")
+                    : undefined
+            }
             onOpen={onOpen}
             onToggle={onToggle}
             onUninstall={onUninstall}
@@ -95,6 +95,14 @@ const RemoteModRow = memo(function RemoteModRow(props: RemoteModRowProps) {
         return (
             <div>
                 <ModHeader {...remoteMod} subtitle={subtitle}>
+                    {remoteMod.source && (
+                        <small
+                            className="mod-source"
+                            title={getTranslation("FROM_SOURCE", { source: remoteMod.source })}
+                        >
+                            {remoteMod.source}
+                        </small>
+                    )}
                     <small>{formatNumber(remoteMod.downloadCount)}</small>
                     {busy ? (
                         <div className="center" aria-busy></div>
@@ -2,16 +2,59 @@ import { commands, hooks } from "@commands";
 import CenteredSpinner from "@components/common/CenteredSpinner";
 import Icon from "@components/common/Icon";
 import { useGetTranslation } from "@hooks";
-import { memo, useCallback, useMemo } from "react";
+import { SyntheticEvent, memo, useCallback, useMemo, useState } from "react";
 import { BsArrowUp } from "react-icons/bs";
 import ModActionButton from "../ModActionButton";
 import ModHeader from "../ModHeader";
-import { LocalMod } from "@types";
+import { ChangelogEntry, LocalMod } from "@types";
 
 export interface UpdateModRowProps {
     uniqueName: string;
 }
 
+const Changelog = memo(function Changelog({ uniqueName }: UpdateModRowProps) {
+    const getTranslation = useGetTranslation();
+    const [entries, setEntries] = useState<ChangelogEntry[] | null>(null);
+    const [error, setError] = useState<string | null>(null);
+
+    // Only fetch when opened, GitHub rate limits the releases API
+    const onToggle = useCallback(
+        (e: SyntheticEvent<HTMLDetailsElement>) => {
+            if (e.currentTarget.open && entries === null) {
+                commands
+                    .getModChangelog({ uniqueName })
+                    .then(setEntries)
+                    .catch((e) => setError(e.toString()));
+            }
+        },
+        [uniqueName, entries]
+    );
+
+    return (
+        <details className="mod-changelog" onToggle={onToggle}>
+            <summary>{getTranslation("CHANGELOG")}</summary>
+            {error !== null ? (
+                <p>{error}</p>
+            ) : entries === null ? (
+                <CenteredSpinner />
+            ) : entries.length === 0 ? (
+                <p>{getTranslation("NO_CHANGELOG")}</p>
+            ) : (
+                entries.map((entry) => (
+                    <div key={entry.version}>
+                        <h6>
+                            <a href={entry.url} target="_blank" rel="noreferrer">
+                                {entry.title}
+                            </a>
+                        </h6>
+                        <p className="changelog-body">{entry.body}</p>
+                    </div>
+                ))
+            )}
+        </details>
+    );
+});
+
 const UpdateModRow = memo(
     function UpdateModRow({ uniqueName }: UpdateModRowProps) {
         const getTranslation = useGetTranslation();
@@ -26,9 +69,14 @@ const UpdateModRow = memo(
         // Assertion is safe bc we're only iterating over valid mods
         const localMod = unsafeLocalMod?.mod as LocalMod | null;
 
+        // The backend already worked out which version the mod's channel updates to
+        const targetVersion =
+            localMod?.errors.find((e) => e.errorType === "Outdated")?.payload ??
+            remoteMod?.version;
+
         const subtitleString = useMemo(
-            () => `${localMod?.manifest.version ?? "v0"} 🡢 ${remoteMod?.version ?? "v0"}`,
-            [remoteMod, localMod]
+            () => `${localMod?.manifest.version ?? "v0"} 🡢 ${targetVersion ?? "v0"}`,
+            [targetVersion, localMod]
         );
 
         const status = [remoteStatus, localStatus];
@@ -65,6 +113,7 @@ const UpdateModRow = memo(
                             </ModActionButton>
                         )}
                     </ModHeader>
+                    <Changelog uniqueName={uniqueName} />
                 </div>
             );
         }
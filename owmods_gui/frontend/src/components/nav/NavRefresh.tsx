@@ -8,12 +8,10 @@ import { watchImmediate } from "tauri-plugin-fs-watch-api";
 import { listen } from "@tauri-apps/api/event";
 import { IconContext } from "react-icons";
 
+// Changes to mods are picked up by the backend, this only needs to catch config changes
 const checkPaths = (paths: string[]) => {
     for (const path of paths) {
         if (
-            path.endsWith("config.json") ||
-            path.endsWith("manifest.json") ||
-            path.endsWith(".dll") ||
             path.endsWith("OWML.Config.json") ||
             path.endsWith("settings.json") ||
             path.endsWith("gui_settings.json")
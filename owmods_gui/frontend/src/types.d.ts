@@ -11,12 +11,73 @@ export interface Alert {
     message?: string;
 }
 
+/** The release notes for one version of a mod */
+export interface ChangelogEntry {
+    version: string;
+    /** The title of the release, falls back to the version if the release doesn't have one */
+    title: string;
+    /** The release notes, usually markdown */
+    body: string;
+    /** A link to the release on GitHub */
+    url: string;
+    /** When the release was published, as an RFC 3339 timestamp */
+    publishedAt?: string;
+    prerelease: boolean;
+}
+
+/** Where OWML and the game are for Outer Wilds Alpha */
+export interface AlphaConfig {
+    owmlPath: string;
+    /** Where Outer Wilds Alpha is installed, this is written to OWML's config when OWML is installed for Alpha */
+    gamePath: string;
+}
+
+/**
+ * A named install of OWML and the game, so multiple copies of the game can be modded side by side.
+ * The instance made from [Config::owml_path], [Config::game] and [Config::alpha] is called `default`.
+ */
+export interface Instance {
+    name: string;
+    owmlPath: string;
+    /** Where the game is installed, this is written to the instance's OWML config. Leave empty to use the game path already in OWML's config */
+    gamePath: string;
+    /** Overrides [Config::game_runner] for this instance */
+    gameRunner?: string;
+    game: Game;
+}
+
 /** Represents the core config, contains critical info needed by the core API */
+/** An extra database to get mods from, on top of the one in `database_url` */
+export interface DatabaseSource {
+    /** A name for the source, shown on mods that come from it */
+    name: string;
+    /** The URL or local file path of the database */
+    url: string;
+    /** When multiple sources have the same mod the one with the highest priority is used, the default database has a priority of `0` */
+    priority: number;
+    /** Whether to use this source */
+    enabled: boolean;
+}
+
 export interface Config {
+    /** The version of the config's format, configs from older versions are migrated when they're loaded */
+    version: number;
+    /** The OWML install for Outer Wilds, use [Config::get_owml_path] to get the one for the selected game */
     owmlPath: string;
     databaseUrl: string;
+    databaseSources: DatabaseSource[];
     alertUrl: string;
     viewedAlerts: string[];
+    /** Which game to manage mods for, this decides which OWML install and which mods in the database are used */
+    game: Game;
+    alpha: AlphaConfig;
+    /** A program to run OWML and pre-patchers with, like `wine`. By default they're run directly on Windows and with `mono` everywhere else */
+    gameRunner?: string;
+    instances: Instance[];
+    /** The name of the instance to use, `None` for the default instance. See [Config::get_instance] */
+    instance?: string;
+    /** OWML installs that already got their mods' enabled states from the old manager, see [crate::migrate::migrate_old_manager] */
+    migratedOwmlPaths: string[];
 }
 
 /** Represents an installed (and valid) mod */
@@ -25,6 +86,9 @@ export interface LocalMod {
     errors: ModValidationError[];
     modPath: string;
     manifest: ModManifest;
+    linked: boolean;
+    installReason: InstallReason;
+    channel: ReleaseChannel;
 }
 
 /** Represents a mod that completely failed to load */
@@ -73,6 +137,8 @@ export interface RemoteMod {
     prerelease?: ModPrerelease;
     alpha?: boolean;
     tags?: string[];
+    /** The name of the [crate::config::DatabaseSource] this mod came from, `None` if it came from the default database */
+    source?: string;
 }
 
 /** A prerelease for a mod */
@@ -105,12 +171,42 @@ export interface SocketMessage {
     messageType: SocketMessageType;
 }
 
+/** How a list in a mod's manifest changed in an update */
+export interface ListDiff {
+    added: string[];
+    removed: string[];
+}
+
+/** How a value in a mod's manifest changed in an update */
+export interface ValueDiff {
+    old?: string;
+    new?: string;
+}
+
+/** What updating a mod will change besides its files, found by comparing the installed manifest to the one in the downloaded update */
+export interface UpdateImpact {
+    uniqueName: string;
+    name: string;
+    oldVersion: string;
+    newVersion: string;
+    dependencies: ListDiff;
+    conflicts: ListDiff;
+    pathsToPreserve: ListDiff;
+    owmlVersion?: ValueDiff;
+    patcher?: ValueDiff;
+    /** New dependencies that aren't installed, these are installed along with the update */
+    missingDependencies: string[];
+    /** Enabled mods that the update adds a conflict with */
+    newConflicts: string[];
+}
+
 export interface GameMessage {
     port: LogPort;
     message: SocketMessage;
 }
 
 export interface GuiConfig {
+    version: number;
     theme: Theme;
     rainbow: boolean;
     language: Language;
@@ -161,6 +257,29 @@ export type UnsafeLocalMod =
     | { loadState: "valid"; mod: LocalMod }
     | { loadState: "invalid"; mod: FailedMod };
 
+/** Which game mods are managed for */
+export enum Game {
+    OuterWilds = "OuterWilds",
+    /** Outer Wilds Alpha, it has its own mods in the database and needs its own OWML install */
+    Alpha = "Alpha"
+}
+
+/** Why a mod was installed, used to find dependencies that nothing needs anymore */
+export enum InstallReason {
+    /** The user asked for this mod, mods installed before we tracked this are treated as explicit so they're never removed automatically */
+    Explicit = "Explicit",
+    /** The mod was only installed because another mod depends on it, or it's the parent of an addon */
+    Dependency = "Dependency"
+}
+
+/** Which releases of a mod to install and update to */
+export enum ReleaseChannel {
+    /** Only use the release in the database */
+    Stable = "Stable",
+    /** Use the mod's prerelease when it has one that's newer than the release */
+    Prerelease = "Prerelease"
+}
+
 /** Represents the type of message sent from the game */
 export enum SocketMessageType {
     Message = "message",
@@ -173,6 +292,15 @@ export enum SocketMessageType {
     Debug = "debug"
 }
 
+/** Represents a change made to a [crate::db::LocalDatabase] after re-reading mods from the disk */
+export type LocalDatabaseChange =
+    /** A new mod was found, contains the unique name of the mod */
+    | { changeType: "Added"; payload: string }
+    /** An existing mod's manifest or config changed, contains the unique name of the mod */
+    | { changeType: "Updated"; payload: string }
+    /** A mod was deleted, contains the unique name of the mod */
+    | { changeType: "Removed"; payload: string };
+
 /** Represents an error with a [LocalMod] */
 export type ModValidationError =
     /** The mod's manifest was invalid, contains the error encountered when loading it */
@@ -188,7 +316,9 @@ export type ModValidationError =
     /** There's another mod already in the DB with this mod's unique name, contains the path of the other mod that has the same unique name */
     | { errorType: "DuplicateMod"; payload: string }
     /** The mod is outdated, contains the newest version */
-    | { errorType: "Outdated"; payload: string };
+    | { errorType: "Outdated"; payload: string }
+    /** The mod's version couldn't be parsed so we can't tell if it's outdated, contains the version */
+    | { errorType: "UnparseableVersion"; payload: string };
 
 export enum Theme {
     White = "White",